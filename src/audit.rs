@@ -0,0 +1,286 @@
+use chrono::{DateTime, Local};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use anyhow::Result;
+use crate::export::is_secret_key;
+use crate::storage;
+
+const AUDIT_FILE: &str = "audit.jsonl";
+const MASK: &str = "******";
+const DEFAULT_LIMIT: usize = 100;
+// audit.jsonl 超过该大小后轮转为 audit.jsonl.1，最多保留 ROTATED_FILES 个旧文件
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+const ROTATED_FILES: usize = 5;
+// 地址查询参数中，除名称敏感的参数外还需要隐藏的签名类参数
+const URL_SECRET_PARAMS: &[&str] = &["key", "sig", "signature"];
+
+lazy_static! {
+    // YAML 的 `KEY: value`、`- KEY=value` 和环境变量的 `KEY=value`
+    static ref ASSIGNMENT: Regex =
+        Regex::new(r#"^(\s*(?:-\s*)?["']?([A-Za-z_][A-Za-z0-9_.-]*)["']?\s*[:=]\s*)(\S.*)$"#).unwrap();
+    // 地址中的 `?token=...`、`&access_token=...` 等查询参数
+    static ref QUERY_PARAM: Regex = Regex::new(r"([?&]([^=&#\s]+)=)([^&#\s]*)").unwrap();
+    // 地址中的 `user:password@`
+    static ref URL_PASSWORD: Regex = Regex::new(r"(://[^/\s:@]+:)[^/\s@]+@").unwrap();
+}
+
+/// 管理器自动执行的操作使用的操作者名称
+pub const MONITOR_ACTOR: &str = "monitor";
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSource {
+    /// 通过 HTTP 接口发起
+    Api,
+    /// 管理器自动执行，如崩溃重启、自动更新
+    Monitor,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditResult {
+    Success,
+    Failure,
+}
+
+/// 一条审计记录
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Local>,
+    pub source: AuditSource,
+    /// 接口调用为代理传入的用户名或客户端地址，自动操作为 `monitor`
+    pub actor: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// 接口调用为 `方法 路由`，如 `DELETE /api/container/:id`
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// 请求参数，敏感信息已隐藏
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+    pub result: AuditResult,
+    /// 接口返回的状态码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    /// 通过接口发起的操作，`error` 为空表示成功
+    pub fn api(actor: String, address: Option<String>, action: String, target: Option<String>, mut params: Value, status: u16, error: Option<String>) -> Self {
+        redact(&mut params);
+        Self {
+            timestamp: Local::now(),
+            source: AuditSource::Api,
+            actor,
+            address,
+            action,
+            target,
+            params,
+            result: if error.is_none() { AuditResult::Success } else { AuditResult::Failure },
+            status: Some(status),
+            error,
+        }
+    }
+
+    /// 管理器自动执行的操作
+    pub fn monitor<T, E: std::fmt::Display>(action: &str, target: &str, mut params: Value, result: &std::result::Result<T, E>) -> Self {
+        redact(&mut params);
+        Self {
+            timestamp: Local::now(),
+            source: AuditSource::Monitor,
+            actor: MONITOR_ACTOR.to_string(),
+            address: None,
+            action: action.to_string(),
+            target: Some(target.to_string()),
+            params,
+            result: if result.is_ok() { AuditResult::Success } else { AuditResult::Failure },
+            status: None,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        }
+    }
+}
+
+/// 审计记录的查询条件
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    pub source: Option<AuditSource>,
+    /// 操作中包含该文本即匹配
+    pub action: Option<String>,
+    /// 操作对象中包含该文本即匹配
+    pub target: Option<String>,
+    pub result: Option<AuditResult>,
+    pub since: Option<DateTime<Local>>,
+    pub until: Option<DateTime<Local>>,
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.actor.as_ref().is_none_or(|actor| entry.actor == *actor)
+            && self.source.is_none_or(|source| entry.source == source)
+            && self.action.as_ref().is_none_or(|action| entry.action.contains(action.as_str()))
+            && self.target.as_ref().is_none_or(|target| {
+                entry.target.as_ref().is_some_and(|t| t.contains(target.as_str()))
+            })
+            && self.result.is_none_or(|result| entry.result == result)
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+    }
+}
+
+/// 只追加的审计日志，每行一条 JSON，保存在数据目录下的 audit.jsonl，超过大小后轮转
+pub struct AuditLog {
+    // 保证多条记录不会交错写入，也不会与轮转同时进行
+    lock: Mutex<()>,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self { lock: Mutex::new(()) }
+    }
+
+    /// 追加一条记录；写入失败只打印错误，不影响操作本身
+    pub fn record(&self, entry: AuditEntry) {
+        let _guard = self.lock.lock().unwrap();
+        let result = serde_json::to_string(&entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| storage::append_line(AUDIT_FILE, &line));
+        if let Err(e) = result {
+            eprintln!("写入审计日志失败: {}", e);
+            return;
+        }
+        let size = std::fs::metadata(storage::data_path(AUDIT_FILE)).map(|m| m.len()).unwrap_or(0);
+        if size > MAX_FILE_SIZE {
+            if let Err(e) = Self::rotate() {
+                eprintln!("轮转审计日志失败: {}", e);
+            }
+        }
+    }
+
+    // audit.jsonl.N 依次改名为 N+1，最旧的一个被删除
+    fn rotate() -> Result<()> {
+        let oldest = Self::rotated_path(ROTATED_FILES);
+        if oldest.exists() {
+            std::fs::remove_file(&oldest)?;
+        }
+        for index in (1..ROTATED_FILES).rev() {
+            let path = Self::rotated_path(index);
+            if path.exists() {
+                std::fs::rename(&path, Self::rotated_path(index + 1))?;
+            }
+        }
+        std::fs::rename(storage::data_path(AUDIT_FILE), Self::rotated_path(1))?;
+        println!("审计日志已轮转");
+        Ok(())
+    }
+
+    fn rotated_path(index: usize) -> PathBuf {
+        storage::data_path(&format!("{}.{}", AUDIT_FILE, index))
+    }
+
+    /// 按条件查询，最新的记录在前
+    pub async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+        let mut entries = Self::matching(query).await?;
+        entries.reverse();
+        entries.truncate(query.limit.unwrap_or(DEFAULT_LIMIT));
+        Ok(entries)
+    }
+
+    /// 按时间顺序导出为 JSONL，只有指定 limit 时才限制条数（保留最新的）
+    pub async fn export(&self, query: &AuditQuery) -> Result<String> {
+        let mut entries = Self::matching(query).await?;
+        if let Some(limit) = query.limit {
+            entries.drain(..entries.len().saturating_sub(limit));
+        }
+        let mut output = String::new();
+        for entry in entries {
+            output.push_str(&serde_json::to_string(&entry)?);
+            output.push('\n');
+        }
+        Ok(output)
+    }
+
+    // 在阻塞线程中按时间顺序读取轮转的旧文件和当前文件，不持有写入锁
+    async fn matching(query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+        let query = query.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut paths: Vec<PathBuf> = (1..=ROTATED_FILES).rev().map(Self::rotated_path).collect();
+            paths.push(storage::data_path(AUDIT_FILE));
+            let mut entries = Vec::new();
+            for path in paths {
+                // 文件可能不存在，或刚好被轮转改名
+                let Ok(file) = File::open(&path) else {
+                    continue;
+                };
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    // 进程中断可能留下写了一半的最后一行
+                    match serde_json::from_str::<AuditEntry>(&line) {
+                        Ok(entry) if query.matches(&entry) => entries.push(entry),
+                        Ok(_) => {}
+                        Err(_) if line.trim().is_empty() => {}
+                        Err(e) => eprintln!("跳过无法解析的审计记录: {}", e),
+                    }
+                }
+            }
+            Ok(entries)
+        }).await?
+    }
+}
+
+/// 隐藏参数中的密码、令牌等敏感信息：敏感字段名的值、`KEY=value` 形式的环境变量，
+/// 多行文本（如 compose 文件）中的 `KEY: value` 和 `KEY=value`，以及地址中的敏感查询参数和密码
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret_key(key) && !value.is_null() {
+                    *value = Value::String(MASK.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        Value::String(text) => *text = redact_text(text),
+        _ => {}
+    }
+}
+
+fn redact_text(text: &str) -> String {
+    text.split('\n')
+        .map(|line| match ASSIGNMENT.captures(line) {
+            Some(caps) if is_secret_key(&caps[2]) => format!("{}{}", &caps[1], MASK),
+            _ => redact_url(line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn redact_url(text: &str) -> String {
+    let text = URL_PASSWORD.replace_all(text, format!("${{1}}{}@", MASK).as_str());
+    QUERY_PARAM.replace_all(&text, |caps: &regex::Captures| {
+        let name = &caps[2];
+        if is_secret_key(name) || URL_SECRET_PARAMS.iter().any(|p| name.eq_ignore_ascii_case(p)) {
+            format!("{}{}", &caps[1], MASK)
+        } else {
+            caps[0].to_string()
+        }
+    }).into_owned()
+}
@@ -0,0 +1,419 @@
+use bollard::Docker;
+use bollard::container::{
+    Config, CreateContainerOptions, DownloadFromContainerOptions, ListContainersOptions,
+    RemoveContainerOptions, UploadToContainerOptions, WaitContainerOptions,
+};
+use bollard::models::{HostConfig, Mount, MountTypeEnum};
+use bollard::volume::CreateVolumeOptions;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use futures::StreamExt;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tokio_util::io::ReaderStream;
+use anyhow::Result;
+use crate::storage;
+use crate::error::ApiError;
+
+pub(crate) const SETTINGS_FILE: &str = "backups.json";
+const ARCHIVE_SUFFIX: &str = ".tar.gz";
+// 精确到毫秒，避免同一秒内的两次备份使用同一个文件名
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+// 辅助容器内卷的挂载位置，归档中的路径都以 volume/ 开头
+const MOUNT_PATH: &str = "/volume";
+
+/// 辅助容器的标签，方便识别和清理
+pub const LABEL_HELPER: &str = "docker-manager.helper";
+
+/// 卷备份设置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// 备份目录，为空时使用数据目录下的 backups
+    pub directory: Option<String>,
+    /// 每个卷保留的备份数量，0 表示不限制
+    pub retention: usize,
+    /// 用于读写卷内容的辅助镜像，需要包含 sh 和 find
+    pub helper_image: String,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            retention: 7,
+            helper_image: "busybox:latest".to_string(),
+        }
+    }
+}
+
+/// 备份目录中的一个归档
+#[derive(Clone, Debug, Serialize)]
+pub struct BackupInfo {
+    pub file: String,
+    pub volume: String,
+    pub size: u64,
+    pub created: DateTime<Local>,
+}
+
+/// 恢复的结果
+#[derive(Clone, Debug, Serialize)]
+pub struct RestoreReport {
+    pub file: String,
+    pub volume: String,
+    /// 目标卷原本不存在，由恢复操作新建
+    pub created: bool,
+}
+
+pub struct BackupManager {
+    docker: Docker,
+    settings: Mutex<BackupSettings>,
+}
+
+impl BackupManager {
+    pub fn load(docker: Docker) -> Result<Self> {
+        let settings: BackupSettings = storage::load_json(SETTINGS_FILE)?;
+        Ok(Self {
+            docker,
+            settings: Mutex::new(settings),
+        })
+    }
+
+    pub fn settings(&self) -> BackupSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update_settings(&self, settings: BackupSettings) -> Result<()> {
+        if settings.helper_image.trim().is_empty() {
+            return Err(ApiError::invalid("辅助镜像不能为空").into());
+        }
+        storage::save_json(SETTINGS_FILE, &settings)?;
+        *self.settings.lock().unwrap() = settings;
+        println!("已更新备份设置");
+        Ok(())
+    }
+
+    pub fn backup_dir(&self) -> PathBuf {
+        self.settings().directory
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| storage::data_path("backups"))
+    }
+
+    /// 列出备份，按时间从新到旧排列；传入 `volume` 时只列出该卷的备份
+    pub fn list_backups(&self, volume: Option<&str>) -> Result<Vec<BackupInfo>> {
+        let dir = self.backup_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut result = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file = entry.file_name().to_string_lossy().to_string();
+            let Some((name, created)) = parse_file_name(&file) else {
+                continue;
+            };
+            if volume.is_some_and(|v| v != name) {
+                continue;
+            }
+            result.push(BackupInfo {
+                size: entry.metadata()?.len(),
+                volume: name,
+                created,
+                file,
+            });
+        }
+        result.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| b.file.cmp(&a.file)));
+        Ok(result)
+    }
+
+    pub fn remove_backup(&self, file: &str) -> Result<()> {
+        let path = self.archive_path(file)?;
+        fs::remove_file(&path)?;
+        println!("已删除备份: {}", file);
+        Ok(())
+    }
+
+    /// 把卷导出为 `.tar.gz`；`pause` 为 true 时先暂停正在使用该卷的容器
+    pub async fn backup_volume(&self, volume: &str, pause: bool) -> Result<BackupInfo> {
+        self.docker.inspect_volume(volume).await?;
+
+        let paused = if pause {
+            self.pause_users(volume).await
+        } else {
+            Vec::new()
+        };
+        let result = self.export_volume(volume).await;
+        self.unpause(&paused).await;
+
+        let info = result?;
+        if let Err(e) = self.enforce_retention(volume) {
+            eprintln!("清理卷 {} 的旧备份失败: {}", volume, e);
+        }
+        Ok(info)
+    }
+
+    /// 暂停正在运行且挂载了该卷的容器，返回成功暂停的容器
+    pub async fn pause_users(&self, volume: &str) -> Vec<String> {
+        let mut filters = HashMap::new();
+        filters.insert("volume".to_string(), vec![volume.to_string()]);
+        filters.insert("status".to_string(), vec!["running".to_string()]);
+        let containers = match self.docker.list_containers(Some(ListContainersOptions::<String> {
+            all: false,
+            filters,
+            ..Default::default()
+        })).await {
+            Ok(containers) => containers,
+            Err(e) => {
+                eprintln!("获取卷 {} 的使用者失败: {}", volume, e);
+                return Vec::new();
+            }
+        };
+
+        let mut paused = Vec::new();
+        for id in containers.into_iter().filter_map(|c| c.id) {
+            if self.pause(&id).await {
+                paused.push(id);
+            }
+        }
+        paused
+    }
+
+    pub async fn pause(&self, container_id: &str) -> bool {
+        match self.docker.pause_container(container_id).await {
+            Ok(_) => {
+                println!("备份期间暂停容器: {}", container_id);
+                true
+            }
+            Err(e) => {
+                eprintln!("暂停容器 {} 失败: {}", container_id, e);
+                false
+            }
+        }
+    }
+
+    pub async fn unpause(&self, containers: &[String]) {
+        for id in containers {
+            match self.docker.unpause_container(id).await {
+                Ok(_) => println!("已恢复容器运行: {}", id),
+                Err(e) => eprintln!("恢复容器 {} 运行失败: {}", id, e),
+            }
+        }
+    }
+
+    /// 从备份恢复卷。`target` 为空时恢复到原来的卷；目标卷已存在时需要 `replace`，
+    /// 此时会先清空卷中原有的内容
+    pub async fn restore(&self, file: &str, target: Option<&str>, replace: bool) -> Result<RestoreReport> {
+        let path = self.archive_path(file)?;
+        let (original, _) = parse_file_name(file)
+            .ok_or_else(|| ApiError::invalid(format!("无效的备份文件名: {}", file)))?;
+        let volume = target.filter(|t| !t.is_empty()).unwrap_or(&original).to_string();
+
+        let exists = match self.docker.inspect_volume(&volume).await {
+            Ok(_) => true,
+            Err(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. }) => false,
+            Err(e) => return Err(e.into()),
+        };
+
+        if exists {
+            if !replace {
+                return Err(ApiError::conflict(format!("卷 {} 已存在，如需覆盖请设置 replace", volume)).into());
+            }
+            let running = self.running_users(&volume).await?;
+            if !running.is_empty() {
+                return Err(ApiError::conflict(format!("卷 {} 正被运行中的容器使用，请先停止: {}", volume, running.join(", "))).into());
+            }
+        } else {
+            self.docker.create_volume(CreateVolumeOptions {
+                name: volume.clone(),
+                driver: "local".to_string(),
+                ..Default::default()
+            }).await?;
+            println!("已为恢复创建卷: {}", volume);
+        }
+
+        let archive = tokio::fs::File::open(&path).await?;
+        let helper = self.create_helper(&volume, false).await?;
+        let result = self.import_volume(&helper, exists, archive).await;
+        self.remove_helper(&helper).await;
+        result?;
+
+        println!("已从 {} 恢复卷 {}", file, volume);
+        Ok(RestoreReport {
+            file: file.to_string(),
+            volume,
+            created: !exists,
+        })
+    }
+
+    async fn export_volume(&self, volume: &str) -> Result<BackupInfo> {
+        let dir = self.backup_dir();
+        fs::create_dir_all(&dir)?;
+        let now = Local::now();
+        let file = format!("{}_{}{}", volume, now.format(TIMESTAMP_FORMAT), ARCHIVE_SUFFIX);
+        let path = dir.join(&file);
+        let tmp = dir.join(format!(".{}.tmp", file));
+
+        let helper = self.create_helper(volume, true).await?;
+        let result = self.download_archive(&helper, &tmp).await;
+        self.remove_helper(&helper).await;
+        if let Err(e) = result {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        fs::rename(&tmp, &path)?;
+
+        let size = fs::metadata(&path)?.len();
+        println!("已备份卷 {} 到 {} ({} 字节)", volume, path.display(), size);
+        Ok(BackupInfo {
+            file,
+            volume: volume.to_string(),
+            size,
+            created: now,
+        })
+    }
+
+    // 压缩和写文件都是阻塞操作，放到单独的线程中，通过通道接收下载的数据
+    async fn download_archive(&self, helper: &str, path: &PathBuf) -> Result<()> {
+        let file = fs::File::create(path)?;
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<hyper::body::Bytes>(16);
+        let writer = tokio::task::spawn_blocking(move || -> Result<()> {
+            let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            while let Some(chunk) = rx.blocking_recv() {
+                encoder.write_all(&chunk)?;
+            }
+            encoder.finish()?.sync_all()?;
+            Ok(())
+        });
+
+        let mut stream = self.docker.download_from_container(helper, Some(DownloadFromContainerOptions {
+            path: MOUNT_PATH,
+        }));
+        let mut download = Ok(());
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                // 发送失败说明写入线程已经出错退出，错误从 writer 中取得
+                Ok(chunk) => if tx.send(chunk).await.is_err() {
+                    break;
+                },
+                Err(e) => {
+                    download = Err(e.into());
+                    break;
+                }
+            }
+        }
+        drop(tx);
+        let written = writer.await?;
+        download.and(written)
+    }
+
+    async fn import_volume(&self, helper: &str, clear: bool, archive: tokio::fs::File) -> Result<()> {
+        if clear {
+            self.docker.start_container::<String>(helper, None).await?;
+            let mut wait = self.docker.wait_container(helper, None::<WaitContainerOptions<String>>);
+            while let Some(result) = wait.next().await {
+                let response = result?;
+                if response.status_code != 0 {
+                    return Err(anyhow::anyhow!("清空卷内容失败，退出码 {}", response.status_code));
+                }
+            }
+        }
+        // Docker 可以直接解压 gzip 压缩的 tar
+        self.docker.upload_to_container(helper, Some(UploadToContainerOptions {
+            path: "/",
+            ..Default::default()
+        }), hyper::Body::wrap_stream(ReaderStream::new(archive))).await?;
+        Ok(())
+    }
+
+    // 辅助容器只创建不启动，通过归档接口读写卷；恢复时才会启动它清空旧内容
+    async fn create_helper(&self, volume: &str, read_only: bool) -> Result<String> {
+        let image = self.settings().helper_image;
+        let mut labels = HashMap::new();
+        labels.insert(LABEL_HELPER.to_string(), "true".to_string());
+
+        let response = self.docker.create_container(
+            None::<CreateContainerOptions<String>>,
+            Config {
+                image: Some(image),
+                cmd: Some(vec![
+                    "sh".to_string(),
+                    "-c".to_string(),
+                    format!("find {} -mindepth 1 -delete", MOUNT_PATH),
+                ]),
+                labels: Some(labels),
+                host_config: Some(HostConfig {
+                    mounts: Some(vec![Mount {
+                        target: Some(MOUNT_PATH.to_string()),
+                        source: Some(volume.to_string()),
+                        typ: Some(MountTypeEnum::VOLUME),
+                        read_only: Some(read_only),
+                        ..Default::default()
+                    }]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        ).await?;
+        Ok(response.id)
+    }
+
+    async fn remove_helper(&self, helper: &str) {
+        if let Err(e) = self.docker.remove_container(helper, Some(RemoveContainerOptions {
+            force: true,
+            ..Default::default()
+        })).await {
+            eprintln!("删除辅助容器 {} 失败: {}", helper, e);
+        }
+    }
+
+    async fn running_users(&self, volume: &str) -> Result<Vec<String>> {
+        let mut filters = HashMap::new();
+        filters.insert("volume".to_string(), vec![volume.to_string()]);
+        let containers = self.docker.list_containers(Some(ListContainersOptions::<String> {
+            all: false,
+            filters,
+            ..Default::default()
+        })).await?;
+        Ok(containers.into_iter()
+            .filter_map(|c| c.names.and_then(|n| n.first().cloned()))
+            .map(|n| n.trim_start_matches('/').to_string())
+            .collect())
+    }
+
+    fn enforce_retention(&self, volume: &str) -> Result<()> {
+        let retention = self.settings().retention;
+        if retention == 0 {
+            return Ok(());
+        }
+        for backup in self.list_backups(Some(volume))?.into_iter().skip(retention) {
+            self.remove_backup(&backup.file)?;
+        }
+        Ok(())
+    }
+
+    // 只接受备份目录下的文件名，防止通过路径访问其他文件
+    fn archive_path(&self, file: &str) -> Result<PathBuf> {
+        if file.contains('/') || file.contains('\\') || file.starts_with('.') || !file.ends_with(ARCHIVE_SUFFIX) {
+            return Err(ApiError::invalid(format!("无效的备份文件名: {}", file)).into());
+        }
+        let path = self.backup_dir().join(file);
+        if !path.is_file() {
+            return Err(ApiError::not_found(format!("备份 {} 不存在", file)).into());
+        }
+        Ok(path)
+    }
+}
+
+// 备份文件名格式为 <卷名>_<时间>.tar.gz，卷名本身可能包含下划线
+fn parse_file_name(file: &str) -> Option<(String, DateTime<Local>)> {
+    let stem = file.strip_suffix(ARCHIVE_SUFFIX)?;
+    let (volume, timestamp) = stem.rsplit_once('_')?;
+    let naive = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+    let created = Local.from_local_datetime(&naive).earliest()?;
+    Some((volume.to_string(), created))
+}
@@ -0,0 +1,1374 @@
+use bollard::container::{Config, NetworkingConfig};
+use bollard::models::{
+    EndpointIpamConfig, EndpointSettings, HealthConfig, HostConfig, MountBindOptionsPropagationEnum,
+    PortBinding, RestartPolicy, RestartPolicyNameEnum,
+};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use crate::models::{parse_size, MountSpec};
+use crate::networks::NetworkSpec;
+use crate::volumes::VolumeSpec;
+
+// 与 docker compose 使用相同的标签，compose 命令行也能识别这些容器
+pub const LABEL_PROJECT: &str = "com.docker.compose.project";
+pub const LABEL_SERVICE: &str = "com.docker.compose.service";
+pub const LABEL_CONTAINER_NUMBER: &str = "com.docker.compose.container-number";
+pub const LABEL_ONEOFF: &str = "com.docker.compose.oneoff";
+pub const LABEL_WORKING_DIR: &str = "com.docker.compose.project.working_dir";
+pub const LABEL_CONFIG_HASH: &str = "com.docker.compose.config-hash";
+pub const LABEL_DEPENDS_ON: &str = "com.docker.compose.depends_on";
+pub const LABEL_NETWORK: &str = "com.docker.compose.network";
+pub const LABEL_VOLUME: &str = "com.docker.compose.volume";
+
+const DEFAULT_NETWORK: &str = "default";
+
+/// 解析完成、可以直接部署的 compose 项目
+#[derive(Clone, Debug)]
+pub struct ComposeProject {
+    pub name: String,
+    /// 按依赖顺序排列，被依赖的服务在前
+    pub services: Vec<ComposeService>,
+    pub networks: Vec<ComposeNetwork>,
+    pub volumes: Vec<ComposeVolume>,
+}
+
+impl ComposeProject {
+    pub fn service(&self, name: &str) -> Option<&ComposeService> {
+        self.services.iter().find(|s| s.name == name)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ComposeService {
+    pub name: String,
+    pub container_name: String,
+    pub image: String,
+    /// 创建容器的完整配置，只包含第一个网络，其余网络在创建后再连接
+    pub config: Config<String>,
+    pub networks: Vec<ServiceNetwork>,
+    pub depends_on: Vec<Dependency>,
+    /// 服务配置的摘要，配置变化时需要重建容器
+    pub config_hash: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ServiceNetwork {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub ipv4_address: Option<String>,
+    pub ipv6_address: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Dependency {
+    pub service: String,
+    pub condition: DependencyCondition,
+    /// 依赖的服务重启后，是否也重启本服务
+    pub restart: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyCondition {
+    #[default]
+    ServiceStarted,
+    ServiceHealthy,
+    ServiceCompletedSuccessfully,
+}
+
+impl DependencyCondition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DependencyCondition::ServiceStarted => "service_started",
+            DependencyCondition::ServiceHealthy => "service_healthy",
+            DependencyCondition::ServiceCompletedSuccessfully => "service_completed_successfully",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "service_started" => Some(DependencyCondition::ServiceStarted),
+            "service_healthy" => Some(DependencyCondition::ServiceHealthy),
+            "service_completed_successfully" => Some(DependencyCondition::ServiceCompletedSuccessfully),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ComposeNetwork {
+    pub spec: NetworkSpec,
+    /// 外部网络由用户自己管理，只检查是否存在
+    pub external: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct ComposeVolume {
+    pub spec: VolumeSpec,
+    pub external: bool,
+}
+
+// ---- compose 文件的原始结构，只覆盖常用字段，未知字段会被忽略 ----
+
+#[derive(Deserialize)]
+struct RawCompose {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    services: BTreeMap<String, RawService>,
+    #[serde(default)]
+    networks: BTreeMap<String, Option<RawNetwork>>,
+    #[serde(default)]
+    volumes: BTreeMap<String, Option<RawVolume>>,
+}
+
+#[derive(Deserialize)]
+struct RawService {
+    image: Option<String>,
+    build: Option<serde_yaml::Value>,
+    container_name: Option<String>,
+    command: Option<StringOrList>,
+    entrypoint: Option<StringOrList>,
+    environment: Option<MapOrList>,
+    env_file: Option<StringOrList>,
+    #[serde(default)]
+    ports: Vec<RawPort>,
+    #[serde(default)]
+    volumes: Vec<RawServiceVolume>,
+    networks: Option<RawServiceNetworks>,
+    network_mode: Option<String>,
+    depends_on: Option<RawDependsOn>,
+    healthcheck: Option<RawHealthcheck>,
+    restart: Option<String>,
+    labels: Option<MapOrList>,
+    hostname: Option<String>,
+    domainname: Option<String>,
+    user: Option<String>,
+    working_dir: Option<String>,
+    privileged: Option<bool>,
+    read_only: Option<bool>,
+    tty: Option<bool>,
+    stdin_open: Option<bool>,
+    #[serde(default)]
+    cap_add: Vec<String>,
+    #[serde(default)]
+    cap_drop: Vec<String>,
+    dns: Option<StringOrList>,
+    #[serde(default)]
+    extra_hosts: Vec<String>,
+    mem_limit: Option<Scalar>,
+    cpus: Option<Scalar>,
+    stop_signal: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrList {
+    String(String),
+    List(Vec<String>),
+}
+
+impl StringOrList {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            StringOrList::String(s) => vec![s],
+            StringOrList::List(l) => l,
+        }
+    }
+
+    // command 写成字符串时按 shell 的规则拆分参数
+    fn into_args(self) -> Result<Vec<String>> {
+        match self {
+            StringOrList::String(s) => split_command(&s),
+            StringOrList::List(l) => Ok(l),
+        }
+    }
+}
+
+/// YAML 中可能写成数字或布尔值的字段
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Scalar {
+    fn as_string(&self) -> String {
+        match self {
+            Scalar::String(s) => s.clone(),
+            Scalar::Integer(i) => i.to_string(),
+            Scalar::Float(f) => f.to_string(),
+            Scalar::Bool(b) => b.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MapOrList {
+    Map(BTreeMap<String, Option<Scalar>>),
+    List(Vec<String>),
+}
+
+impl MapOrList {
+    // 列表形式中没有值的项（如 `- DEBUG`）返回 None
+    fn into_pairs(self) -> Vec<(String, Option<String>)> {
+        match self {
+            MapOrList::Map(map) => map.into_iter()
+                .map(|(k, v)| (k, v.map(|v| v.as_string())))
+                .collect(),
+            MapOrList::List(list) => list.into_iter()
+                .map(|item| match item.split_once('=') {
+                    Some((k, v)) => (k.to_string(), Some(v.to_string())),
+                    None => (item, None),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawPort {
+    Short(Scalar),
+    Long {
+        target: u16,
+        published: Option<Scalar>,
+        host_ip: Option<String>,
+        protocol: Option<String>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawServiceVolume {
+    Short(String),
+    Long(RawLongVolume),
+}
+
+#[derive(Deserialize)]
+struct RawLongVolume {
+    #[serde(rename = "type")]
+    typ: String,
+    source: Option<String>,
+    target: String,
+    #[serde(default)]
+    read_only: bool,
+    bind: Option<RawBindOptions>,
+    volume: Option<RawVolumeOptions>,
+    tmpfs: Option<RawTmpfsOptions>,
+}
+
+#[derive(Deserialize)]
+struct RawBindOptions {
+    propagation: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawVolumeOptions {
+    #[serde(default)]
+    nocopy: bool,
+}
+
+#[derive(Deserialize)]
+struct RawTmpfsOptions {
+    size: Option<Scalar>,
+    mode: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawServiceNetworks {
+    List(Vec<String>),
+    Map(BTreeMap<String, Option<RawServiceNetwork>>),
+}
+
+#[derive(Deserialize, Default)]
+struct RawServiceNetwork {
+    #[serde(default)]
+    aliases: Vec<String>,
+    ipv4_address: Option<String>,
+    ipv6_address: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDependsOn {
+    List(Vec<String>),
+    Map(BTreeMap<String, RawDependency>),
+}
+
+#[derive(Deserialize)]
+struct RawDependency {
+    #[serde(default)]
+    condition: DependencyCondition,
+    #[serde(default)]
+    restart: bool,
+}
+
+#[derive(Deserialize)]
+struct RawHealthcheck {
+    test: Option<StringOrList>,
+    interval: Option<String>,
+    timeout: Option<String>,
+    start_period: Option<String>,
+    retries: Option<i64>,
+    #[serde(default)]
+    disable: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct RawNetwork {
+    driver: Option<String>,
+    #[serde(default)]
+    driver_opts: HashMap<String, Scalar>,
+    external: Option<bool>,
+    name: Option<String>,
+    #[serde(default)]
+    internal: bool,
+    #[serde(default)]
+    attachable: bool,
+    #[serde(default)]
+    enable_ipv6: bool,
+    ipam: Option<RawIpam>,
+    labels: Option<MapOrList>,
+}
+
+#[derive(Deserialize)]
+struct RawIpam {
+    #[serde(default)]
+    config: Vec<RawIpamConfig>,
+}
+
+#[derive(Deserialize)]
+struct RawIpamConfig {
+    subnet: Option<String>,
+    gateway: Option<String>,
+    ip_range: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawVolume {
+    driver: Option<String>,
+    #[serde(default)]
+    driver_opts: HashMap<String, Scalar>,
+    external: Option<bool>,
+    name: Option<String>,
+    labels: Option<MapOrList>,
+}
+
+/// 解析 compose 文件。`working_dir` 用于解析 `env_file`、`.env` 和相对路径的绑定挂载，
+/// `env` 中的变量优先于 `.env` 文件参与插值，管理器自身的环境变量不参与
+pub fn parse(
+    project: &str,
+    content: &str,
+    working_dir: &Path,
+    env: &HashMap<String, String>,
+) -> Result<ComposeProject> {
+    let variables = load_variables(working_dir, env)?;
+    let value: serde_yaml::Value = serde_yaml::from_str(content)
+        .map_err(|e| anyhow::anyhow!("解析 compose 文件失败: {}", e))?;
+    let value = interpolate_value(value, &variables)?;
+    let raw: RawCompose = serde_yaml::from_value(value)
+        .map_err(|e| anyhow::anyhow!("解析 compose 文件失败: {}", e))?;
+
+    let name = normalize_project_name(
+        if project.is_empty() { raw.name.as_deref().unwrap_or_default() } else { project },
+    );
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("缺少项目名称"));
+    }
+    if raw.services.is_empty() {
+        return Err(anyhow::anyhow!("compose 文件中没有定义服务"));
+    }
+
+    let mut networks = BTreeMap::new();
+    for (key, network) in raw.networks {
+        networks.insert(key.clone(), resolve_network(&name, &key, network.unwrap_or_default()));
+    }
+    let mut volumes = BTreeMap::new();
+    for (key, volume) in raw.volumes {
+        volumes.insert(key.clone(), resolve_volume(&name, &key, volume.unwrap_or_default()));
+    }
+
+    let container_names: HashMap<String, String> = raw.services.iter()
+        .map(|(key, service)| {
+            let container_name = service.container_name.clone()
+                .unwrap_or_else(|| format!("{}-{}-1", name, key));
+            (key.clone(), container_name)
+        })
+        .collect();
+
+    let mut services = Vec::new();
+    let mut used_networks = HashSet::new();
+    for (key, service) in raw.services {
+        let resolved = resolve_service(
+            &name, &key, service, working_dir, &variables,
+            &mut networks, &volumes, &container_names,
+        )?;
+        used_networks.extend(resolved.networks.iter().map(|n| n.name.clone()));
+        services.push(resolved);
+    }
+
+    // 只创建实际被服务使用的网络，与 compose 的行为一致
+    let networks = networks.into_values()
+        .filter(|n| used_networks.contains(&n.spec.name))
+        .collect();
+
+    Ok(ComposeProject {
+        services: sort_services(services)?,
+        networks,
+        volumes: volumes.into_values().collect(),
+        name,
+    })
+}
+
+/// 项目名称只保留小写字母、数字、`-` 和 `_`
+pub fn normalize_project_name(name: &str) -> String {
+    name.to_ascii_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+fn resolve_network(project: &str, key: &str, raw: RawNetwork) -> ComposeNetwork {
+    let external = raw.external.unwrap_or(false);
+    let name = raw.name.unwrap_or_else(|| {
+        if external { key.to_string() } else { format!("{}_{}", project, key) }
+    });
+    let mut labels: HashMap<String, String> = raw.labels
+        .map(|l| l.into_pairs().into_iter().map(|(k, v)| (k, v.unwrap_or_default())).collect())
+        .unwrap_or_default();
+    labels.insert(LABEL_PROJECT.to_string(), project.to_string());
+    labels.insert(LABEL_NETWORK.to_string(), key.to_string());
+    let ipam = raw.ipam.and_then(|ipam| ipam.config.into_iter().next());
+
+    ComposeNetwork {
+        spec: NetworkSpec {
+            name,
+            driver: raw.driver.unwrap_or_else(|| "bridge".to_string()),
+            subnet: ipam.as_ref().and_then(|c| c.subnet.clone()),
+            gateway: ipam.as_ref().and_then(|c| c.gateway.clone()),
+            ip_range: ipam.as_ref().and_then(|c| c.ip_range.clone()),
+            internal: raw.internal,
+            attachable: raw.attachable,
+            enable_ipv6: raw.enable_ipv6,
+            labels,
+            options: raw.driver_opts.iter().map(|(k, v)| (k.clone(), v.as_string())).collect(),
+        },
+        external,
+    }
+}
+
+fn resolve_volume(project: &str, key: &str, raw: RawVolume) -> ComposeVolume {
+    let external = raw.external.unwrap_or(false);
+    let name = raw.name.unwrap_or_else(|| {
+        if external { key.to_string() } else { format!("{}_{}", project, key) }
+    });
+    let mut labels: HashMap<String, String> = raw.labels
+        .map(|l| l.into_pairs().into_iter().map(|(k, v)| (k, v.unwrap_or_default())).collect())
+        .unwrap_or_default();
+    labels.insert(LABEL_PROJECT.to_string(), project.to_string());
+    labels.insert(LABEL_VOLUME.to_string(), key.to_string());
+
+    ComposeVolume {
+        spec: VolumeSpec {
+            name,
+            driver: raw.driver.unwrap_or_else(|| "local".to_string()),
+            driver_opts: raw.driver_opts.iter().map(|(k, v)| (k.clone(), v.as_string())).collect(),
+            labels,
+        },
+        external,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn resolve_service(
+    project: &str,
+    key: &str,
+    raw: RawService,
+    working_dir: &Path,
+    variables: &HashMap<String, String>,
+    networks: &mut BTreeMap<String, ComposeNetwork>,
+    volumes: &BTreeMap<String, ComposeVolume>,
+    container_names: &HashMap<String, String>,
+) -> Result<ComposeService> {
+    let context = |e: anyhow::Error| anyhow::anyhow!("服务 {}: {}", key, e);
+
+    let image = match (raw.image, raw.build) {
+        (Some(image), _) => image,
+        (None, Some(_)) => return Err(anyhow::anyhow!("服务 {}: 暂不支持 build，请先构建镜像并指定 image", key)),
+        (None, None) => return Err(anyhow::anyhow!("服务 {} 缺少 image", key)),
+    };
+    let container_name = container_names[key].clone();
+
+    // env_file 先读取，environment 中的同名变量覆盖它
+    let mut environment = BTreeMap::new();
+    for file in raw.env_file.map(StringOrList::into_vec).unwrap_or_default() {
+        let path = working_dir.join(&file);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("服务 {}: 读取 env_file {} 失败: {}", key, path.display(), e))?;
+        environment.extend(parse_env_file(&content));
+    }
+    for (name, value) in raw.environment.map(MapOrList::into_pairs).unwrap_or_default() {
+        // 没有写值的变量从插值变量中取，取不到则不设置
+        match value.or_else(|| variables.get(&name).cloned()) {
+            Some(value) => { environment.insert(name, value); }
+            None => { environment.remove(&name); }
+        }
+    }
+
+    let mut port_bindings: HashMap<String, Option<Vec<PortBinding>>> = HashMap::new();
+    let mut exposed_ports = HashMap::new();
+    for port in raw.ports {
+        for (container_port, binding) in parse_port(port).map_err(context)? {
+            exposed_ports.insert(container_port.clone(), HashMap::new());
+            port_bindings.entry(container_port).or_insert_with(|| Some(Vec::new()))
+                .get_or_insert_with(Vec::new)
+                .push(binding);
+        }
+    }
+
+    let mut mounts = Vec::new();
+    for volume in raw.volumes {
+        let spec = parse_service_volume(volume, working_dir, volumes).map_err(context)?;
+        mounts.push(spec.to_mount());
+    }
+
+    let mut service_networks = Vec::new();
+    let network_service = raw.network_mode.as_deref()
+        .and_then(|mode| mode.strip_prefix("service:"))
+        .map(str::to_string);
+    let network_mode = match raw.network_mode {
+        Some(mode) => {
+            if raw.networks.is_some() {
+                return Err(anyhow::anyhow!("服务 {}: network_mode 与 networks 不能同时使用", key));
+            }
+            // service:<name> 指向同一项目中另一个服务的容器
+            match mode.strip_prefix("service:") {
+                Some(service) => {
+                    let target = container_names.get(service)
+                        .ok_or_else(|| anyhow::anyhow!("服务 {}: network_mode 引用了不存在的服务 {}", key, service))?;
+                    Some(format!("container:{}", target))
+                }
+                None => Some(mode),
+            }
+        }
+        None => {
+            let entries: Vec<(String, RawServiceNetwork)> = match raw.networks {
+                None => vec![(DEFAULT_NETWORK.to_string(), RawServiceNetwork::default())],
+                Some(RawServiceNetworks::List(list)) => list.into_iter()
+                    .map(|n| (n, RawServiceNetwork::default()))
+                    .collect(),
+                Some(RawServiceNetworks::Map(map)) => map.into_iter()
+                    .map(|(n, v)| (n, v.unwrap_or_default()))
+                    .collect(),
+            };
+            for (network_key, settings) in entries {
+                if network_key == DEFAULT_NETWORK && !networks.contains_key(DEFAULT_NETWORK) {
+                    networks.insert(
+                        DEFAULT_NETWORK.to_string(),
+                        resolve_network(project, DEFAULT_NETWORK, RawNetwork::default()),
+                    );
+                }
+                let network = networks.get(&network_key)
+                    .ok_or_else(|| anyhow::anyhow!("服务 {} 使用了未定义的网络 {}", key, network_key))?;
+                // 服务名总是可以作为网络别名访问
+                let mut aliases = vec![key.to_string()];
+                aliases.extend(settings.aliases.into_iter().filter(|a| a != key));
+                service_networks.push(ServiceNetwork {
+                    name: network.spec.name.clone(),
+                    aliases,
+                    ipv4_address: settings.ipv4_address,
+                    ipv6_address: settings.ipv6_address,
+                });
+            }
+            service_networks.first().map(|n| n.name.clone())
+        }
+    };
+
+    let mut depends_on: Vec<Dependency> = match raw.depends_on {
+        None => Vec::new(),
+        Some(RawDependsOn::List(list)) => list.into_iter()
+            .map(|service| Dependency { service, condition: DependencyCondition::ServiceStarted, restart: false })
+            .collect(),
+        Some(RawDependsOn::Map(map)) => map.into_iter()
+            .map(|(service, d)| Dependency { service, condition: d.condition, restart: d.restart })
+            .collect(),
+    };
+    // 共享另一个服务的网络时隐式依赖它
+    if let Some(service) = network_service {
+        if !depends_on.iter().any(|d| d.service == service) {
+            depends_on.push(Dependency { service, condition: DependencyCondition::ServiceStarted, restart: false });
+        }
+    }
+    for dependency in &depends_on {
+        if !container_names.contains_key(&dependency.service) {
+            return Err(anyhow::anyhow!("服务 {} 依赖了不存在的服务 {}", key, dependency.service));
+        }
+    }
+
+    let healthcheck = match raw.healthcheck {
+        Some(check) => Some(parse_healthcheck(check).map_err(context)?),
+        None => None,
+    };
+
+    let mut labels: HashMap<String, String> = raw.labels
+        .map(|l| l.into_pairs().into_iter().map(|(k, v)| (k, v.unwrap_or_default())).collect())
+        .unwrap_or_default();
+    labels.insert(LABEL_PROJECT.to_string(), project.to_string());
+    labels.insert(LABEL_SERVICE.to_string(), key.to_string());
+    labels.insert(LABEL_CONTAINER_NUMBER.to_string(), "1".to_string());
+    labels.insert(LABEL_ONEOFF.to_string(), "False".to_string());
+    labels.insert(LABEL_WORKING_DIR.to_string(), working_dir.display().to_string());
+    if !depends_on.is_empty() {
+        // 与 compose v2 的格式相同：服务:条件:是否随依赖重启
+        let value = depends_on.iter()
+            .map(|d| format!("{}:{}:{}", d.service, d.condition.as_str(), d.restart))
+            .collect::<Vec<_>>()
+            .join(",");
+        labels.insert(LABEL_DEPENDS_ON.to_string(), value);
+    }
+
+    let command = raw.command.map(StringOrList::into_args).transpose().map_err(context)?;
+    let entrypoint = raw.entrypoint.map(StringOrList::into_args).transpose().map_err(context)?;
+    let memory = raw.mem_limit.map(|m| parse_size(&m.as_string())).transpose().map_err(context)?;
+    let nano_cpus = match raw.cpus {
+        Some(cpus) => {
+            let value: f64 = cpus.as_string().parse()
+                .map_err(|_| anyhow::anyhow!("服务 {}: 无效的 cpus", key))?;
+            Some((value * 1e9) as i64)
+        }
+        None => None,
+    };
+
+    let first_network = service_networks.first().map(|network| {
+        let mut endpoints = HashMap::new();
+        endpoints.insert(network.name.clone(), endpoint_settings(network));
+        NetworkingConfig { endpoints_config: endpoints }
+    });
+
+    let mut config = Config {
+        image: Some(image.clone()),
+        cmd: command,
+        entrypoint,
+        env: Some(environment.into_iter().map(|(k, v)| format!("{}={}", k, v)).collect()),
+        exposed_ports: if exposed_ports.is_empty() { None } else { Some(exposed_ports) },
+        hostname: raw.hostname,
+        domainname: raw.domainname,
+        user: raw.user,
+        working_dir: raw.working_dir,
+        tty: raw.tty,
+        open_stdin: raw.stdin_open,
+        healthcheck,
+        stop_signal: raw.stop_signal,
+        host_config: Some(HostConfig {
+            port_bindings: if port_bindings.is_empty() { None } else { Some(port_bindings) },
+            mounts: if mounts.is_empty() { None } else { Some(mounts) },
+            network_mode,
+            restart_policy: raw.restart.map(|r| parse_restart(&r)).transpose().map_err(context)?,
+            privileged: raw.privileged,
+            readonly_rootfs: raw.read_only,
+            cap_add: if raw.cap_add.is_empty() { None } else { Some(raw.cap_add) },
+            cap_drop: if raw.cap_drop.is_empty() { None } else { Some(raw.cap_drop) },
+            dns: raw.dns.map(StringOrList::into_vec),
+            extra_hosts: if raw.extra_hosts.is_empty() { None } else { Some(raw.extra_hosts) },
+            memory,
+            nano_cpus,
+            ..Default::default()
+        }),
+        networking_config: first_network,
+        ..Default::default()
+    };
+
+    // 摘要不包含自身，计算完再写入标签
+    config.labels = Some(labels);
+    let config_hash = hash_config(&config, &service_networks);
+    if let Some(labels) = config.labels.as_mut() {
+        labels.insert(LABEL_CONFIG_HASH.to_string(), config_hash.clone());
+    }
+
+    Ok(ComposeService {
+        name: key.to_string(),
+        container_name,
+        image,
+        config,
+        networks: service_networks,
+        depends_on,
+        config_hash,
+    })
+}
+
+pub fn endpoint_settings(network: &ServiceNetwork) -> EndpointSettings {
+    let ipam_config = if network.ipv4_address.is_some() || network.ipv6_address.is_some() {
+        Some(EndpointIpamConfig {
+            ipv4_address: network.ipv4_address.clone(),
+            ipv6_address: network.ipv6_address.clone(),
+            ..Default::default()
+        })
+    } else {
+        None
+    };
+    EndpointSettings {
+        aliases: Some(network.aliases.clone()),
+        ipam_config,
+        ..Default::default()
+    }
+}
+
+fn hash_config(config: &Config<String>, networks: &[ServiceNetwork]) -> String {
+    let mut hasher = Sha256::new();
+    // 先转换为 Value，对象的键会排序，HashMap 的遍历顺序不影响结果
+    let value = serde_json::to_value(config).unwrap_or_default();
+    hasher.update(serde_json::to_vec(&value).unwrap_or_default());
+    for network in networks {
+        hasher.update(network.name.as_bytes());
+        hasher.update(network.aliases.join(",").as_bytes());
+        hasher.update(network.ipv4_address.as_deref().unwrap_or_default().as_bytes());
+        hasher.update(network.ipv6_address.as_deref().unwrap_or_default().as_bytes());
+    }
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 拓扑排序，依赖相同层级的服务按名称排列
+fn sort_services(services: Vec<ComposeService>) -> Result<Vec<ComposeService>> {
+    let mut remaining: BTreeMap<String, ComposeService> = services.into_iter()
+        .map(|s| (s.name.clone(), s))
+        .collect();
+    let mut sorted: Vec<ComposeService> = Vec::new();
+    let mut done = HashSet::new();
+
+    while !remaining.is_empty() {
+        let ready: Vec<String> = remaining.values()
+            .filter(|s| s.depends_on.iter().all(|d| done.contains(&d.service)))
+            .map(|s| s.name.clone())
+            .collect();
+        if ready.is_empty() {
+            let names: Vec<&str> = remaining.keys().map(|k| k.as_str()).collect();
+            return Err(anyhow::anyhow!("服务之间存在循环依赖: {}", names.join(", ")));
+        }
+        for name in ready {
+            if let Some(service) = remaining.remove(&name) {
+                done.insert(name);
+                sorted.push(service);
+            }
+        }
+    }
+    Ok(sorted)
+}
+
+// 支持 8080:80、127.0.0.1:8080:80、8080:80/udp、80 以及端口范围
+fn parse_port(port: RawPort) -> Result<Vec<(String, PortBinding)>> {
+    match port {
+        RawPort::Long { target, published, host_ip, protocol } => {
+            let protocol = protocol.unwrap_or_else(|| "tcp".to_string());
+            Ok(vec![(
+                format!("{}/{}", target, protocol),
+                PortBinding {
+                    host_ip: Some(host_ip.unwrap_or_else(|| "0.0.0.0".to_string())),
+                    host_port: published.map(|p| p.as_string()),
+                },
+            )])
+        }
+        RawPort::Short(value) => {
+            let value = value.as_string();
+            let (mapping, protocol) = value.split_once('/').unwrap_or((&value, "tcp"));
+            let parts: Vec<&str> = mapping.rsplitn(3, ':').collect();
+            let (host_ip, host_port, container_port) = match parts.as_slice() {
+                [container] => (None, None, *container),
+                [container, host] => (None, Some(*host), *container),
+                [container, host, ip] => (Some(*ip), Some(*host), *container),
+                _ => return Err(anyhow::anyhow!("无效的端口映射: {}", value)),
+            };
+            let host_ip = host_ip.map(|ip| ip.trim_matches(|c| c == '[' || c == ']').to_string());
+
+            let container_ports = parse_port_range(container_port)
+                .ok_or_else(|| anyhow::anyhow!("无效的端口映射: {}", value))?;
+            let host_ports = match host_port.filter(|p| !p.is_empty()) {
+                Some(host) => {
+                    let ports = parse_port_range(host)
+                        .ok_or_else(|| anyhow::anyhow!("无效的端口映射: {}", value))?;
+                    if ports.len() != container_ports.len() {
+                        return Err(anyhow::anyhow!("端口范围长度不一致: {}", value));
+                    }
+                    ports.into_iter().map(|p| Some(p.to_string())).collect()
+                }
+                None => vec![None; container_ports.len()],
+            };
+
+            Ok(container_ports.into_iter().zip(host_ports)
+                .map(|(container, host)| (
+                    format!("{}/{}", container, protocol),
+                    PortBinding {
+                        host_ip: Some(host_ip.clone().unwrap_or_else(|| "0.0.0.0".to_string())),
+                        host_port: host,
+                    },
+                ))
+                .collect())
+        }
+    }
+}
+
+fn parse_port_range(value: &str) -> Option<Vec<u16>> {
+    match value.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (start.parse::<u16>().ok()?, end.parse::<u16>().ok()?);
+            if start > end {
+                return None;
+            }
+            Some((start..=end).collect())
+        }
+        None => value.parse::<u16>().ok().map(|p| vec![p]),
+    }
+}
+
+fn parse_service_volume(
+    volume: RawServiceVolume,
+    working_dir: &Path,
+    volumes: &BTreeMap<String, ComposeVolume>,
+) -> Result<MountSpec> {
+    let spec = match volume {
+        RawServiceVolume::Short(value) => value.parse::<MountSpec>()?,
+        RawServiceVolume::Long(long) => match long.typ.as_str() {
+            "bind" => MountSpec::Bind {
+                source: long.source.ok_or_else(|| anyhow::anyhow!("绑定挂载缺少 source: {}", long.target))?,
+                target: long.target,
+                read_only: long.read_only,
+                propagation: long.bind.and_then(|b| b.propagation)
+                    .map(|p| p.parse::<MountBindOptionsPropagationEnum>()
+                        .map_err(|_| anyhow::anyhow!("未知的传播选项: {}", p)))
+                    .transpose()?,
+            },
+            "volume" => MountSpec::Volume {
+                source: long.source,
+                target: long.target,
+                read_only: long.read_only,
+                driver: None,
+                driver_opts: HashMap::new(),
+                no_copy: long.volume.map(|v| v.nocopy).unwrap_or(false),
+            },
+            "tmpfs" => MountSpec::Tmpfs {
+                target: long.target,
+                size: long.tmpfs.as_ref()
+                    .and_then(|t| t.size.as_ref())
+                    .map(|s| parse_size(&s.as_string()))
+                    .transpose()?,
+                mode: long.tmpfs.and_then(|t| t.mode),
+            },
+            other => return Err(anyhow::anyhow!("不支持的挂载类型: {}", other)),
+        },
+    };
+
+    Ok(match spec {
+        MountSpec::Bind { source, target, read_only, propagation } => MountSpec::Bind {
+            source: resolve_host_path(&source, working_dir).display().to_string(),
+            target,
+            read_only,
+            propagation,
+        },
+        // 命名卷必须在顶层 volumes 中声明，映射为带项目前缀的实际卷名
+        MountSpec::Volume { source: Some(source), target, read_only, driver, driver_opts, no_copy } => {
+            let volume = volumes.get(&source)
+                .ok_or_else(|| anyhow::anyhow!("使用了未在顶层 volumes 中声明的卷 {}", source))?;
+            MountSpec::Volume {
+                source: Some(volume.spec.name.clone()),
+                target,
+                read_only,
+                driver,
+                driver_opts,
+                no_copy,
+            }
+        }
+        other => other,
+    })
+}
+
+fn resolve_host_path(source: &str, working_dir: &Path) -> PathBuf {
+    if let Some(rest) = source.strip_prefix("~/") {
+        if let Some(home) = std::env::var_os("HOME") {
+            return PathBuf::from(home).join(rest);
+        }
+    }
+    let path = Path::new(source);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    // 去掉多余的 ./，让保存的配置更易读
+    let mut resolved = working_dir.to_path_buf();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => { resolved.pop(); }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+fn parse_healthcheck(check: RawHealthcheck) -> Result<HealthConfig> {
+    if check.disable {
+        return Ok(HealthConfig {
+            test: Some(vec!["NONE".to_string()]),
+            ..Default::default()
+        });
+    }
+    // 字符串形式的 test 交给 shell 执行
+    let test = check.test.map(|test| match test {
+        StringOrList::String(s) => vec!["CMD-SHELL".to_string(), s],
+        StringOrList::List(l) => l,
+    });
+    let nanos = |value: Option<String>| value.map(|v| parse_duration(&v)).transpose();
+    Ok(HealthConfig {
+        test,
+        interval: nanos(check.interval)?,
+        timeout: nanos(check.timeout)?,
+        start_period: nanos(check.start_period)?,
+        retries: check.retries,
+    })
+}
+
+/// 解析 `1m30s`、`500ms`、`2h` 这类时长，返回纳秒
+pub fn parse_duration(value: &str) -> Result<i64> {
+    let mut total: f64 = 0.0;
+    let mut number = String::new();
+    let mut chars = value.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let mut unit = c.to_string();
+        while let Some(next) = chars.peek() {
+            if next.is_ascii_alphabetic() || *next == 'µ' {
+                unit.push(*next);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        let multiplier = match unit.as_str() {
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            _ => return Err(anyhow::anyhow!("无效的时长: {}", value)),
+        };
+        let n: f64 = number.parse().map_err(|_| anyhow::anyhow!("无效的时长: {}", value))?;
+        total += n * multiplier;
+        number.clear();
+    }
+    if !number.is_empty() {
+        // 没有单位时按秒处理
+        let n: f64 = number.parse().map_err(|_| anyhow::anyhow!("无效的时长: {}", value))?;
+        total += n * 1e9;
+    }
+    Ok(total as i64)
+}
+
+fn parse_restart(value: &str) -> Result<RestartPolicy> {
+    let (name, retries) = value.split_once(':').unwrap_or((value, ""));
+    let name = match name {
+        "no" | "" => RestartPolicyNameEnum::NO,
+        "always" => RestartPolicyNameEnum::ALWAYS,
+        "unless-stopped" => RestartPolicyNameEnum::UNLESS_STOPPED,
+        "on-failure" => RestartPolicyNameEnum::ON_FAILURE,
+        other => return Err(anyhow::anyhow!("无效的重启策略: {}", other)),
+    };
+    let maximum_retry_count = if retries.is_empty() {
+        None
+    } else {
+        Some(retries.parse().map_err(|_| anyhow::anyhow!("无效的重启策略: {}", value))?)
+    };
+    Ok(RestartPolicy { name: Some(name), maximum_retry_count })
+}
+
+// 按 shell 的规则拆分命令行，支持单引号、双引号和反斜杠转义
+fn split_command(command: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                    in_arg = true;
+                }
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(anyhow::anyhow!("命令中的引号没有闭合: {}", command));
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+fn parse_env_file(content: &str) -> Vec<(String, String)> {
+    content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+// 插值变量的优先级：请求中的 env > .env 文件。不读取进程环境变量，
+// 以免 DOCKER_MANAGER_SECRET 等管理器自身的配置被写进容器
+fn load_variables(working_dir: &Path, env: &HashMap<String, String>) -> Result<HashMap<String, String>> {
+    let mut variables = HashMap::new();
+    let dotenv = working_dir.join(".env");
+    if dotenv.is_file() {
+        let content = std::fs::read_to_string(&dotenv)?;
+        variables.extend(parse_env_file(&content));
+    }
+    variables.extend(env.iter().map(|(k, v)| (k.clone(), v.clone())));
+    Ok(variables)
+}
+
+// 先解析 YAML 再对其中的字符串插值，注释不参与插值，变量的值也不会改变文件结构
+fn interpolate_value(value: serde_yaml::Value, variables: &HashMap<String, String>) -> Result<serde_yaml::Value> {
+    use serde_yaml::Value;
+    Ok(match value {
+        Value::String(s) if s.contains('$') => typed_scalar(interpolate(&s, variables)?),
+        Value::Sequence(items) => Value::Sequence(
+            items.into_iter().map(|v| interpolate_value(v, variables)).collect::<Result<_>>()?,
+        ),
+        Value::Mapping(map) => {
+            let mut result = serde_yaml::Mapping::new();
+            for (key, value) in map {
+                let key = match key {
+                    Value::String(s) if s.contains('$') => Value::String(interpolate(&s, variables)?),
+                    key => key,
+                };
+                result.insert(key, interpolate_value(value, variables)?);
+            }
+            Value::Mapping(result)
+        }
+        Value::Tagged(mut tagged) => {
+            tagged.value = interpolate_value(tagged.value, variables)?;
+            Value::Tagged(tagged)
+        }
+        value => value,
+    })
+}
+
+// 插值后的 `true`、`8080` 等按布尔值和数字处理，与直接写在文件中一致；
+// 写法不规范的数字（如 `1.10`、`0755`）保持为字符串，避免改变原值
+fn typed_scalar(text: String) -> serde_yaml::Value {
+    match serde_yaml::from_str::<serde_yaml::Value>(&text) {
+        Ok(serde_yaml::Value::Bool(b)) if b.to_string() == text => serde_yaml::Value::Bool(b),
+        Ok(serde_yaml::Value::Number(n)) if n.to_string() == text => serde_yaml::Value::Number(n),
+        _ => serde_yaml::Value::String(text),
+    }
+}
+
+// 支持 $VAR、${VAR}、${VAR:-默认值}、${VAR-默认值}、${VAR:?错误}、${VAR?错误}，$$ 表示 $
+fn interpolate(content: &str, variables: &HashMap<String, String>) -> Result<String> {
+    let mut result = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            result.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('$') => {
+                chars.next();
+                result.push('$');
+            }
+            Some('{') => {
+                chars.next();
+                let mut expr = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    expr.push(c);
+                }
+                if !closed {
+                    return Err(anyhow::anyhow!("变量没有闭合: ${{{}", expr));
+                }
+                result.push_str(&expand_variable(&expr, variables)?);
+            }
+            Some(c) if c.is_ascii_alphabetic() || *c == '_' => {
+                let mut name = String::new();
+                while let Some(c) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || *c == '_' {
+                        name.push(*c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                result.push_str(variables.get(&name).map(String::as_str).unwrap_or_default());
+            }
+            _ => result.push('$'),
+        }
+    }
+    Ok(result)
+}
+
+fn expand_variable(expr: &str, variables: &HashMap<String, String>) -> Result<String> {
+    let end = expr.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(expr.len());
+    let (name, rest) = expr.split_at(end);
+    let value = variables.get(name);
+    let non_empty = value.filter(|v| !v.is_empty());
+
+    if let Some(default) = rest.strip_prefix(":-") {
+        return Ok(non_empty.cloned().unwrap_or_else(|| default.to_string()));
+    }
+    if let Some(default) = rest.strip_prefix('-') {
+        return Ok(value.cloned().unwrap_or_else(|| default.to_string()));
+    }
+    if let Some(message) = rest.strip_prefix(":?") {
+        return non_empty.cloned().ok_or_else(|| anyhow::anyhow!("缺少变量 {}: {}", name, message));
+    }
+    if let Some(message) = rest.strip_prefix('?') {
+        return value.cloned().ok_or_else(|| anyhow::anyhow!("缺少变量 {}: {}", name, message));
+    }
+    if !rest.is_empty() {
+        return Err(anyhow::anyhow!("不支持的变量写法: ${{{}}}", expr));
+    }
+    Ok(value.cloned().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> HashMap<String, String> {
+        [("NAME", "web"), ("EMPTY", ""), ("PORT", "8080")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn load(content: &str) -> Result<ComposeProject> {
+        parse("test", content, Path::new("/nonexistent"), &HashMap::new())
+    }
+
+    #[test]
+    fn interpolation() {
+        let cases = [
+            ("$NAME", "web"),
+            ("${NAME}-1", "web-1"),
+            ("$NAME.local", "web.local"),
+            ("${MISSING}", ""),
+            ("${EMPTY:-default}", "default"),
+            ("${MISSING:-default}", "default"),
+            ("${EMPTY-default}", ""),
+            ("${MISSING-default}", "default"),
+            ("${NAME:?required}", "web"),
+            ("${EMPTY?required}", ""),
+            ("$$NAME", "$NAME"),
+            ("cost $5", "cost $5"),
+            ("end$", "end$"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(interpolate(input, &variables()).unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn interpolation_errors() {
+        for input in ["${MISSING:?需要设置}", "${EMPTY:?需要设置}", "${MISSING?需要设置}", "${NAME", "${NAME/a/b}"] {
+            assert!(interpolate(input, &variables()).is_err(), "{}", input);
+        }
+        let error = interpolate("${MISSING:?需要设置}", &variables()).unwrap_err().to_string();
+        assert!(error.contains("MISSING") && error.contains("需要设置"), "{}", error);
+    }
+
+    #[test]
+    fn interpolated_scalars() {
+        use serde_yaml::Value;
+        assert_eq!(typed_scalar("true".to_string()), Value::Bool(true));
+        assert_eq!(typed_scalar("8080".to_string()), Value::Number(8080.into()));
+        for text in ["True", "1.10", "0755", "1e3", "web", ""] {
+            assert_eq!(typed_scalar(text.to_string()), Value::String(text.to_string()), "{}", text);
+        }
+    }
+
+    #[test]
+    fn interpolation_skips_comments() {
+        let project = load("
+services:
+  web:
+    # ${MISSING:?不会检查注释}
+    image: nginx:${TAG:-alpine}
+").unwrap();
+        assert_eq!(project.services[0].image, "nginx:alpine");
+    }
+
+    // (容器端口, 主机 IP, 主机端口)
+    type Binding = (String, Option<String>, Option<String>);
+
+    fn port(text: &str) -> Result<Vec<Binding>> {
+        Ok(parse_port(RawPort::Short(Scalar::String(text.to_string())))?
+            .into_iter()
+            .map(|(port, binding)| (port, binding.host_ip, binding.host_port))
+            .collect())
+    }
+
+    fn binding(port: &str, ip: &str, host: Option<&str>) -> Binding {
+        (port.to_string(), Some(ip.to_string()), host.map(str::to_string))
+    }
+
+    #[test]
+    fn ports() {
+        let cases: [(&str, Vec<_>); 6] = [
+            ("80", vec![binding("80/tcp", "0.0.0.0", None)]),
+            ("8080:80", vec![binding("80/tcp", "0.0.0.0", Some("8080"))]),
+            ("53:53/udp", vec![binding("53/udp", "0.0.0.0", Some("53"))]),
+            ("127.0.0.1:8080:80", vec![binding("80/tcp", "127.0.0.1", Some("8080"))]),
+            ("[::1]:8080:80", vec![binding("80/tcp", "::1", Some("8080"))]),
+            ("8000-8001:9000-9001", vec![
+                binding("9000/tcp", "0.0.0.0", Some("8000")),
+                binding("9001/tcp", "0.0.0.0", Some("8001")),
+            ]),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(port(input).unwrap(), expected, "{}", input);
+        }
+        for input in ["8000-8002:9000-9001", "9001-9000", "http", "70000", "a:b:c:d"] {
+            assert!(port(input).is_err(), "{}", input);
+        }
+        let long = parse_port(RawPort::Long {
+            target: 80,
+            published: Some(Scalar::Integer(8080)),
+            host_ip: None,
+            protocol: None,
+        }).unwrap();
+        assert_eq!(long[0].0, "80/tcp");
+        assert_eq!(long[0].1.host_port.as_deref(), Some("8080"));
+    }
+
+    #[test]
+    fn durations() {
+        let cases = [
+            ("30s", 30_000_000_000),
+            ("1m30s", 90_000_000_000),
+            ("1h", 3_600_000_000_000),
+            ("1.5s", 1_500_000_000),
+            ("500ms", 500_000_000),
+            ("10us", 10_000),
+            ("10", 10_000_000_000),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_duration(input).unwrap(), expected, "{}", input);
+        }
+        for input in ["5x", "s", "1d"] {
+            assert!(parse_duration(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn commands() {
+        let cases: [(&str, &[&str]); 5] = [
+            ("echo hello", &["echo", "hello"]),
+            ("  sh  -c 'echo $HOME'  ", &["sh", "-c", "echo $HOME"]),
+            (r#"echo "a \"b\" c" d\ e"#, &["echo", "a \"b\" c", "d e"]),
+            ("echo ''", &["echo", ""]),
+            ("", &[]),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(split_command(input).unwrap(), expected, "{}", input);
+        }
+        assert!(split_command("echo 'unterminated").is_err());
+    }
+
+    #[test]
+    fn env_file() {
+        let parsed = parse_env_file("
+# 注释
+A=1
+export B = \"two words\"
+C='single'
+D=
+invalid line
+");
+        let expected = [("A", "1"), ("B", "two words"), ("C", "single"), ("D", "")]
+            .map(|(k, v)| (k.to_string(), v.to_string()));
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn dependency_order() {
+        let project = load("
+services:
+  web:
+    image: nginx
+    depends_on: [api]
+  api:
+    image: api
+    depends_on:
+      db:
+        condition: service_healthy
+  db:
+    image: postgres
+").unwrap();
+        let order: Vec<&str> = project.services.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(order, ["db", "api", "web"]);
+    }
+
+    #[test]
+    fn dependency_cycle() {
+        let error = load("
+services:
+  a:
+    image: a
+    depends_on: [b]
+  b:
+    image: b
+    depends_on: [c]
+  c:
+    image: c
+    depends_on: [a]
+  d:
+    image: d
+").unwrap_err().to_string();
+        assert!(error.contains("循环依赖") && error.contains("a, b, c"), "{}", error);
+    }
+}
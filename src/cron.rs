@@ -0,0 +1,261 @@
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use anyhow::Result;
+
+// 最多向后查找的天数，足够覆盖只在闰年 2 月 29 日执行的表达式
+const SEARCH_DAYS: i64 = 366 * 8;
+
+const MONTHS: [&str; 12] = ["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// 标准的 5 段 cron 表达式（分 时 日 月 周），也支持 `@daily` 这类简写
+#[derive(Clone, Debug)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // 日和周都有限制时满足其一即可，与 Vixie cron 相同
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronExpr {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let expr = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(anyhow::anyhow!("cron 表达式需要 5 段（分 时 日 月 周）: {}", expr));
+        }
+        let mut weekdays = parse_field(fields[4], 0, 7, &WEEKDAYS)?;
+        // 0 和 7 都表示周日
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59, &[])?,
+            hours: parse_field(fields[1], 0, 23, &[])?,
+            days: parse_field(fields[2], 1, 31, &[])?,
+            months: parse_field(fields[3], 1, 12, &MONTHS)?,
+            weekdays,
+            days_restricted: !fields[2].starts_with('*'),
+            weekdays_restricted: !fields[4].starts_with('*'),
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            (true, false) => day,
+            (false, true) => weekday,
+            (false, false) => true,
+        }
+    }
+
+    /// `after` 之后（不含）的下一次执行时间。夏令时跳过的时刻顺延到跳过之后，重复的时刻只执行第一次
+    pub fn next_after(&self, after: DateTime<Utc>, zone: &Zone) -> Option<DateTime<Utc>> {
+        let start = zone.to_local(after);
+        let mut date = start.date();
+        for _ in 0..SEARCH_DAYS {
+            if self.matches_date(date) {
+                for hour in (0..24).filter(|h| self.hours & (1 << h) != 0) {
+                    for minute in (0..60).filter(|m| self.minutes & (1 << m) != 0) {
+                        let local = date.and_hms_opt(hour, minute, 0)?;
+                        if let Some(instant) = zone.resolve(local).filter(|t| *t > after) {
+                            return Some(instant);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+fn parse_value(text: &str, min: u32, max: u32, names: &[&str]) -> Result<u32> {
+    let upper = text.to_ascii_uppercase();
+    if let Some(index) = names.iter().position(|n| *n == upper) {
+        // 月份名从 1 开始，星期名从 0 开始
+        return Ok(index as u32 + min);
+    }
+    let value: u32 = text.parse().map_err(|_| anyhow::anyhow!("无法解析 cron 字段的值: {}", text))?;
+    if value < min || value > max {
+        return Err(anyhow::anyhow!("cron 字段的值 {} 超出范围 {}-{}", value, min, max));
+    }
+    Ok(value)
+}
+
+// 返回位图，第 n 位表示值 n
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| anyhow::anyhow!("无法解析 cron 步长: {}", step))?;
+                if step == 0 {
+                    return Err(anyhow::anyhow!("cron 步长不能为 0"));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max, names)?, parse_value(end, min, max, names)?)
+        } else {
+            let value = parse_value(range, min, max, names)?;
+            // `5/15` 表示从 5 开始每 15 个单位
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start > end {
+            return Err(anyhow::anyhow!("cron 范围 {} 的起点大于终点", range));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// 计划使用的时区：管理器所在主机的时区、固定偏移或 IANA 时区名
+#[derive(Clone, Debug)]
+pub enum Zone {
+    Local,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl Zone {
+    /// 空字符串和 `local` 为主机时区；支持 `UTC`、`+08:00` 和 `Asia/Shanghai` 这样的名称
+    pub fn parse(name: &str) -> Result<Self> {
+        let name = name.trim();
+        if name.is_empty() || name.eq_ignore_ascii_case("local") {
+            return Ok(Zone::Local);
+        }
+        if name.eq_ignore_ascii_case("utc") || name == "Z" {
+            return Ok(Zone::Fixed(FixedOffset::east_opt(0).unwrap()));
+        }
+        if name.starts_with('+') || name.starts_with('-') {
+            let offset: FixedOffset = name.parse()
+                .map_err(|_| anyhow::anyhow!("无法解析时区偏移: {}", name))?;
+            return Ok(Zone::Fixed(offset));
+        }
+        name.parse::<Tz>()
+            .map(Zone::Named)
+            .map_err(|_| anyhow::anyhow!("未知的时区: {}", name))
+    }
+
+    pub fn to_local(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Local => instant.with_timezone(&Local).naive_local(),
+            Zone::Fixed(offset) => instant.with_timezone(offset).naive_local(),
+            Zone::Named(tz) => instant.with_timezone(tz).naive_local(),
+        }
+    }
+
+    /// 本地时间对应的时刻。重复的时间取较早的一个；
+    /// 夏令时跳过的时间按切换前的偏移换算，即顺延到跳过之后
+    pub fn resolve(&self, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Zone::Local => resolve_in(&Local, local),
+            Zone::Fixed(offset) => resolve_in(offset, local),
+            Zone::Named(tz) => resolve_in(tz, local),
+        }
+    }
+}
+
+fn resolve_in<T: TimeZone>(zone: &T, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    match zone.from_local_datetime(&local) {
+        LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => Some(t.with_timezone(&Utc)),
+        LocalResult::None => {
+            let before = zone.from_local_datetime(&(local - Duration::days(1))).earliest()?;
+            Some((local - Duration::seconds(before.offset().fix().local_minus_utc() as i64)).and_utc())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    fn next(expr: &str, zone: &str, after: &str) -> DateTime<Utc> {
+        CronExpr::parse(expr).unwrap()
+            .next_after(utc(after), &Zone::parse(zone).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn skipped_time_runs_after_the_gap() {
+        // 2024-03-10 02:00 EST 直接跳到 03:00 EDT，02:30 按切换前的偏移执行，即 03:30 EDT
+        assert_eq!(next("30 2 * * *", "America/New_York", "2024-03-10T00:00:00Z"), utc("2024-03-10T07:30:00Z"));
+        assert_eq!(next("30 2 * * *", "America/New_York", "2024-03-10T07:30:00Z"), utc("2024-03-11T06:30:00Z"));
+    }
+
+    #[test]
+    fn repeated_time_runs_once() {
+        // 2024-11-03 01:00-02:00 出现两次，只在第一次（EDT）执行
+        assert_eq!(next("30 1 * * *", "America/New_York", "2024-11-03T04:00:00Z"), utc("2024-11-03T05:30:00Z"));
+        assert_eq!(next("30 1 * * *", "America/New_York", "2024-11-03T05:30:00Z"), utc("2024-11-04T06:30:00Z"));
+    }
+
+    #[test]
+    fn hourly_across_fall_back() {
+        // 重复的 02:00 CET 不再执行，下一次为 03:00 CET
+        assert_eq!(next("0 * * * *", "Europe/Berlin", "2024-10-26T23:00:00Z"), utc("2024-10-27T00:00:00Z"));
+        assert_eq!(next("0 * * * *", "Europe/Berlin", "2024-10-27T00:00:00Z"), utc("2024-10-27T02:00:00Z"));
+    }
+
+    #[test]
+    fn day_of_month_or_day_of_week() {
+        // 2024-10-13 是周日，日和周同时指定时满足其一即可
+        assert_eq!(next("0 0 13 * 5", "UTC", "2024-10-01T00:00:00Z"), utc("2024-10-04T00:00:00Z"));
+        assert_eq!(next("0 0 13 * 5", "UTC", "2024-10-11T00:00:00Z"), utc("2024-10-13T00:00:00Z"));
+        assert_eq!(next("0 0 13 * 5", "UTC", "2024-10-13T00:00:00Z"), utc("2024-10-18T00:00:00Z"));
+        assert_eq!(next("0 0 13 * *", "UTC", "2024-10-01T00:00:00Z"), utc("2024-10-13T00:00:00Z"));
+        assert_eq!(next("0 0 * * fri", "UTC", "2024-10-01T00:00:00Z"), utc("2024-10-04T00:00:00Z"));
+        // 星期字段以 * 开头时不算限制
+        assert_eq!(next("0 0 13 * */2", "UTC", "2024-10-01T00:00:00Z"), utc("2024-10-13T00:00:00Z"));
+    }
+
+    #[test]
+    fn leap_day() {
+        assert_eq!(next("0 0 29 2 *", "UTC", "2025-01-01T00:00:00Z"), utc("2028-02-29T00:00:00Z"));
+    }
+
+    #[test]
+    fn fixed_offset() {
+        assert_eq!(next("@daily", "+08:00", "2024-01-01T00:00:00Z"), utc("2024-01-01T16:00:00Z"));
+    }
+
+    #[test]
+    fn parse_fields() {
+        let expr = CronExpr::parse("5/15 9-17/4 * jan,jul 0").unwrap();
+        assert_eq!(expr.minutes, 1 << 5 | 1 << 20 | 1 << 35 | 1 << 50);
+        assert_eq!(expr.hours, 1 << 9 | 1 << 13 | 1 << 17);
+        assert_eq!(expr.months, 1 << 1 | 1 << 7);
+        assert_eq!(CronExpr::parse("0 0 * * 7").unwrap().weekdays, 1);
+        assert!(CronExpr::parse("0 0 * *").is_err());
+        assert!(CronExpr::parse("*/0 * * * *").is_err());
+        assert!(CronExpr::parse("0 24 * * *").is_err());
+        assert!(Zone::parse("Mars/Olympus").is_err());
+    }
+}
@@ -14,7 +14,7 @@ lazy_static! {
 pub struct DockerClient;
 
 impl DockerClient {
-    fn get_docker_socket_path() -> String {
+    pub(crate) fn get_docker_socket_path() -> String {
         if Path::new("/var/run/docker.sock").exists() {
             return "/var/run/docker.sock".to_string();
         }
@@ -0,0 +1,63 @@
+use serde::Serialize;
+
+/// 接口错误的类别，决定返回的 HTTP 状态码
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiErrorKind {
+    NotFound,
+    /// 请求参数不正确
+    Invalid,
+    /// 与当前状态冲突，如名称已存在、容器没有运行或正在被使用
+    Conflict,
+    /// 功能没有开启，或用户、容器不允许执行该操作
+    Forbidden,
+    /// 超过大小上限
+    TooLarge,
+    /// 同时进行的操作过多
+    Busy,
+}
+
+/// 各模块共用的业务错误，Docker 和 IO 错误仍按原样返回
+#[derive(Debug)]
+pub struct ApiError {
+    pub kind: ApiErrorKind,
+    pub message: String,
+}
+
+impl ApiError {
+    pub(crate) fn new(kind: ApiErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+
+    pub(crate) fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ApiErrorKind::NotFound, message)
+    }
+
+    pub(crate) fn invalid(message: impl Into<String>) -> Self {
+        Self::new(ApiErrorKind::Invalid, message)
+    }
+
+    pub(crate) fn conflict(message: impl Into<String>) -> Self {
+        Self::new(ApiErrorKind::Conflict, message)
+    }
+
+    pub(crate) fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(ApiErrorKind::Forbidden, message)
+    }
+
+    pub(crate) fn too_large(message: impl Into<String>) -> Self {
+        Self::new(ApiErrorKind::TooLarge, message)
+    }
+
+    pub(crate) fn busy(message: impl Into<String>) -> Self {
+        Self::new(ApiErrorKind::Busy, message)
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
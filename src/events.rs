@@ -0,0 +1,274 @@
+use bollard::models::{EventMessage, EventMessageTypeEnum};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::sync::Mutex;
+use anyhow::Result;
+use crate::storage;
+
+pub(crate) const SETTINGS_FILE: &str = "events.json";
+// 按天保存在数据目录下的 events/YYYY-MM-DD.jsonl
+const EVENTS_DIR: &str = "events";
+const DEFAULT_PER_PAGE: usize = 50;
+const MAX_PER_PAGE: usize = 500;
+
+/// 事件历史设置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventSettings {
+    /// 保留最近多少天的事件
+    pub retention_days: u32,
+}
+
+impl Default for EventSettings {
+    fn default() -> Self {
+        Self { retention_days: 7 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSource {
+    /// Docker 守护进程产生的事件
+    Docker,
+    /// 管理器做出的决定，如崩溃重启、OOM 处理
+    Manager,
+}
+
+/// 一条历史事件
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub timestamp: DateTime<Local>,
+    pub source: EventSource,
+    /// Docker 事件的对象类型，如 container、image、network；管理器的决定为 container
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Docker 事件的属性（不含标签），或管理器决定的参数和结果
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub details: Value,
+}
+
+impl EventRecord {
+    pub fn from_docker(event: &EventMessage) -> Option<Self> {
+        let action = event.action.clone()?;
+        let actor = event.actor.clone().unwrap_or_default();
+        let attributes = actor.attributes.unwrap_or_default();
+        let timestamp = event.time_nano
+            .map(|nanos| Local.timestamp_nanos(nanos))
+            .or_else(|| event.time.and_then(|secs| Local.timestamp_opt(secs, 0).single()))
+            .unwrap_or_else(Local::now);
+        // 属性里混有容器的全部标签，只保留 Docker 自己的属性（标签名通常带有点号）
+        let details: serde_json::Map<String, Value> = attributes.iter()
+            .filter(|(key, _)| !key.contains('.') && key.as_str() != "name")
+            .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+            .collect();
+        Some(Self {
+            timestamp,
+            source: EventSource::Docker,
+            kind: event.typ.map(|t| t.to_string()).unwrap_or_else(|| EventMessageTypeEnum::EMPTY.to_string()),
+            action,
+            id: actor.id,
+            name: attributes.get("name").cloned(),
+            details: if details.is_empty() { Value::Null } else { Value::Object(details) },
+        })
+    }
+
+    /// 管理器对容器做出的决定
+    pub fn decision<T, E: std::fmt::Display>(action: &str, container: &str, params: &Value, result: &std::result::Result<T, E>) -> Self {
+        let mut details = params.clone();
+        if let (Value::Object(map), Err(e)) = (&mut details, result) {
+            map.insert("error".to_string(), Value::String(e.to_string()));
+        }
+        Self {
+            timestamp: Local::now(),
+            source: EventSource::Manager,
+            kind: "container".to_string(),
+            action: action.to_string(),
+            id: params.get("container_id").and_then(|id| id.as_str()).map(str::to_string),
+            name: Some(container.to_string()),
+            details,
+        }
+    }
+}
+
+/// 事件历史的查询条件
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct EventQuery {
+    /// 容器名，或容器 ID 的前缀
+    pub container: Option<String>,
+    /// 逗号分隔的操作，事件操作以其中之一开头即匹配，如 `start,die,health_status`
+    pub action: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub source: Option<EventSource>,
+    pub since: Option<DateTime<Local>>,
+    pub until: Option<DateTime<Local>>,
+    /// 从 1 开始的页码
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+impl EventQuery {
+    fn matches(&self, record: &EventRecord) -> bool {
+        self.container.as_ref().is_none_or(|container| {
+            record.name.as_deref() == Some(container.as_str())
+                || record.id.as_ref().is_some_and(|id| id.starts_with(container.as_str()))
+        })
+            && self.action.as_ref().is_none_or(|actions| {
+                actions.split(',')
+                    .map(str::trim)
+                    .filter(|a| !a.is_empty())
+                    .any(|a| record.action.starts_with(a))
+            })
+            && self.kind.as_ref().is_none_or(|kind| record.kind == *kind)
+            && self.source.is_none_or(|source| record.source == source)
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp < until)
+    }
+
+    // 需要读取的日期范围
+    fn covers(&self, date: NaiveDate) -> bool {
+        self.since.is_none_or(|since| date >= since.date_naive())
+            && self.until.is_none_or(|until| date <= until.date_naive())
+    }
+}
+
+/// 一页查询结果，最新的事件在前
+#[derive(Clone, Debug, Serialize)]
+pub struct EventPage {
+    pub total: usize,
+    pub page: usize,
+    pub per_page: usize,
+    pub events: Vec<EventRecord>,
+}
+
+pub struct EventStore {
+    settings: Mutex<EventSettings>,
+    // 最近一次清理过期事件的日期，跨天后写入时再清理
+    last_pruned: Mutex<Option<NaiveDate>>,
+    lock: Mutex<()>,
+}
+
+impl EventStore {
+    pub fn load() -> Result<Self> {
+        let settings: EventSettings = storage::load_json(SETTINGS_FILE)?;
+        Ok(Self {
+            settings: Mutex::new(settings),
+            last_pruned: Mutex::new(None),
+            lock: Mutex::new(()),
+        })
+    }
+
+    pub fn settings(&self) -> EventSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update_settings(&self, settings: EventSettings) -> Result<()> {
+        if settings.retention_days == 0 {
+            return Err(anyhow::anyhow!("保留天数至少为 1"));
+        }
+        storage::save_json(SETTINGS_FILE, &settings)?;
+        *self.settings.lock().unwrap() = settings;
+        println!("已更新事件历史设置");
+        self.prune(Local::now().date_naive());
+        Ok(())
+    }
+
+    /// 追加一条事件；写入失败只打印错误
+    pub fn record(&self, record: EventRecord) {
+        let today = Local::now().date_naive();
+        let due = self.last_pruned.lock().unwrap().is_none_or(|date| date != today);
+        if due {
+            self.prune(today);
+        }
+
+        let _guard = self.lock.lock().unwrap();
+        let name = format!("{}/{}.jsonl", EVENTS_DIR, record.timestamp.format("%Y-%m-%d"));
+        let result = serde_json::to_string(&record)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| storage::append_line(&name, &line));
+        if let Err(e) = result {
+            eprintln!("保存事件失败: {}", e);
+        }
+    }
+
+    /// 删除超过保留天数的事件文件
+    fn prune(&self, today: NaiveDate) {
+        *self.last_pruned.lock().unwrap() = Some(today);
+        let retention = self.settings().retention_days as u64;
+        let Some(oldest) = today.checked_sub_days(chrono::Days::new(retention.saturating_sub(1))) else {
+            return;
+        };
+        let _guard = self.lock.lock().unwrap();
+        for (date, path) in Self::files() {
+            if date < oldest {
+                match fs::remove_file(&path) {
+                    Ok(()) => println!("已删除过期的事件记录 {}", path.display()),
+                    Err(e) => eprintln!("删除事件记录 {} 失败: {}", path.display(), e),
+                }
+            }
+        }
+    }
+
+    pub async fn query(&self, query: &EventQuery) -> Result<EventPage> {
+        let query = query.clone();
+        tokio::task::spawn_blocking(move || Self::read_page(&query)).await?
+    }
+
+    // 在阻塞线程中读取，不持有写入锁；正在写入的最后一行可能不完整，解析失败时跳过
+    fn read_page(query: &EventQuery) -> Result<EventPage> {
+        let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+        let page = query.page.unwrap_or(1).max(1);
+
+        let mut files = Self::files();
+        files.retain(|(date, _)| query.covers(*date));
+        files.sort_by_key(|(date, _)| std::cmp::Reverse(*date));
+
+        let mut events = Vec::new();
+        for (_, path) in files {
+            // 文件可能刚好因过期被删除
+            let Ok(file) = File::open(&path) else {
+                continue;
+            };
+            let mut day = Vec::new();
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                // 进程中断可能留下写了一半的最后一行
+                if let Ok(record) = serde_json::from_str::<EventRecord>(&line) {
+                    if query.matches(&record) {
+                        day.push(record);
+                    }
+                }
+            }
+            // 同一秒内的事件按写入顺序排列
+            day.reverse();
+            events.extend(day);
+        }
+        events.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+
+        let total = events.len();
+        let events = events.into_iter().skip(page.saturating_sub(1).saturating_mul(per_page)).take(per_page).collect();
+        Ok(EventPage { total, page, per_page, events })
+    }
+
+    fn files() -> Vec<(NaiveDate, std::path::PathBuf)> {
+        let Ok(entries) = fs::read_dir(storage::data_path(EVENTS_DIR)) else {
+            return Vec::new();
+        };
+        entries.flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let date = path.file_name()?.to_str()?.strip_suffix(".jsonl")?;
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+                Some((date, path))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,400 @@
+use bollard::Docker;
+use bollard::container::LogOutput;
+use bollard::exec::{CreateExecOptions, ResizeExecOptions, StartExecOptions, StartExecResults};
+use futures::{Stream, StreamExt};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::AsyncWrite;
+use anyhow::Result;
+use crate::backups::LABEL_HELPER;
+use crate::storage;
+use crate::error::ApiError;
+
+pub(crate) const SETTINGS_FILE: &str = "exec.json";
+// 一次性命令每个输出流最多保留的字节数
+const MAX_OUTPUT: usize = 1024 * 1024;
+const MAX_TIMEOUT: u64 = 3600;
+
+/// 设置为 `false` 时不允许进入该容器的终端
+pub const LABEL_EXEC: &str = "docker-manager.exec";
+
+/// Web 终端设置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecSettings {
+    /// 默认关闭，需要显式开启
+    pub enabled: bool,
+    /// 未指定时使用的 shell
+    pub shell: String,
+    /// 未指定时使用的用户，为空表示容器的默认用户
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// 允许使用终端的用户（由反向代理传入），为空表示不限制
+    pub allowed_users: Vec<String>,
+    /// 没有输入超过该时间（秒）后断开
+    pub idle_timeout: u64,
+    /// 同时打开的终端数上限
+    pub max_sessions: usize,
+    /// 是否允许通过接口执行任意命令；关闭时只能执行 `actions` 中定义的操作
+    pub allow_commands: bool,
+    /// 一次性命令的默认超时（秒）
+    pub command_timeout: u64,
+    /// 预先定义的操作，按容器标签限定可以在哪些容器上执行
+    pub actions: Vec<ExecAction>,
+}
+
+/// 预先定义的一次性命令
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExecAction {
+    pub name: String,
+    /// 容器需要带有的标签，`key` 或 `key=value`，为空表示所有容器
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub command: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<String>,
+    /// 超时（秒），为空时使用 `command_timeout`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl ExecAction {
+    fn applies_to(&self, labels: &HashMap<String, String>) -> bool {
+        match self.label.as_deref().map(|l| l.split_once('=')) {
+            None => true,
+            Some(Some((key, value))) => labels.get(key.trim()).is_some_and(|v| v == value.trim()),
+            Some(None) => labels.contains_key(self.label.as_deref().unwrap_or_default().trim()),
+        }
+    }
+}
+
+impl Default for ExecSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            shell: "/bin/sh".to_string(),
+            user: None,
+            allowed_users: Vec::new(),
+            idle_timeout: 900,
+            max_sessions: 5,
+            allow_commands: false,
+            command_timeout: 60,
+            actions: Vec::new(),
+        }
+    }
+}
+
+/// 打开终端的参数，为空的使用设置中的默认值
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TerminalOptions {
+    pub shell: Option<String>,
+    pub user: Option<String>,
+    pub cols: Option<u16>,
+    pub rows: Option<u16>,
+}
+
+/// 一次性命令的请求，`action` 和 `command` 二选一
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandRequest {
+    /// 预先定义的操作名
+    pub action: Option<String>,
+    /// 任意命令，需要开启 `allow_commands`
+    pub command: Option<Vec<String>>,
+    pub user: Option<String>,
+    pub working_dir: Option<String>,
+    pub env: Vec<String>,
+    /// 超时（秒），不能超过操作本身的超时
+    pub timeout: Option<u64>,
+}
+
+/// 一次性命令的执行结果
+#[derive(Clone, Debug, Serialize)]
+pub struct CommandOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    pub command: Vec<String>,
+    /// 超时时为空，命令可能仍在容器中运行
+    pub exit_code: Option<i64>,
+    pub stdout: String,
+    pub stderr: String,
+    pub timed_out: bool,
+    /// 输出超过上限被截断
+    pub truncated: bool,
+    pub duration_ms: u64,
+}
+
+/// 一个已经启动、带 TTY 的 exec 实例
+pub struct Terminal {
+    pub exec_id: String,
+    pub shell: String,
+    pub user: Option<String>,
+    pub output: Pin<Box<dyn Stream<Item = Result<LogOutput, bollard::errors::Error>> + Send>>,
+    pub input: Pin<Box<dyn AsyncWrite + Send>>,
+    _session: SessionGuard,
+}
+
+// 终端关闭时释放占用的名额
+struct SessionGuard(Arc<AtomicUsize>);
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub struct ExecManager {
+    docker: Docker,
+    settings: Mutex<ExecSettings>,
+    sessions: Arc<AtomicUsize>,
+}
+
+impl ExecManager {
+    pub fn load(docker: Docker) -> Result<Self> {
+        let settings: ExecSettings = storage::load_json(SETTINGS_FILE)?;
+        Ok(Self {
+            docker,
+            settings: Mutex::new(settings),
+            sessions: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn settings(&self) -> ExecSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update_settings(&self, settings: ExecSettings) -> Result<()> {
+        if settings.shell.trim().is_empty() {
+            return Err(anyhow::anyhow!("shell 不能为空"));
+        }
+        if settings.idle_timeout == 0 || settings.max_sessions == 0 || settings.command_timeout == 0 {
+            return Err(anyhow::anyhow!("超时和终端数上限必须大于 0"));
+        }
+        let mut names = std::collections::HashSet::new();
+        for action in &settings.actions {
+            if action.name.trim().is_empty() || action.command.iter().all(|arg| arg.is_empty()) {
+                return Err(anyhow::anyhow!("操作的名称和命令不能为空"));
+            }
+            if !names.insert((action.name.as_str(), action.label.as_deref())) {
+                return Err(anyhow::anyhow!("操作 {} 重复定义", action.name));
+            }
+        }
+        storage::save_json(SETTINGS_FILE, &settings)?;
+        *self.settings.lock().unwrap() = settings;
+        println!("已更新终端设置");
+        Ok(())
+    }
+
+    /// 检查终端是否开启，以及用户是否在允许列表中
+    pub fn authorize(&self, user: Option<&str>) -> Result<()> {
+        let settings = self.settings();
+        if !settings.enabled {
+            return Err(ApiError::forbidden("Web 终端未开启").into());
+        }
+        Self::check_user(&settings, user)
+    }
+
+    fn check_user(settings: &ExecSettings, user: Option<&str>) -> Result<()> {
+        if !settings.allowed_users.is_empty()
+            && !user.is_some_and(|u| settings.allowed_users.iter().any(|allowed| allowed == u))
+        {
+            return Err(ApiError::forbidden("当前用户不允许在容器中执行命令").into());
+        }
+        Ok(())
+    }
+
+    // 检查容器是否允许进入且正在运行，返回容器标签
+    async fn inspect_target(&self, container: &str) -> Result<HashMap<String, String>> {
+        let inspect = self.docker.inspect_container(container, None).await?;
+        let labels = inspect.config.as_ref().and_then(|c| c.labels.clone()).unwrap_or_default();
+        if labels.contains_key(LABEL_HELPER)
+            || labels.get(LABEL_EXEC).is_some_and(|v| v.eq_ignore_ascii_case("false") || v == "0")
+        {
+            return Err(ApiError::forbidden(format!("容器 {} 不允许执行命令", container)).into());
+        }
+        if !inspect.state.as_ref().and_then(|s| s.running).unwrap_or(false) {
+            return Err(ApiError::conflict(format!("容器 {} 没有运行", container)).into());
+        }
+        Ok(labels)
+    }
+
+    /// 可以在该容器上执行的预定义操作
+    pub async fn actions(&self, container: &str) -> Result<Vec<ExecAction>> {
+        let inspect = self.docker.inspect_container(container, None).await?;
+        let labels = inspect.config.and_then(|c| c.labels).unwrap_or_default();
+        Ok(self.settings().actions.into_iter().filter(|a| a.applies_to(&labels)).collect())
+    }
+
+    /// 运行一次性命令并收集输出，超时后返回已有的输出
+    pub async fn run_command(&self, container: &str, request: &CommandRequest, user: Option<&str>) -> Result<CommandOutput> {
+        self.check_command_user(user)?;
+        self.execute(container, request).await
+    }
+
+    /// 检查用户是否在允许列表中，执行命令和读写终端设置前调用
+    pub fn check_command_user(&self, user: Option<&str>) -> Result<()> {
+        Self::check_user(&self.settings(), user)
+    }
+
+    async fn execute(&self, container: &str, request: &CommandRequest) -> Result<CommandOutput> {
+        let settings = self.settings();
+        let labels = self.inspect_target(container).await?;
+
+        let (action, mut command, run_as, working_dir, env, limit) = match (&request.action, &request.command) {
+            (Some(name), None) => {
+                let action = settings.actions.iter()
+                    .find(|a| a.name == *name && a.applies_to(&labels))
+                    .ok_or_else(|| ApiError::not_found(format!("容器 {} 没有操作 {}", container, name)))?;
+                // 预定义操作的命令、用户、工作目录和环境变量都以定义为准，请求只能缩短超时；
+                // 否则可以通过 LD_PRELOAD、PATH 等变量改变命令的行为
+                let overridden = request.user.as_deref().is_some_and(|u| !u.is_empty())
+                    || request.working_dir.as_deref().is_some_and(|d| !d.is_empty())
+                    || !request.env.is_empty();
+                if overridden {
+                    return Err(ApiError::invalid("预定义操作不能指定 user、working_dir 或 env").into());
+                }
+                (
+                    Some(name.clone()),
+                    action.command.clone(),
+                    action.user.clone(),
+                    action.working_dir.clone(),
+                    action.env.clone(),
+                    action.timeout.unwrap_or(settings.command_timeout),
+                )
+            }
+            (None, Some(command)) => {
+                if !settings.allow_commands {
+                    return Err(ApiError::forbidden("不允许执行任意命令，请使用预先定义的操作").into());
+                }
+                (
+                    None,
+                    command.clone(),
+                    request.user.clone(),
+                    request.working_dir.clone(),
+                    request.env.clone(),
+                    MAX_TIMEOUT,
+                )
+            }
+            _ => return Err(ApiError::invalid("需要指定 action 或 command 中的一个").into()),
+        };
+        command.retain(|arg| !arg.is_empty());
+        if command.is_empty() {
+            return Err(ApiError::invalid("命令不能为空").into());
+        }
+        let timeout = request.timeout.unwrap_or(if action.is_some() { limit } else { settings.command_timeout })
+            .min(limit)
+            .max(1);
+
+        let exec = self.docker.create_exec(container, CreateExecOptions {
+            cmd: Some(command.clone()),
+            user: run_as.filter(|u| !u.is_empty()),
+            working_dir: working_dir.filter(|d| !d.is_empty()),
+            env: (!env.is_empty()).then_some(env),
+            attach_stdout: Some(true),
+            attach_stderr: Some(true),
+            ..Default::default()
+        }).await?;
+
+        let started = std::time::Instant::now();
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut truncated = false;
+        let collect = async {
+            if let StartExecResults::Attached { mut output, .. } = self.docker.start_exec(&exec.id, None).await? {
+                while let Some(chunk) = output.next().await {
+                    let (buffer, bytes) = match chunk? {
+                        LogOutput::StdErr { message } => (&mut stderr, message),
+                        LogOutput::StdOut { message } | LogOutput::Console { message } => (&mut stdout, message),
+                        LogOutput::StdIn { .. } => continue,
+                    };
+                    let room = MAX_OUTPUT.saturating_sub(buffer.len());
+                    truncated |= bytes.len() > room;
+                    buffer.extend_from_slice(&bytes[..bytes.len().min(room)]);
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        };
+        let timed_out = match tokio::time::timeout(std::time::Duration::from_secs(timeout), collect).await {
+            Ok(result) => {
+                result?;
+                false
+            }
+            Err(_) => true,
+        };
+        let exit_code = if timed_out { None } else { self.exit_code(&exec.id).await };
+
+        Ok(CommandOutput {
+            action,
+            command,
+            exit_code,
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+            timed_out,
+            truncated,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// 在运行中的容器里启动带 TTY 的 shell
+    pub async fn open_terminal(&self, container: &str, options: &TerminalOptions) -> Result<Terminal> {
+        let settings = self.settings();
+        self.inspect_target(container).await?;
+
+        let shell = options.shell.clone().filter(|s| !s.trim().is_empty()).unwrap_or(settings.shell);
+        let cmd: Vec<String> = shell.split_whitespace().map(str::to_string).collect();
+        let user = options.user.clone().filter(|u| !u.is_empty()).or(settings.user);
+
+        let sessions = self.sessions.clone();
+        if sessions.fetch_add(1, Ordering::SeqCst) >= settings.max_sessions {
+            sessions.fetch_sub(1, Ordering::SeqCst);
+            return Err(ApiError::busy("打开的终端过多").into());
+        }
+        let guard = SessionGuard(sessions);
+
+        let exec = self.docker.create_exec(container, CreateExecOptions {
+            cmd: Some(cmd),
+            user: user.clone(),
+            env: Some(vec!["TERM=xterm-256color".to_string()]),
+            attach_stdin: Some(true),
+            attach_stdout: Some(true),
+            attach_stderr: Some(true),
+            tty: Some(true),
+            ..Default::default()
+        }).await?;
+        let StartExecResults::Attached { output, input } = self.docker
+            .start_exec(&exec.id, Some(StartExecOptions { detach: false, output_capacity: None }))
+            .await?
+        else {
+            return Err(anyhow::anyhow!("无法连接到 exec 的输入输出"));
+        };
+
+        if let (Some(cols), Some(rows)) = (options.cols, options.rows) {
+            // shell 刚启动时调整大小可能失败，之后前端还会再发一次
+            let _ = self.resize(&exec.id, cols, rows).await;
+        }
+        Ok(Terminal { exec_id: exec.id, shell, user, output, input, _session: guard })
+    }
+
+    pub async fn resize(&self, exec_id: &str, cols: u16, rows: u16) -> Result<()> {
+        if cols == 0 || rows == 0 {
+            return Ok(());
+        }
+        self.docker.resize_exec(exec_id, ResizeExecOptions { width: cols, height: rows }).await?;
+        Ok(())
+    }
+
+    /// exec 结束后的退出码，还在运行时为 None
+    pub async fn exit_code(&self, exec_id: &str) -> Option<i64> {
+        self.docker.inspect_exec(exec_id).await.ok()?.exit_code
+    }
+}
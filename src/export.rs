@@ -0,0 +1,565 @@
+use bollard::container::Config;
+use bollard::models::{HealthConfig, HostConfig, RestartPolicyNameEnum};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use crate::models::{ContainerConfig, MountSpec};
+
+const MASK: &str = "******";
+
+// 名称中包含这些词的环境变量视为敏感信息
+const SECRET_MARKERS: &[&str] = &[
+    "PASSWORD", "PASSWD", "SECRET", "TOKEN", "API_KEY", "APIKEY", "ACCESS_KEY",
+    "PRIVATE_KEY", "CREDENTIAL", "AUTH",
+];
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Compose,
+    Run,
+}
+
+/// 环境变量名是否像密码、令牌这类敏感信息
+pub fn is_secret_key(key: &str) -> bool {
+    let upper = key.to_ascii_uppercase();
+    SECRET_MARKERS.iter().any(|marker| upper.contains(marker))
+}
+
+/// 导出用的容器描述：保存的配置去掉镜像默认值之后的结果
+pub struct ExportedContainer {
+    pub stored: ContainerConfig,
+    pub config: Config<String>,
+}
+
+#[derive(Serialize)]
+struct ComposeFile {
+    services: BTreeMap<String, ComposeService>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    networks: BTreeMap<String, External>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    volumes: BTreeMap<String, External>,
+}
+
+// 网络和卷已经存在于主机上，导出为外部资源，避免 compose 重新创建
+#[derive(Serialize)]
+struct External {
+    external: bool,
+}
+
+#[derive(Serialize, Default)]
+struct ComposeService {
+    image: String,
+    container_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    domainname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    working_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entrypoint: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    environment: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    ports: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    volumes: Vec<ComposeMount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    network_mode: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    networks: BTreeMap<String, ComposeNetwork>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restart: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    labels: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    healthcheck: Option<ComposeHealthcheck>,
+    #[serde(skip_serializing_if = "is_false")]
+    privileged: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    read_only: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    tty: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    stdin_open: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cap_add: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cap_drop: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    dns: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extra_hosts: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mem_limit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpus: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_signal: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ComposeMount {
+    #[serde(rename = "type")]
+    typ: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    target: String,
+    #[serde(skip_serializing_if = "is_false")]
+    read_only: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bind: Option<BTreeMap<&'static str, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volume: Option<BTreeMap<&'static str, bool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tmpfs: Option<BTreeMap<&'static str, i64>>,
+}
+
+#[derive(Serialize, Default)]
+struct ComposeNetwork {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ComposeHealthcheck {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    test: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interval: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_period: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retries: Option<i64>,
+    #[serde(skip_serializing_if = "is_false")]
+    disable: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// 把容器导出为 compose 文件，服务名使用容器名
+pub fn to_compose(containers: &[ExportedContainer], show_secrets: bool) -> anyhow::Result<String> {
+    let mut file = ComposeFile {
+        services: BTreeMap::new(),
+        networks: BTreeMap::new(),
+        volumes: BTreeMap::new(),
+    };
+
+    for container in containers {
+        let service = compose_service(container, show_secrets);
+        for network in service.networks.keys() {
+            file.networks.insert(network.clone(), External { external: true });
+        }
+        for mount in &service.volumes {
+            if let (Some(source), "volume") = (&mount.source, mount.typ) {
+                file.volumes.insert(source.clone(), External { external: true });
+            }
+        }
+        file.services.insert(service_name(&container.stored.name), service);
+    }
+
+    Ok(serde_yaml::to_string(&file)?)
+}
+
+/// 把容器导出为等价的 `docker run` 命令，额外的网络用 `docker network connect` 连接
+pub fn to_run_commands(containers: &[ExportedContainer], show_secrets: bool) -> String {
+    containers.iter()
+        .map(|c| run_command(c, show_secrets))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn compose_service(container: &ExportedContainer, show_secrets: bool) -> ComposeService {
+    let config = &container.config;
+    let host = container.stored.host_config.clone().unwrap_or_default();
+    let (network_mode, networks) = export_networks(container, &host);
+
+    ComposeService {
+        image: container.stored.image.clone(),
+        container_name: container.stored.name.clone(),
+        hostname: config.hostname.clone().filter(|h| !h.is_empty()),
+        domainname: config.domainname.clone().filter(|d| !d.is_empty()),
+        user: config.user.clone().filter(|u| !u.is_empty()),
+        working_dir: config.working_dir.clone().filter(|w| !w.is_empty()),
+        entrypoint: config.entrypoint.clone(),
+        command: config.cmd.clone(),
+        environment: export_env(config, show_secrets),
+        ports: export_ports(&host),
+        volumes: container.stored.mounts.iter().map(compose_mount).collect(),
+        network_mode,
+        networks: networks.into_iter()
+            .map(|(name, aliases)| (name, ComposeNetwork { aliases }))
+            .collect(),
+        restart: export_restart(&host),
+        labels: export_labels(config),
+        healthcheck: config.healthcheck.as_ref().map(compose_healthcheck),
+        privileged: host.privileged.unwrap_or(false),
+        read_only: host.readonly_rootfs.unwrap_or(false),
+        tty: config.tty.unwrap_or(false),
+        stdin_open: config.open_stdin.unwrap_or(false),
+        cap_add: host.cap_add.clone().unwrap_or_default(),
+        cap_drop: host.cap_drop.clone().unwrap_or_default(),
+        dns: host.dns.clone().unwrap_or_default(),
+        extra_hosts: host.extra_hosts.clone().unwrap_or_default(),
+        mem_limit: host.memory.filter(|m| *m > 0).map(format_size),
+        cpus: host.nano_cpus.filter(|n| *n > 0).map(|n| n as f64 / 1e9),
+        stop_signal: config.stop_signal.clone().filter(|s| !s.is_empty()),
+    }
+}
+
+fn run_command(container: &ExportedContainer, show_secrets: bool) -> String {
+    let config = &container.config;
+    let host = container.stored.host_config.clone().unwrap_or_default();
+    let mut args: Vec<String> = vec!["docker".into(), "run".into(), "-d".into()];
+
+    push(&mut args, "--name", container.stored.name.clone());
+    if let Some(hostname) = config.hostname.clone().filter(|h| !h.is_empty()) {
+        push(&mut args, "--hostname", hostname);
+    }
+    if let Some(domainname) = config.domainname.clone().filter(|d| !d.is_empty()) {
+        push(&mut args, "--domainname", domainname);
+    }
+    if let Some(user) = config.user.clone().filter(|u| !u.is_empty()) {
+        push(&mut args, "--user", user);
+    }
+    if let Some(dir) = config.working_dir.clone().filter(|w| !w.is_empty()) {
+        push(&mut args, "--workdir", dir);
+    }
+    // --entrypoint 只接受一个参数，其余部分放到镜像名之后
+    let mut trailing = Vec::new();
+    if let Some(entrypoint) = &config.entrypoint {
+        let mut parts = entrypoint.iter().cloned();
+        push(&mut args, "--entrypoint", parts.next().unwrap_or_default());
+        trailing.extend(parts);
+    }
+    for env in export_env(config, show_secrets) {
+        push(&mut args, "-e", env);
+    }
+    for port in export_ports(&host) {
+        push(&mut args, "-p", port);
+    }
+    for mount in &container.stored.mounts {
+        push(&mut args, "--mount", run_mount(mount));
+    }
+
+    let (network_mode, networks) = export_networks(container, &host);
+    if let Some(mode) = network_mode {
+        push(&mut args, "--network", mode);
+    }
+    let mut networks = networks.into_iter();
+    let first_network = networks.next();
+    if let Some((name, aliases)) = &first_network {
+        push(&mut args, "--network", name.clone());
+        for alias in aliases {
+            push(&mut args, "--network-alias", alias.clone());
+        }
+    }
+
+    if let Some(restart) = export_restart(&host) {
+        push(&mut args, "--restart", restart);
+    }
+    for (key, value) in export_labels(config) {
+        push(&mut args, "--label", format!("{}={}", key, value));
+    }
+    if let Some(check) = &config.healthcheck {
+        let test = check.test.clone().unwrap_or_default();
+        match test.first().map(String::as_str) {
+            Some("NONE") => args.push("--no-healthcheck".into()),
+            Some("CMD-SHELL") => push(&mut args, "--health-cmd", test[1..].join(" ")),
+            Some("CMD") => push(&mut args, "--health-cmd", test[1..].iter().map(|a| shell_quote(a)).collect::<Vec<_>>().join(" ")),
+            _ => {}
+        }
+        if let Some(interval) = check.interval.filter(|i| *i > 0) {
+            push(&mut args, "--health-interval", format_duration(interval));
+        }
+        if let Some(timeout) = check.timeout.filter(|t| *t > 0) {
+            push(&mut args, "--health-timeout", format_duration(timeout));
+        }
+        if let Some(start) = check.start_period.filter(|s| *s > 0) {
+            push(&mut args, "--health-start-period", format_duration(start));
+        }
+        if let Some(retries) = check.retries.filter(|r| *r > 0) {
+            push(&mut args, "--health-retries", retries.to_string());
+        }
+    }
+    for cap in host.cap_add.clone().unwrap_or_default() {
+        push(&mut args, "--cap-add", cap);
+    }
+    for cap in host.cap_drop.clone().unwrap_or_default() {
+        push(&mut args, "--cap-drop", cap);
+    }
+    for dns in host.dns.clone().unwrap_or_default() {
+        push(&mut args, "--dns", dns);
+    }
+    for extra in host.extra_hosts.clone().unwrap_or_default() {
+        push(&mut args, "--add-host", extra);
+    }
+    if let Some(memory) = host.memory.filter(|m| *m > 0) {
+        push(&mut args, "--memory", format_size(memory));
+    }
+    if let Some(nano) = host.nano_cpus.filter(|n| *n > 0) {
+        push(&mut args, "--cpus", (nano as f64 / 1e9).to_string());
+    }
+    if let Some(signal) = config.stop_signal.clone().filter(|s| !s.is_empty()) {
+        push(&mut args, "--stop-signal", signal);
+    }
+    if host.privileged.unwrap_or(false) {
+        args.push("--privileged".into());
+    }
+    if host.readonly_rootfs.unwrap_or(false) {
+        args.push("--read-only".into());
+    }
+    if config.tty.unwrap_or(false) {
+        args.push("-t".into());
+    }
+    if config.open_stdin.unwrap_or(false) {
+        args.push("-i".into());
+    }
+
+    args.push(container.stored.image.clone());
+    args.extend(trailing);
+    args.extend(config.cmd.clone().unwrap_or_default());
+
+    let mut lines = vec![args.iter().map(|a| shell_quote(a)).collect::<Vec<_>>().join(" ")];
+    for (name, aliases) in networks {
+        let mut connect = vec!["docker".to_string(), "network".into(), "connect".into()];
+        for alias in aliases {
+            connect.push("--alias".into());
+            connect.push(alias);
+        }
+        connect.push(name);
+        connect.push(container.stored.name.clone());
+        lines.push(connect.iter().map(|a| shell_quote(a)).collect::<Vec<_>>().join(" "));
+    }
+    lines.join("\n")
+}
+
+fn push(args: &mut Vec<String>, flag: &str, value: String) {
+    args.push(flag.to_string());
+    args.push(value);
+}
+
+fn export_env(config: &Config<String>, show_secrets: bool) -> Vec<String> {
+    config.env.clone().unwrap_or_default()
+        .into_iter()
+        .map(|entry| match entry.split_once('=') {
+            Some((key, _)) if !show_secrets && is_secret_key(key) => format!("{}={}", key, MASK),
+            _ => entry,
+        })
+        .collect()
+}
+
+// compose 管理的标签由 compose 自己生成，导出时去掉
+fn export_labels(config: &Config<String>) -> BTreeMap<String, String> {
+    config.labels.clone().unwrap_or_default()
+        .into_iter()
+        .filter(|(k, _)| !k.starts_with("com.docker.compose."))
+        .collect()
+}
+
+fn export_ports(host: &HostConfig) -> Vec<String> {
+    let mut ports = Vec::new();
+    for (container_port, bindings) in host.port_bindings.clone().unwrap_or_default() {
+        let container_port = container_port.strip_suffix("/tcp").unwrap_or(&container_port).to_string();
+        for binding in bindings.unwrap_or_default() {
+            let host_port = binding.host_port.unwrap_or_default();
+            let port = match binding.host_ip.as_deref() {
+                Some(ip) if !ip.is_empty() && ip != "0.0.0.0" => format!("{}:{}:{}", ip, host_port, container_port),
+                _ if host_port.is_empty() => container_port.clone(),
+                _ => format!("{}:{}", host_port, container_port),
+            };
+            ports.push(port);
+        }
+    }
+    ports.sort();
+    ports
+}
+
+fn export_restart(host: &HostConfig) -> Option<String> {
+    let policy = host.restart_policy.as_ref()?;
+    match policy.name? {
+        RestartPolicyNameEnum::ALWAYS => Some("always".to_string()),
+        RestartPolicyNameEnum::UNLESS_STOPPED => Some("unless-stopped".to_string()),
+        RestartPolicyNameEnum::ON_FAILURE => Some(match policy.maximum_retry_count.filter(|c| *c > 0) {
+            Some(count) => format!("on-failure:{}", count),
+            None => "on-failure".to_string(),
+        }),
+        RestartPolicyNameEnum::NO | RestartPolicyNameEnum::EMPTY => None,
+    }
+}
+
+/// 返回 network_mode 和自定义网络（网络名、别名）；默认 bridge 网络不需要导出
+fn export_networks(container: &ExportedContainer, host: &HostConfig) -> (Option<String>, Vec<(String, Vec<String>)>) {
+    let mode = host.network_mode.clone().unwrap_or_default();
+    if mode == "host" || mode == "none" || mode.starts_with("container:") {
+        return (Some(mode), Vec::new());
+    }
+    let id = &container.stored.container_id;
+    let mut networks: Vec<(String, Vec<String>)> = container.stored.networks.iter()
+        .filter(|n| n.name != "bridge")
+        .map(|n| {
+            // 容器 ID 前缀是 Docker 自动添加的别名
+            let aliases = n.aliases.iter()
+                .filter(|a| !id.starts_with(a.as_str()) && **a != container.stored.name)
+                .cloned()
+                .collect();
+            (n.name.clone(), aliases)
+        })
+        .collect();
+    // 创建容器时指定的网络排在最前面，`docker run` 用它作为 --network
+    networks.sort_by_key(|(name, _)| (*name != mode, name.clone()));
+    (None, networks)
+}
+
+fn compose_mount(mount: &MountSpec) -> ComposeMount {
+    match mount {
+        MountSpec::Bind { source, target, read_only, propagation } => ComposeMount {
+            typ: "bind",
+            source: Some(source.clone()),
+            target: target.clone(),
+            read_only: *read_only,
+            bind: propagation.map(|p| BTreeMap::from([("propagation", p.to_string())])),
+            volume: None,
+            tmpfs: None,
+        },
+        MountSpec::Volume { source, target, read_only, no_copy, .. } => ComposeMount {
+            typ: "volume",
+            source: named_volume(source),
+            target: target.clone(),
+            read_only: *read_only,
+            bind: None,
+            volume: no_copy.then(|| BTreeMap::from([("nocopy", true)])),
+            tmpfs: None,
+        },
+        MountSpec::Tmpfs { target, size, mode } => {
+            let mut options = BTreeMap::new();
+            if let Some(size) = size {
+                options.insert("size", *size);
+            }
+            if let Some(mode) = mode {
+                options.insert("mode", *mode);
+            }
+            ComposeMount {
+                typ: "tmpfs",
+                source: None,
+                target: target.clone(),
+                read_only: false,
+                bind: None,
+                volume: None,
+                tmpfs: if options.is_empty() { None } else { Some(options) },
+            }
+        }
+    }
+}
+
+fn run_mount(mount: &MountSpec) -> String {
+    let mut parts = Vec::new();
+    match mount {
+        MountSpec::Bind { source, target, read_only, propagation } => {
+            parts.push("type=bind".to_string());
+            parts.push(format!("source={}", source));
+            parts.push(format!("target={}", target));
+            if *read_only {
+                parts.push("readonly".to_string());
+            }
+            if let Some(propagation) = propagation {
+                parts.push(format!("bind-propagation={}", propagation));
+            }
+        }
+        MountSpec::Volume { source, target, read_only, driver, driver_opts, no_copy } => {
+            parts.push("type=volume".to_string());
+            if let Some(source) = named_volume(source) {
+                parts.push(format!("source={}", source));
+            }
+            parts.push(format!("target={}", target));
+            if *read_only {
+                parts.push("readonly".to_string());
+            }
+            if let Some(driver) = driver.as_ref().filter(|d| *d != "local") {
+                parts.push(format!("volume-driver={}", driver));
+            }
+            let mut opts: Vec<_> = driver_opts.iter().collect();
+            opts.sort();
+            for (key, value) in opts {
+                parts.push(format!("volume-opt={}={}", key, value));
+            }
+            if *no_copy {
+                parts.push("volume-nocopy".to_string());
+            }
+        }
+        MountSpec::Tmpfs { target, size, mode } => {
+            parts.push("type=tmpfs".to_string());
+            parts.push(format!("target={}", target));
+            if let Some(size) = size {
+                parts.push(format!("tmpfs-size={}", size));
+            }
+            if let Some(mode) = mode {
+                parts.push(format!("tmpfs-mode={:o}", mode));
+            }
+        }
+    }
+    parts.join(",")
+}
+
+// 匿名卷的名称是 64 位十六进制，导出时不保留，让 Docker 重新创建
+fn named_volume(source: &Option<String>) -> Option<String> {
+    source.clone().filter(|s| !(s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit())))
+}
+
+fn compose_healthcheck(check: &HealthConfig) -> ComposeHealthcheck {
+    let disable = check.test.as_ref().is_some_and(|t| t.first().map(String::as_str) == Some("NONE"));
+    let duration = |value: Option<i64>| value.filter(|v| *v > 0).map(format_duration);
+    ComposeHealthcheck {
+        test: if disable { None } else { check.test.clone() },
+        interval: duration(check.interval),
+        timeout: duration(check.timeout),
+        start_period: duration(check.start_period),
+        retries: check.retries.filter(|r| *r > 0),
+        disable,
+    }
+}
+
+// 服务名只能包含小写字母、数字、`-`、`_` 和 `.`
+fn service_name(name: &str) -> String {
+    name.to_ascii_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect()
+}
+
+fn format_duration(nanos: i64) -> String {
+    if nanos % 1_000_000_000 == 0 {
+        format!("{}s", nanos / 1_000_000_000)
+    } else {
+        format!("{}ms", nanos / 1_000_000)
+    }
+}
+
+fn format_size(bytes: i64) -> String {
+    const UNITS: [(i64, &str); 3] = [(1024 * 1024 * 1024, "g"), (1024 * 1024, "m"), (1024, "k")];
+    UNITS.iter()
+        .find(|(size, _)| bytes % size == 0)
+        .map(|(size, unit)| format!("{}{}", bytes / size, unit))
+        .unwrap_or_else(|| bytes.to_string())
+}
+
+// 只有包含特殊字符的参数才加引号，保持命令易读
+fn shell_quote(value: &str) -> String {
+    let safe = !value.is_empty() && value.chars().all(|c| {
+        c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c)
+    });
+    if safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
@@ -0,0 +1,566 @@
+use bollard::Docker;
+use bollard::container::{DownloadFromContainerOptions, UploadToContainerOptions};
+use chrono::{DateTime, Local, TimeZone};
+use futures::{Stream, StreamExt};
+use hyper::body::Bytes;
+use serde::{Serialize, Deserialize};
+use std::io::Read;
+use std::pin::Pin;
+use std::sync::Mutex;
+use tar::EntryType;
+use tokio_util::io::{StreamReader, SyncIoBridge};
+use anyhow::Result;
+use crate::backups::LABEL_HELPER;
+use crate::storage;
+use crate::error::ApiError;
+
+pub(crate) const SETTINGS_FILE: &str = "files.json";
+// 下载单个文件时每次读取的大小
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// 设置为 `false` 时不允许浏览和读写该容器的文件
+pub const LABEL_FILES: &str = "docker-manager.files";
+
+/// 文件浏览和上传下载的设置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileSettings {
+    /// 下载的大小上限（字节），目录按打包后的 tar 计算
+    pub max_download_size: u64,
+    /// 上传的大小上限（字节）
+    pub max_upload_size: u64,
+    /// 一个目录最多列出的条目数
+    pub max_list_entries: usize,
+    /// 列目录时最多读取的归档字节数，目录下的文件内容也要读过，超过时返回不完整的列表
+    pub max_list_scan: u64,
+    /// 不允许访问的路径及其子路径
+    pub blocked_paths: Vec<String>,
+}
+
+impl Default for FileSettings {
+    fn default() -> Self {
+        Self {
+            max_download_size: 100 * 1024 * 1024,
+            max_upload_size: 100 * 1024 * 1024,
+            max_list_entries: 1000,
+            max_list_scan: 64 * 1024 * 1024,
+            blocked_paths: vec!["/proc".to_string(), "/sys".to_string(), "/dev".to_string()],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    /// 硬链接
+    Link,
+    Other,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FileEntry {
+    pub name: String,
+    pub path: String,
+    pub kind: FileKind,
+    pub size: u64,
+    /// 权限位，如 0o644
+    pub mode: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime<Local>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<String>,
+}
+
+/// 目录列表；路径是文件时只返回文件本身的信息
+#[derive(Clone, Debug, Serialize)]
+pub struct FileListing {
+    pub entry: FileEntry,
+    pub entries: Vec<FileEntry>,
+    /// 条目数或读取的字节数超过上限时为 false
+    pub complete: bool,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Modified,
+    Added,
+    Deleted,
+}
+
+/// 容器可写层中相对镜像的一处变更
+#[derive(Clone, Debug, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferFormat {
+    /// 打包为 tar，可以是目录
+    #[default]
+    Tar,
+    /// 单个普通文件的内容
+    File,
+}
+
+pub type DownloadStream = Pin<Box<dyn Stream<Item = std::io::Result<Vec<u8>>> + Send>>;
+
+/// 准备好的下载，状态码和文件名确定后再开始传输内容
+pub struct Download {
+    pub file_name: String,
+    /// 单个文件的大小；tar 的大小事先无法知道
+    pub size: Option<u64>,
+    pub stream: DownloadStream,
+}
+
+type ArchiveStream = Pin<Box<dyn Stream<Item = Result<Bytes, bollard::errors::Error>> + Send>>;
+// 在阻塞线程中用 tar 解析 Docker 返回的归档流
+type ArchiveReader = SyncIoBridge<StreamReader<Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>, Bytes>>;
+
+struct TarEntry {
+    name: String,
+    kind: FileKind,
+    size: u64,
+    mode: u32,
+    mtime: Option<u64>,
+    link_target: Option<String>,
+}
+
+impl TarEntry {
+    // 全局 PAX 头部不是文件，返回 None
+    fn read<R: Read>(entry: &tar::Entry<'_, R>) -> Result<Option<Self>> {
+        let header = entry.header();
+        let kind = match header.entry_type() {
+            EntryType::Regular | EntryType::Continuous => FileKind::File,
+            EntryType::Directory => FileKind::Dir,
+            EntryType::Symlink => FileKind::Symlink,
+            EntryType::Link => FileKind::Link,
+            EntryType::XGlobalHeader => return Ok(None),
+            _ => FileKind::Other,
+        };
+        let link_target = match kind {
+            FileKind::Symlink | FileKind::Link => entry.link_name()?.map(|l| l.to_string_lossy().into_owned()),
+            _ => None,
+        };
+        Ok(Some(Self {
+            name: entry.path()?.to_string_lossy().into_owned(),
+            kind,
+            size: entry.size(),
+            mode: header.mode().unwrap_or(0) & 0o7777,
+            mtime: header.mtime().ok(),
+            link_target,
+        }))
+    }
+
+    // 去掉开头的 `./`、`/` 和结尾的 `/`
+    fn relative_name(&self) -> &str {
+        let mut name = self.name.as_str();
+        while let Some(rest) = name.strip_prefix("./").or_else(|| name.strip_prefix('/')) {
+            name = rest;
+        }
+        let name = name.trim_end_matches('/');
+        if name == "." { "" } else { name }
+    }
+
+    fn into_file_entry(self, name: String, path: String) -> FileEntry {
+        FileEntry {
+            name,
+            path,
+            kind: self.kind,
+            size: if self.kind == FileKind::File { self.size } else { 0 },
+            mode: self.mode,
+            modified: self.mtime.and_then(|t| Local.timestamp_opt(t as i64, 0).single()),
+            link_target: self.link_target,
+        }
+    }
+}
+
+// 下一个条目及其头部在归档中的位置
+fn next_entry<R: Read>(entries: &mut tar::Entries<'_, R>) -> Result<Option<(TarEntry, u64)>> {
+    for entry in entries {
+        let entry = entry?;
+        if let Some(parsed) = TarEntry::read(&entry)? {
+            return Ok(Some((parsed, entry.raw_header_position())));
+        }
+    }
+    Ok(None)
+}
+
+pub struct FileManager {
+    docker: Docker,
+    settings: Mutex<FileSettings>,
+}
+
+impl FileManager {
+    pub fn load(docker: Docker) -> Result<Self> {
+        let settings: FileSettings = storage::load_json(SETTINGS_FILE)?;
+        Ok(Self {
+            docker,
+            settings: Mutex::new(settings),
+        })
+    }
+
+    pub fn settings(&self) -> FileSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update_settings(&self, settings: FileSettings) -> Result<()> {
+        if settings.max_download_size == 0 || settings.max_upload_size == 0
+            || settings.max_list_entries == 0 || settings.max_list_scan == 0
+        {
+            return Err(anyhow::anyhow!("大小和数量上限必须大于 0"));
+        }
+        for path in &settings.blocked_paths {
+            validate_path(path)?;
+        }
+        storage::save_json(SETTINGS_FILE, &settings)?;
+        *self.settings.lock().unwrap() = settings;
+        println!("已更新文件管理设置");
+        Ok(())
+    }
+
+    // 检查容器是否允许访问文件，返回规范化后的路径
+    async fn check(&self, container: &str, path: &str) -> Result<String> {
+        let path = validate_path(path)?;
+        let settings = self.settings();
+        if let Some(blocked) = settings.blocked_paths.iter()
+            .filter_map(|p| validate_path(p).ok())
+            .find(|blocked| is_within(&path, blocked))
+        {
+            return Err(ApiError::forbidden(format!("不允许访问 {}", blocked)).into());
+        }
+        self.check_container(container).await?;
+        Ok(path)
+    }
+
+    // 辅助容器和带有 `docker-manager.files=false` 标签的容器不允许访问，提交快照前同样检查
+    pub(crate) async fn check_container(&self, container: &str) -> Result<()> {
+        let inspect = self.docker.inspect_container(container, None).await?;
+        let labels = inspect.config.and_then(|c| c.labels).unwrap_or_default();
+        if labels.contains_key(LABEL_HELPER)
+            || labels.get(LABEL_FILES).is_some_and(|v| v.eq_ignore_ascii_case("false") || v == "0")
+        {
+            return Err(ApiError::forbidden(format!("容器 {} 不允许访问文件", container)).into());
+        }
+        Ok(())
+    }
+
+    // 先读一段，让路径不存在等错误在返回响应前以 Docker 错误的形式暴露出来
+    async fn archive_stream(&self, container: &str, path: &str) -> Result<ArchiveStream> {
+        let mut stream = self.docker
+            .download_from_container(container, Some(DownloadFromContainerOptions { path: path.to_string() }));
+        let first = stream.next().await.transpose()?;
+        Ok(Box::pin(futures::stream::iter(first.map(Ok)).chain(stream)))
+    }
+
+    async fn archive(&self, container: &str, path: &str) -> Result<ArchiveReader> {
+        let stream: Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>> = Box::pin(
+            self.archive_stream(container, path).await?.map(|chunk| chunk.map_err(std::io::Error::other)),
+        );
+        Ok(SyncIoBridge::new(StreamReader::new(stream)))
+    }
+
+    /// 容器可写层相对镜像的变更，按路径排序
+    pub async fn changes(&self, container: &str) -> Result<Vec<FileChange>> {
+        self.check_container(container).await?;
+        let mut changes: Vec<FileChange> = self.docker.container_changes(container).await?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|change| FileChange {
+                path: change.path,
+                kind: match change.kind {
+                    1 => ChangeKind::Added,
+                    2 => ChangeKind::Deleted,
+                    _ => ChangeKind::Modified,
+                },
+            })
+            .collect();
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(changes)
+    }
+
+    /// 列出目录的直接子项，目录在前
+    pub async fn list(&self, container: &str, path: &str) -> Result<FileListing> {
+        let path = self.check(container, path).await?;
+        let settings = self.settings();
+        let reader = self.archive(container, &path).await?;
+
+        tokio::task::spawn_blocking(move || {
+            let mut archive = tar::Archive::new(reader);
+            let mut iter = archive.entries()?;
+            let (root, _) = next_entry(&mut iter)?
+                .ok_or_else(|| anyhow::anyhow!("{} 的归档为空", path))?;
+            let base = root.relative_name().to_string();
+            let is_dir = root.kind == FileKind::Dir;
+            let entry = root.into_file_entry(base_name(&path), path.clone());
+            if !is_dir {
+                return Ok(FileListing { entry, entries: Vec::new(), complete: true });
+            }
+
+            let mut entries = Vec::new();
+            let mut complete = true;
+            loop {
+                if entries.len() >= settings.max_list_entries {
+                    complete = false;
+                    break;
+                }
+                let Some((child, position)) = next_entry(&mut iter)? else {
+                    break;
+                };
+                if position > settings.max_list_scan {
+                    complete = false;
+                    break;
+                }
+                let name = child.relative_name();
+                let name = if base.is_empty() { name } else { name.strip_prefix(&base).and_then(|n| n.strip_prefix('/')).unwrap_or("") };
+                if name.is_empty() || name.contains('/') {
+                    continue;
+                }
+                let name = name.to_string();
+                let child_path = join_path(&path, &name);
+                entries.push(child.into_file_entry(name, child_path));
+            }
+            // 提前结束时丢弃剩余的流，Docker 随之停止打包
+            drop(archive);
+            entries.sort_by(|a, b| (b.kind == FileKind::Dir).cmp(&(a.kind == FileKind::Dir)).then_with(|| a.name.cmp(&b.name)));
+            Ok(FileListing { entry, entries, complete })
+        }).await?
+    }
+
+    /// 下载路径。tar 格式按读取的字节数限制大小，超过时中断传输；单个文件在开始前检查大小
+    pub async fn download(&self, container: &str, path: &str, format: TransferFormat) -> Result<Download> {
+        let path = self.check(container, path).await?;
+        let limit = self.settings().max_download_size;
+        let name = base_name(&path);
+
+        match format {
+            TransferFormat::Tar => {
+                let stream = self.archive_stream(container, &path).await?;
+                let stream = futures::stream::unfold((stream, 0u64), move |(mut stream, sent)| async move {
+                    if sent > limit {
+                        return None;
+                    }
+                    match stream.next().await {
+                        Some(Ok(chunk)) => {
+                            let sent = sent + chunk.len() as u64;
+                            if sent > limit {
+                                eprintln!("下载超过大小上限 {} 字节，已中断", limit);
+                                Some((Err(std::io::Error::other("超过下载大小上限")), (stream, sent)))
+                            } else {
+                                Some((Ok(chunk.to_vec()), (stream, sent)))
+                            }
+                        }
+                        Some(Err(e)) => Some((Err(std::io::Error::other(e.to_string())), (stream, limit + 1))),
+                        None => None,
+                    }
+                });
+                Ok(Download { file_name: format!("{}.tar", name), size: None, stream: Box::pin(stream) })
+            }
+            TransferFormat::File => {
+                let reader = self.archive(container, &path).await?;
+                // 阻塞线程先检查第一个条目，再把内容分块送回
+                let (checked_tx, checked_rx) = tokio::sync::oneshot::channel::<Result<u64>>();
+                let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<Vec<u8>>>(4);
+                tokio::task::spawn_blocking(move || {
+                    let mut archive = tar::Archive::new(reader);
+                    let mut entry = match first_file(&mut archive, &path, limit) {
+                        Ok(entry) => entry,
+                        Err(e) => {
+                            let _ = checked_tx.send(Err(e));
+                            return;
+                        }
+                    };
+                    let _ = checked_tx.send(Ok(entry.size()));
+                    let mut buffer = vec![0u8; CHUNK_SIZE];
+                    loop {
+                        let chunk = match entry.read(&mut buffer) {
+                            Ok(0) => return,
+                            Ok(n) => Ok(buffer[..n].to_vec()),
+                            Err(e) => Err(e),
+                        };
+                        let failed = chunk.is_err();
+                        // 接收方已经断开，或读取出错
+                        if tx.blocking_send(chunk).is_err() || failed {
+                            return;
+                        }
+                    }
+                });
+                let size = checked_rx.await??;
+                let stream = futures::stream::unfold(rx, |mut rx| async move {
+                    rx.recv().await.map(|chunk| (chunk, rx))
+                });
+                Ok(Download { file_name: name, size: Some(size), stream: Box::pin(stream) })
+            }
+        }
+    }
+
+    /// 上传到容器。tar 格式解压到 `path` 目录下；单个文件时 `path` 是目标文件的完整路径
+    pub async fn upload(&self, container: &str, path: &str, format: TransferFormat, data: Vec<u8>) -> Result<String> {
+        let path = self.check(container, path).await?;
+        let (target, archive) = match format {
+            TransferFormat::Tar => {
+                let blocked: Vec<String> = self.settings().blocked_paths.iter()
+                    .filter_map(|p| validate_path(p).ok())
+                    .collect();
+                let dest = path.clone();
+                let data = tokio::task::spawn_blocking(move || check_archive(&dest, &blocked, &data).map(|_| data)).await??;
+                (path.clone(), data)
+            }
+            TransferFormat::File => {
+                if path == "/" {
+                    return Err(ApiError::invalid("需要指定目标文件的完整路径").into());
+                }
+                let (dir, name) = path.rsplit_once('/').unwrap_or(("", &path));
+                let dir = if dir.is_empty() { "/" } else { dir };
+                (dir.to_string(), single_file_archive(name, &data)?)
+            }
+        };
+        self.docker.upload_to_container(container, Some(UploadToContainerOptions {
+            path: target.clone(),
+            no_overwrite_dir_non_dir: "true".to_string(),
+        }), archive.into()).await?;
+        println!("已上传文件到容器 {} 的 {}", container, path);
+        Ok(path)
+    }
+}
+
+/// 只接受绝对路径，不允许 `..`，返回去掉多余 `/` 和 `.` 的路径
+pub fn validate_path(path: &str) -> Result<String> {
+    if !path.starts_with('/') {
+        return Err(ApiError::invalid(format!("路径必须是绝对路径: {}", path)).into());
+    }
+    if path.contains('\0') {
+        return Err(ApiError::invalid("路径不能包含空字符").into());
+    }
+    let mut parts = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => return Err(ApiError::invalid(format!("路径不能包含 ..: {}", path)).into()),
+            part => parts.push(part),
+        }
+    }
+    Ok(format!("/{}", parts.join("/")))
+}
+
+fn is_within(path: &str, parent: &str) -> bool {
+    parent == "/" || path == parent || path.strip_prefix(parent).is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn base_name(path: &str) -> String {
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => "root".to_string(),
+    }
+}
+
+fn join_path(dir: &str, name: &str) -> String {
+    if dir == "/" { format!("/{}", name) } else { format!("{}/{}", dir, name) }
+}
+
+// 归档中的第一个条目，需要是不超过大小上限的普通文件
+fn first_file<'a, R: Read>(archive: &'a mut tar::Archive<R>, path: &str, limit: u64) -> Result<tar::Entry<'a, R>> {
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let kind = entry.header().entry_type();
+        if kind == EntryType::XGlobalHeader {
+            continue;
+        }
+        if !matches!(kind, EntryType::Regular | EntryType::Continuous) {
+            return Err(ApiError::invalid(format!("{} 不是普通文件，请按 tar 格式下载", path)).into());
+        }
+        if entry.size() > limit {
+            return Err(ApiError::too_large(format!("{} 有 {} 字节，超过下载上限 {} 字节", path, entry.size(), limit)).into());
+        }
+        return Ok(entry);
+    }
+    Err(anyhow::anyhow!("{} 的归档为空", path))
+}
+
+// 检查上传的 tar（可以用 gzip 压缩）中的每个条目：解压后的路径和链接指向都要在 `dest` 之内，且不在屏蔽路径下
+fn check_archive(dest: &str, blocked: &[String], data: &[u8]) -> Result<()> {
+    let reader: Box<dyn Read + '_> = if data.starts_with(&[0x1f, 0x8b]) {
+        Box::new(flate2::read::GzDecoder::new(data))
+    } else {
+        Box::new(data)
+    };
+    let check_blocked = |path: &str| -> Result<()> {
+        match blocked.iter().find(|b| is_within(path, b)) {
+            Some(b) => Err(ApiError::forbidden(format!("不允许访问 {}", b)).into()),
+            None => Ok(()),
+        }
+    };
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive.entries()
+        .map_err(|e| ApiError::invalid(format!("无法解析上传的归档: {}", e)))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| ApiError::invalid(format!("无法解析上传的归档: {}", e)))?;
+        let kind = entry.header().entry_type();
+        if kind == EntryType::XGlobalHeader {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let target = archive_target(dest, &name)?;
+        check_blocked(&target)?;
+
+        let link = entry.link_name()?.map(|l| l.to_string_lossy().into_owned());
+        match (kind, link) {
+            (EntryType::Symlink, Some(link)) => {
+                // 相对链接按链接所在的目录解析
+                let parent = target.rsplit_once('/').map_or("/", |(dir, _)| if dir.is_empty() { "/" } else { dir });
+                let base = if link.starts_with('/') { "/" } else { parent };
+                let resolved = resolve_link(base, &link)
+                    .filter(|resolved| is_within(resolved, dest))
+                    .ok_or_else(|| ApiError::invalid(format!("归档中的链接 {} 指向 {} 之外: {}", name, dest, link)))?;
+                check_blocked(&resolved)?;
+            }
+            // 硬链接的目标是归档中的另一个条目
+            (EntryType::Link, Some(link)) => check_blocked(&archive_target(dest, &link)?)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// 归档条目解压后的路径，不允许绝对路径和 `..`
+fn archive_target(dest: &str, name: &str) -> Result<String> {
+    if name.starts_with('/') {
+        return Err(ApiError::invalid(format!("归档中的路径不能是绝对路径: {}", name)).into());
+    }
+    if name.split('/').any(|part| part == "..") {
+        return Err(ApiError::invalid(format!("归档中的路径不能包含 ..: {}", name)).into());
+    }
+    validate_path(&join_path(dest, name))
+}
+
+// 把链接目标按 `base` 目录解析为绝对路径，`..` 超出根目录时返回 None
+fn resolve_link(base: &str, link: &str) -> Option<String> {
+    let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+    for part in link.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(format!("/{}", parts.join("/")))
+}
+
+// 只包含一个文件的 tar，文件名过长时由 tar 写入 GNU 长文件名头部
+fn single_file_archive(name: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(EntryType::Regular);
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Local::now().timestamp().max(0) as u64);
+    let mut builder = tar::Builder::new(Vec::with_capacity(data.len() + 2048));
+    builder.append_data(&mut header, name, data)?;
+    Ok(builder.into_inner()?)
+}
@@ -0,0 +1,229 @@
+use bollard::Docker;
+use bollard::container::ListContainersOptions;
+use bollard::models::ContainerSummary;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use anyhow::Result;
+use crate::backups::LABEL_HELPER;
+use crate::compose::{self, DependencyCondition};
+use crate::error::ApiError;
+
+/// 自定义分组名，优先于 compose 项目名
+pub const LABEL_GROUP: &str = "docker-manager.group";
+/// 同组内依赖的成员，逗号分隔，格式为 `名称[:条件]`，例如 `db:service_healthy,cache`
+pub const LABEL_DEPENDS_ON: &str = "docker-manager.depends-on";
+/// 设置为 `true` 时，任一依赖崩溃并恢复后也重启本容器
+pub const LABEL_CASCADE_RESTART: &str = "docker-manager.cascade-restart";
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupSource {
+    Compose,
+    Label,
+}
+
+/// 一组相互依赖的容器
+#[derive(Clone, Debug, Serialize)]
+pub struct GroupInfo {
+    pub name: String,
+    pub source: GroupSource,
+    /// 按依赖顺序排列，被依赖的成员在前
+    pub members: Vec<GroupMember>,
+    /// 依赖关系存在循环时的错误，此时成员保持原顺序
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GroupMember {
+    /// 组内名称：compose 容器为服务名，其他为容器名
+    pub name: String,
+    pub container_id: String,
+    pub container_name: String,
+    pub state: String,
+    pub depends_on: Vec<GroupDependency>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GroupDependency {
+    pub name: String,
+    pub condition: DependencyCondition,
+    /// 依赖崩溃并恢复后级联重启本成员
+    pub cascade: bool,
+}
+
+impl GroupInfo {
+    pub fn member(&self, name: &str) -> Option<&GroupMember> {
+        self.members.iter().find(|m| m.name == name || m.container_name == name)
+    }
+
+    /// 某个成员重启后需要级联重启的成员，按依赖顺序排列；级联会继续传递给它们的依赖者
+    pub fn cascade_targets(&self, container_id: &str) -> Vec<GroupMember> {
+        let Some(origin) = self.members.iter().find(|m| m.container_id == container_id) else {
+            return Vec::new();
+        };
+        let mut restarted: HashSet<String> = HashSet::from([origin.container_id.clone()]);
+        let mut targets = Vec::new();
+        // 成员已经按依赖顺序排列，顺序扫描一遍即可覆盖传递的级联
+        for member in &self.members {
+            let triggered = member.depends_on.iter().any(|d| {
+                d.cascade && self.member(&d.name).is_some_and(|dep| restarted.contains(&dep.container_id))
+            });
+            if triggered && restarted.insert(member.container_id.clone()) {
+                targets.push(member.clone());
+            }
+        }
+        targets
+    }
+}
+
+impl GroupMember {
+    pub fn is_running(&self) -> bool {
+        matches!(self.state.as_str(), "running" | "paused" | "restarting")
+    }
+}
+
+pub struct GroupManager {
+    docker: Docker,
+}
+
+impl GroupManager {
+    pub fn new(docker: Docker) -> Self {
+        Self { docker }
+    }
+
+    pub async fn list_groups(&self) -> Result<Vec<GroupInfo>> {
+        let containers = self.docker.list_containers(Some(ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        })).await?;
+        Ok(Self::build(containers))
+    }
+
+    pub async fn group(&self, name: &str) -> Result<GroupInfo> {
+        self.list_groups().await?
+            .into_iter()
+            .find(|g| g.name == name)
+            .ok_or_else(|| ApiError::not_found(format!("分组 {} 不存在", name)).into())
+    }
+
+    /// 容器所在的分组
+    pub async fn group_of(&self, container_id: &str) -> Result<Option<GroupInfo>> {
+        Ok(self.list_groups().await?
+            .into_iter()
+            .find(|g| g.members.iter().any(|m| m.container_id == container_id)))
+    }
+
+    fn build(containers: Vec<ContainerSummary>) -> Vec<GroupInfo> {
+        let mut groups: BTreeMap<String, (GroupSource, Vec<GroupMember>)> = BTreeMap::new();
+        for container in containers {
+            let labels = container.labels.clone().unwrap_or_default();
+            if labels.contains_key(LABEL_HELPER) {
+                continue;
+            }
+            // `docker compose run` 创建的一次性容器不属于项目
+            if labels.get(compose::LABEL_ONEOFF).is_some_and(|v| v.eq_ignore_ascii_case("true")) {
+                continue;
+            }
+            let (group, source) = match (labels.get(LABEL_GROUP), labels.get(compose::LABEL_PROJECT)) {
+                (Some(group), _) if !group.is_empty() => (group.clone(), GroupSource::Label),
+                (_, Some(project)) => (project.clone(), GroupSource::Compose),
+                _ => continue,
+            };
+            let Some(id) = container.id else {
+                continue;
+            };
+            let container_name = container.names.unwrap_or_default()
+                .first()
+                .map(|n| n.trim_start_matches('/').to_string())
+                .unwrap_or_default();
+            let name = labels.get(compose::LABEL_SERVICE).cloned().unwrap_or_else(|| container_name.clone());
+
+            groups.entry(group).or_insert((source, Vec::new())).1.push(GroupMember {
+                name,
+                container_id: id,
+                container_name,
+                state: container.state.unwrap_or_default(),
+                depends_on: parse_dependencies(&labels),
+            });
+        }
+
+        groups.into_iter()
+            .map(|(name, (source, mut members))| {
+                members.sort_by(|a, b| a.name.cmp(&b.name));
+                let (members, error) = match sort_members(&members) {
+                    Ok(sorted) => (sorted, None),
+                    Err(e) => (members, Some(e)),
+                };
+                GroupInfo { name, source, members, error }
+            })
+            .collect()
+    }
+}
+
+/// 合并 compose 的 depends_on 标签和自定义依赖标签
+fn parse_dependencies(labels: &HashMap<String, String>) -> Vec<GroupDependency> {
+    let mut result: Vec<GroupDependency> = Vec::new();
+    // compose 格式为 `服务:条件:是否随依赖重启`
+    for entry in labels.get(compose::LABEL_DEPENDS_ON).map(String::as_str).unwrap_or_default().split(',') {
+        let mut parts = entry.trim().split(':');
+        let name = parts.next().unwrap_or_default();
+        if name.is_empty() {
+            continue;
+        }
+        result.push(GroupDependency {
+            name: name.to_string(),
+            condition: parts.next().and_then(DependencyCondition::parse).unwrap_or_default(),
+            cascade: parts.next().is_some_and(|r| r.eq_ignore_ascii_case("true")),
+        });
+    }
+
+    let cascade = labels.get(LABEL_CASCADE_RESTART).is_some_and(|v| v.eq_ignore_ascii_case("true") || v == "1");
+    for entry in labels.get(LABEL_DEPENDS_ON).map(String::as_str).unwrap_or_default().split(',') {
+        let (name, condition) = match entry.trim().split_once(':') {
+            Some((name, condition)) => (name, DependencyCondition::parse(condition).unwrap_or_default()),
+            None => (entry.trim(), DependencyCondition::ServiceStarted),
+        };
+        if name.is_empty() {
+            continue;
+        }
+        match result.iter_mut().find(|d| d.name == name) {
+            Some(existing) => existing.condition = condition,
+            None => result.push(GroupDependency { name: name.to_string(), condition, cascade: false }),
+        }
+    }
+    if cascade {
+        for dependency in &mut result {
+            dependency.cascade = true;
+        }
+    }
+    result
+}
+
+/// 按依赖关系排序，组内不存在的依赖忽略
+fn sort_members(members: &[GroupMember]) -> std::result::Result<Vec<GroupMember>, String> {
+    let resolve = |name: &str| members.iter().position(|m| m.name == name || m.container_name == name);
+    let mut done = vec![false; members.len()];
+    let mut sorted = Vec::with_capacity(members.len());
+
+    while sorted.len() < members.len() {
+        let ready: Vec<usize> = (0..members.len())
+            .filter(|&i| !done[i])
+            .filter(|&i| members[i].depends_on.iter()
+                .filter_map(|d| resolve(&d.name))
+                .all(|j| done[j] || j == i))
+            .collect();
+        if ready.is_empty() {
+            let remaining: Vec<&str> = (0..members.len())
+                .filter(|&i| !done[i])
+                .map(|i| members[i].name.as_str())
+                .collect();
+            return Err(format!("成员之间存在循环依赖: {}", remaining.join(", ")));
+        }
+        for i in ready {
+            done[i] = true;
+            sorted.push(members[i].clone());
+        }
+    }
+    Ok(sorted)
+}
@@ -0,0 +1,153 @@
+use bollard::models::{ContainerInspectResponse, ContainerState, HealthStatusEnum};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use anyhow::Result;
+use crate::storage;
+
+pub(crate) const SETTINGS_FILE: &str = "health.json";
+// 未配置检查间隔时重新检查不健康容器的间隔
+const DEFAULT_RECHECK: Duration = Duration::from_secs(10);
+
+/// 设置为 `false` 时，健康检查失败不会重启该容器
+pub const LABEL_RESTART_UNHEALTHY: &str = "docker-manager.restart-unhealthy";
+
+/// 健康检查监控设置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthSettings {
+    /// 是否重启健康检查失败的容器
+    pub enabled: bool,
+    /// 容器启动后的宽限期（秒），期间不会因为健康检查失败而重启
+    pub grace_period: u64,
+    /// 健康检查连续失败的次数达到该值才重启
+    pub failure_threshold: i64,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            grace_period: 60,
+            failure_threshold: 3,
+        }
+    }
+}
+
+/// 容器的健康检查状态
+#[derive(Clone, Debug, Serialize)]
+pub struct ContainerHealth {
+    /// starting、healthy 或 unhealthy
+    pub status: String,
+    pub failing_streak: i64,
+    /// 最近一次检查的输出
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_output: Option<String>,
+}
+
+impl ContainerHealth {
+    /// 没有配置健康检查的容器返回 None
+    pub fn from_state(state: &ContainerState) -> Option<Self> {
+        let health = state.health.as_ref()?;
+        let status = health.status.filter(|s| !matches!(s, HealthStatusEnum::NONE | HealthStatusEnum::EMPTY))?;
+        Some(Self {
+            status: status.to_string(),
+            failing_streak: health.failing_streak.unwrap_or_default(),
+            last_output: health.log.as_ref()
+                .and_then(|log| log.last())
+                .and_then(|entry| entry.output.as_ref())
+                .map(|output| output.trim().to_string())
+                .filter(|output| !output.is_empty()),
+        })
+    }
+}
+
+/// 对不健康容器的处理
+#[derive(Debug, PartialEq)]
+pub enum UnhealthyAction {
+    /// 已经恢复健康，或者容器不再运行
+    Recovered,
+    /// 还在宽限期内或未达到失败次数，等待后重新检查
+    Wait(Duration),
+    Restart,
+    /// 监控已关闭或容器通过标签排除
+    Skip,
+}
+
+pub struct HealthSupervisor {
+    settings: Mutex<HealthSettings>,
+    // 正在跟踪的不健康容器，避免重复处理同一个容器
+    watching: Mutex<HashSet<String>>,
+}
+
+impl HealthSupervisor {
+    pub fn load() -> Result<Self> {
+        let settings: HealthSettings = storage::load_json(SETTINGS_FILE)?;
+        Ok(Self {
+            settings: Mutex::new(settings),
+            watching: Mutex::new(HashSet::new()),
+        })
+    }
+
+    pub fn settings(&self) -> HealthSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update_settings(&self, settings: HealthSettings) -> Result<()> {
+        if settings.failure_threshold < 1 {
+            return Err(anyhow::anyhow!("连续失败次数至少为 1"));
+        }
+        storage::save_json(SETTINGS_FILE, &settings)?;
+        *self.settings.lock().unwrap() = settings;
+        println!("已更新健康检查设置");
+        Ok(())
+    }
+
+    /// 开始跟踪容器；已经在跟踪时返回 false
+    pub fn watch(&self, id: &str) -> bool {
+        self.watching.lock().unwrap().insert(id.to_string())
+    }
+
+    pub fn unwatch(&self, id: &str) {
+        self.watching.lock().unwrap().remove(id);
+    }
+
+    pub fn evaluate(&self, inspect: &ContainerInspectResponse) -> UnhealthyAction {
+        let settings = self.settings();
+        let excluded = inspect.config.as_ref()
+            .and_then(|c| c.labels.as_ref())
+            .and_then(|l| l.get(LABEL_RESTART_UNHEALTHY))
+            .is_some_and(|v| v.eq_ignore_ascii_case("false") || v == "0");
+        if !settings.enabled || excluded {
+            return UnhealthyAction::Skip;
+        }
+
+        let state = inspect.state.clone().unwrap_or_default();
+        let health = ContainerHealth::from_state(&state);
+        if !state.running.unwrap_or(false) || health.as_ref().map(|h| h.status.as_str()) != Some("unhealthy") {
+            return UnhealthyAction::Recovered;
+        }
+
+        let uptime = state.started_at.as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .and_then(|t| (Utc::now() - t.with_timezone(&Utc)).to_std().ok())
+            .unwrap_or_default();
+        let grace = Duration::from_secs(settings.grace_period);
+        if uptime < grace {
+            return UnhealthyAction::Wait(grace - uptime);
+        }
+
+        if health.is_some_and(|h| h.failing_streak < settings.failure_threshold) {
+            let interval = inspect.config.as_ref()
+                .and_then(|c| c.healthcheck.as_ref())
+                .and_then(|h| h.interval)
+                .filter(|i| *i > 0)
+                .map(|i| Duration::from_nanos(i as u64))
+                .unwrap_or(DEFAULT_RECHECK);
+            return UnhealthyAction::Wait(interval);
+        }
+        UnhealthyAction::Restart
+    }
+}
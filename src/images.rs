@@ -0,0 +1,488 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE;
+use bollard::Docker;
+use bollard::auth::DockerCredentials;
+use bollard::container::ListContainersOptions;
+use bollard::image::{CreateImageOptions, ListImagesOptions, RemoveImageOptions};
+use futures::StreamExt;
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
+use anyhow::Result;
+use crate::docker::DockerClient;
+use crate::registry::RegistryStore;
+
+/// 镜像列表中的单个镜像
+#[derive(Clone, Debug, Serialize)]
+pub struct ImageInfo {
+    pub id: String,
+    pub tags: Vec<String>,
+    pub digests: Vec<String>,
+    pub size: i64,
+    pub created: i64,
+    pub containers: Vec<ImageContainer>,
+}
+
+/// 使用某个镜像的容器
+#[derive(Clone, Debug, Serialize)]
+pub struct ImageContainer {
+    pub id: String,
+    pub name: String,
+    pub managed: bool,
+}
+
+/// 镜像拉取过程中某一层的进度
+#[derive(Clone, Debug, Serialize)]
+pub struct PullProgress {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+}
+
+/// 删除镜像的结果
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum RemoveOutcome {
+    Removed { deleted: Vec<String>, untagged: Vec<String> },
+    InUse { containers: Vec<String> },
+}
+
+/// 清理镜像的结果
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PruneReport {
+    pub deleted: Vec<String>,
+    pub skipped: Vec<String>,
+    pub space_reclaimed: i64,
+}
+
+/// 创建或更新容器时的镜像拉取策略
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PullPolicy {
+    /// 每次都从仓库拉取
+    Always,
+    /// 本地不存在时才拉取
+    #[default]
+    Missing,
+    /// 只使用本地镜像，适用于离线环境和本地构建的镜像
+    Never,
+}
+
+/// 拉取失败的原因
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PullErrorKind {
+    NotFound,
+    Unauthorized,
+    Network,
+    /// 拉取策略为 never 且本地没有该镜像
+    NotPresent,
+    Other,
+}
+
+#[derive(Debug)]
+pub struct PullError {
+    pub image: String,
+    pub kind: PullErrorKind,
+    pub message: String,
+}
+
+impl PullError {
+    // 根据 Docker 返回的状态码和错误信息判断失败原因
+    fn classify(image: &str, status_code: Option<u16>, message: String, has_credentials: bool) -> Self {
+        let lower = message.to_ascii_lowercase();
+        let kind = if status_code == Some(401) || status_code == Some(403)
+            || lower.contains("unauthorized")
+            || lower.contains("authentication required")
+            || lower.contains("no basic auth credentials")
+            || lower.contains("incorrect username or password")
+        {
+            PullErrorKind::Unauthorized
+        } else if lower.contains("pull access denied") || lower.contains("access to the resource is denied") {
+            // Docker Hub 对不存在的仓库和无权访问的仓库返回同样的信息，
+            // 已经配置了凭据时更可能是权限问题
+            if has_credentials { PullErrorKind::Unauthorized } else { PullErrorKind::NotFound }
+        } else if status_code == Some(404)
+            || lower.contains("not found")
+            || lower.contains("manifest unknown")
+            || lower.contains("does not exist")
+        {
+            PullErrorKind::NotFound
+        } else if lower.contains("timeout")
+            || lower.contains("timed out")
+            || lower.contains("connection refused")
+            || lower.contains("connection reset")
+            || lower.contains("no such host")
+            || lower.contains("dial tcp")
+            || lower.contains("network is unreachable")
+            || lower.contains("tls handshake")
+            || lower.contains("temporary failure in name resolution")
+        {
+            PullErrorKind::Network
+        } else {
+            PullErrorKind::Other
+        };
+        Self { image: image.to_string(), kind, message }
+    }
+
+    fn from_docker(image: &str, error: bollard::errors::Error, has_credentials: bool) -> Self {
+        match error {
+            bollard::errors::Error::DockerResponseServerError { status_code, message } => {
+                Self::classify(image, Some(status_code), message, has_credentials)
+            }
+            bollard::errors::Error::RequestTimeoutError => Self {
+                image: image.to_string(),
+                kind: PullErrorKind::Network,
+                message: error.to_string(),
+            },
+            other => Self::classify(image, None, other.to_string(), has_credentials),
+        }
+    }
+}
+
+impl std::fmt::Display for PullError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            PullErrorKind::NotFound => write!(f, "镜像 {} 不存在: {}", self.image, self.message),
+            PullErrorKind::Unauthorized => write!(f, "无权拉取镜像 {}，请检查仓库凭据: {}", self.image, self.message),
+            PullErrorKind::Network => write!(f, "无法连接镜像仓库拉取 {}: {}", self.image, self.message),
+            PullErrorKind::NotPresent => write!(f, "本地不存在镜像 {}，且拉取策略为 never", self.image),
+            PullErrorKind::Other => write!(f, "拉取镜像 {} 失败: {}", self.image, self.message),
+        }
+    }
+}
+
+impl std::error::Error for PullError {}
+
+/// 管理器所依赖的镜像：受管容器正在使用的镜像 ID，以及保存的配置中引用的镜像名
+#[derive(Clone, Debug, Default)]
+pub struct ImageReferences {
+    pub managed_containers: HashSet<String>,
+    pub image_names: HashSet<String>,
+}
+
+impl ImageReferences {
+    fn protects(&self, image_id: &str, tags: &[String], users: &[ImageContainer]) -> bool {
+        users.iter().any(|c| c.managed)
+            || self.image_names.contains(image_id)
+            || tags.iter().any(|tag| self.image_names.contains(tag))
+    }
+}
+
+/// 未指定标签时补上 `latest`，与 `docker pull` 的行为保持一致
+pub fn normalize_image_name(image: &str) -> String {
+    let last_segment = image.rsplit('/').next().unwrap_or(image);
+    if image.contains('@') || last_segment.contains(':') {
+        image.to_string()
+    } else {
+        format!("{}:latest", image)
+    }
+}
+
+// 查询仓库摘要的超时
+const REGISTRY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+// `GET /distribution/:name/json` 的响应，只用到摘要
+#[derive(Deserialize)]
+struct DistributionInspect {
+    #[serde(rename = "Descriptor")]
+    descriptor: DistributionDescriptor,
+}
+
+#[derive(Deserialize)]
+struct DistributionDescriptor {
+    digest: String,
+}
+
+pub struct ImageManager {
+    docker: Docker,
+    registry: Arc<RegistryStore>,
+}
+
+impl ImageManager {
+    pub fn new(docker: Docker, registry: Arc<RegistryStore>) -> Self {
+        Self { docker, registry }
+    }
+
+    pub async fn list_images(&self, refs: &ImageReferences) -> Result<Vec<ImageInfo>> {
+        let images = self.docker.list_images(Some(ListImagesOptions::<String> {
+            all: false,
+            ..Default::default()
+        })).await?;
+        let users = self.image_users(refs).await?;
+
+        let mut result: Vec<ImageInfo> = images.into_iter()
+            .map(|image| ImageInfo {
+                containers: users.get(&image.id).cloned().unwrap_or_default(),
+                tags: image.repo_tags.into_iter().filter(|t| t != "<none>:<none>").collect(),
+                digests: image.repo_digests.into_iter().filter(|d| d != "<none>@<none>").collect(),
+                id: image.id,
+                size: image.size,
+                created: image.created,
+            })
+            .collect();
+        result.sort_by_key(|image| std::cmp::Reverse(image.created));
+        Ok(result)
+    }
+
+    /// 拉取镜像，进度输出到日志；传入 `progress` 时同时把每层的进度发送给调用方
+    pub async fn pull_image(&self, image: &str, progress: Option<&UnboundedSender<PullProgress>>) -> Result<()> {
+        println!("开始拉取镜像: {}", image);
+        let pull_opts = CreateImageOptions::<String> {
+            from_image: image.to_string(),
+            ..Default::default()
+        };
+
+        let credentials = self.credentials(image).await;
+        let has_credentials = credentials.is_some();
+        if has_credentials {
+            println!("使用已配置的仓库凭据拉取: {}", image);
+        }
+
+        println!("开始下载镜像层...");
+        let mut stream = self.docker.create_image(Some(pull_opts), None, credentials);
+        let mut last_progress = HashMap::new();
+
+        while let Some(result) = stream.next().await {
+            match result {
+                Ok(info) => {
+                    if let Some(error) = info.error {
+                        let error = PullError::classify(image, None, error, has_credentials);
+                        eprintln!("❌ {}", error);
+                        return Err(error.into());
+                    }
+                    let (current, total) = info.progress_detail.as_ref()
+                        .map(|d| (d.current, d.total))
+                        .unwrap_or_default();
+                    match (info.id, info.status) {
+                        (Some(id), Some(status)) => {
+                            let progress_text = info.progress.as_deref().unwrap_or_default();
+                            let current_progress = format!("{} - {}", status, progress_text);
+                            if last_progress.get(&id) != Some(&current_progress) {
+                                if status.contains("Pull complete") {
+                                    println!("✅ 层 [{}] 下载完成", id);
+                                } else if status.contains("Downloading") {
+                                    println!("⏳ 层 [{}] {}", id, progress_text);
+                                } else if status.contains("Extracting") {
+                                    println!("📦 层 [{}] 正在解压 {}", id, progress_text);
+                                } else {
+                                    println!("层 [{}] {}", id, status);
+                                }
+                                last_progress.insert(id.clone(), current_progress);
+                                if let Some(tx) = progress {
+                                    let _ = tx.send(PullProgress {
+                                        layer: Some(id),
+                                        status,
+                                        progress: info.progress,
+                                        current,
+                                        total,
+                                    });
+                                }
+                            }
+                        }
+                        (None, Some(status)) => {
+                            println!("状态更新: {}", status);
+                            if let Some(tx) = progress {
+                                let _ = tx.send(PullProgress {
+                                    layer: None,
+                                    status,
+                                    progress: None,
+                                    current: None,
+                                    total: None,
+                                });
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Err(e) => {
+                    let error = PullError::from_docker(image, e, has_credentials);
+                    eprintln!("❌ {}", error);
+                    return Err(error.into());
+                }
+            }
+        }
+
+        println!("✨ 镜像拉取完成: {}", image);
+        Ok(())
+    }
+
+    // 凭据助手是外部进程，放到阻塞线程里执行
+    async fn credentials(&self, image: &str) -> Option<DockerCredentials> {
+        let registry = self.registry.clone();
+        let image_name = image.to_string();
+        tokio::task::spawn_blocking(move || registry.credentials_for_image(&image_name))
+            .await
+            .unwrap_or(None)
+    }
+
+    /// 不拉取镜像，通过 Docker 查询仓库中该标签当前的摘要（`GET /distribution/:name/json`）。
+    /// bollard 没有封装这个接口，直接通过 Docker socket 请求
+    pub async fn registry_digest(&self, image: &str) -> Result<String> {
+        let credentials = self.credentials(image).await;
+        let has_credentials = credentials.is_some();
+        let uri: hyper::Uri = hyperlocal::Uri::new(
+            DockerClient::get_docker_socket_path(),
+            &format!("/distribution/{}/json", image),
+        ).into();
+        let mut request = hyper::Request::get(uri);
+        if let Some(credentials) = &credentials {
+            request = request.header("X-Registry-Auth", URL_SAFE.encode(serde_json::to_vec(credentials)?));
+        }
+        let client = hyper::Client::builder().build::<_, hyper::Body>(hyperlocal::UnixConnector);
+        let response = tokio::time::timeout(REGISTRY_TIMEOUT, client.request(request.body(hyper::Body::empty())?))
+            .await
+            .map_err(|_| PullError {
+                image: image.to_string(),
+                kind: PullErrorKind::Network,
+                message: "查询镜像仓库超时".to_string(),
+            })?
+            .map_err(|e| PullError::classify(image, None, e.to_string(), has_credentials))?;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        if !status.is_success() {
+            // Docker 的错误响应为 {"message": "..."}
+            let message = serde_json::from_slice::<serde_json::Value>(&body).ok()
+                .and_then(|v| v.get("message")?.as_str().map(str::to_string))
+                .unwrap_or_else(|| String::from_utf8_lossy(&body).trim().to_string());
+            return Err(PullError::classify(image, Some(status.as_u16()), message, has_credentials).into());
+        }
+        let inspect: DistributionInspect = serde_json::from_slice(&body)?;
+        Ok(inspect.descriptor.digest)
+    }
+
+    /// 按拉取策略准备镜像，`missing` 时先通过 inspect 检查本地是否已有
+    pub async fn ensure_image(
+        &self,
+        image: &str,
+        policy: PullPolicy,
+        progress: Option<&UnboundedSender<PullProgress>>,
+    ) -> Result<()> {
+        if policy == PullPolicy::Always {
+            return self.pull_image(image, progress).await;
+        }
+
+        match self.docker.inspect_image(image).await {
+            Ok(_) => {
+                println!("镜像 {} 已存在，跳过拉取", image);
+                Ok(())
+            }
+            Err(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. }) => {
+                if policy == PullPolicy::Never {
+                    let error = PullError {
+                        image: image.to_string(),
+                        kind: PullErrorKind::NotPresent,
+                        message: String::new(),
+                    };
+                    eprintln!("❌ {}", error);
+                    return Err(error.into());
+                }
+                self.pull_image(image, progress).await
+            }
+            Err(e) => Err(anyhow::anyhow!("检查镜像 {} 失败: {}", image, e)),
+        }
+    }
+
+    pub async fn remove_image(&self, image: &str, force: bool, refs: &ImageReferences) -> Result<RemoveOutcome> {
+        let inspect = self.docker.inspect_image(image).await?;
+        let image_id = inspect.id.unwrap_or_default();
+        let tags = inspect.repo_tags.unwrap_or_default();
+        let users = self.image_users(refs).await?;
+        let containers = users.get(&image_id).cloned().unwrap_or_default();
+
+        if refs.protects(&image_id, &tags, &containers) {
+            let mut names: Vec<String> = containers.into_iter()
+                .filter(|c| c.managed)
+                .map(|c| c.name)
+                .collect();
+            if names.is_empty() {
+                names.push("已保存的容器配置".to_string());
+            }
+            println!("镜像 {} 正被受管容器使用，拒绝删除: {:?}", image, names);
+            return Ok(RemoveOutcome::InUse { containers: names });
+        }
+
+        let items = self.docker.remove_image(
+            image,
+            Some(RemoveImageOptions { force, noprune: false }),
+            None,
+        ).await?;
+        println!("已删除镜像: {}", image);
+
+        Ok(RemoveOutcome::Removed {
+            deleted: items.iter().filter_map(|i| i.deleted.clone()).collect(),
+            untagged: items.iter().filter_map(|i| i.untagged.clone()).collect(),
+        })
+    }
+
+    /// 清理未被容器使用的镜像；`all` 为 false 时只清理悬空镜像
+    pub async fn prune_images(&self, all: bool, refs: &ImageReferences) -> Result<PruneReport> {
+        let mut filters = HashMap::new();
+        if !all {
+            filters.insert("dangling".to_string(), vec!["true".to_string()]);
+        }
+        let images = self.docker.list_images(Some(ListImagesOptions::<String> {
+            all: false,
+            filters,
+            ..Default::default()
+        })).await?;
+        let users = self.image_users(refs).await?;
+
+        let mut report = PruneReport::default();
+        for image in images {
+            let containers = users.get(&image.id).cloned().unwrap_or_default();
+            if refs.protects(&image.id, &image.repo_tags, &containers) {
+                report.skipped.push(image.id);
+                continue;
+            }
+            // 仍被非受管容器使用的镜像交给 Docker 处理，这里同样跳过
+            if !containers.is_empty() {
+                continue;
+            }
+            // 已确认没有容器使用，按 ID 删除有多个标签的镜像时需要 force，否则 Docker 返回 409
+            let options = RemoveImageOptions { force: true, ..Default::default() };
+            match self.docker.remove_image(&image.id, Some(options), None).await {
+                Ok(_) => {
+                    println!("已清理镜像: {}", image.id);
+                    report.space_reclaimed += image.size;
+                    report.deleted.push(image.id);
+                }
+                Err(e) => eprintln!("清理镜像 {} 失败: {}", image.id, e),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// 按镜像 ID 汇总使用它的容器
+    async fn image_users(&self, refs: &ImageReferences) -> Result<HashMap<String, Vec<ImageContainer>>> {
+        let containers = self.docker.list_containers(Some(ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        })).await?;
+
+        let mut users: HashMap<String, Vec<ImageContainer>> = HashMap::new();
+        for container in containers {
+            let (Some(id), Some(image_id)) = (container.id, container.image_id) else {
+                continue;
+            };
+            let name = container.names.unwrap_or_default()
+                .first()
+                .map(|n| n.trim_start_matches('/').to_string())
+                .unwrap_or_default();
+            users.entry(image_id).or_default().push(ImageContainer {
+                managed: refs.managed_containers.contains(&id),
+                id,
+                name,
+            });
+        }
+        Ok(users)
+    }
+}
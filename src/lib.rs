@@ -2,4 +2,29 @@ pub mod models;
 pub mod monitor;
 pub mod restart;
 pub mod docker;
+pub mod images;
+pub mod networks;
+pub mod volumes;
+pub mod backups;
+pub mod compose;
+pub mod stacks;
+pub mod groups;
+pub mod health;
+pub mod probes;
+pub mod oom;
+pub mod export;
+pub mod registry;
+pub mod storage;
+pub mod error;
+pub mod notify;
+pub mod audit;
+pub mod events;
+pub mod exec;
+pub mod files;
+pub mod snapshots;
+pub mod cron;
+pub mod scheduler;
+pub mod maintenance;
+pub mod status;
+pub mod updater;
 pub mod web; 
\ No newline at end of file
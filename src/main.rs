@@ -1,12 +1,7 @@
-mod docker;
-mod monitor;
-mod restart;
-mod web;
-
 use anyhow::Result;
 use std::sync::Arc;
-use monitor::ContainerMonitor;
-use docker_manager::models;
+use docker_manager::monitor::ContainerMonitor;
+use docker_manager::web;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -19,8 +14,23 @@ async fn main() -> Result<()> {
         web::start_web_server(monitor_clone).await;
     });
 
+    // 启动镜像自动更新
+    tokio::spawn(monitor.clone().start_auto_update());
+
+    // 定时同步容器配置
+    tokio::spawn(monitor.clone().start_reconciliation());
+
+    // 启动计划任务
+    tokio::spawn(monitor.clone().start_scheduler());
+
+    // 启动存活探测
+    tokio::spawn(monitor.clone().start_probes());
+
+    // 检查出现故障的容器是否已经恢复
+    tokio::spawn(monitor.clone().start_notifications());
+
     // 启动容器监控
     monitor.start_monitoring().await?;
 
     Ok(())
-} 
\ No newline at end of file
+}
@@ -0,0 +1,173 @@
+use chrono::{DateTime, Duration, Local};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use anyhow::Result;
+use crate::storage;
+use crate::error::ApiError;
+
+const MAINTENANCE_FILE: &str = "maintenance.json";
+/// 维护模式最长持续 7 天，到期自动结束
+pub const MAX_DURATION_SECS: i64 = 7 * 24 * 3600;
+
+/// 开启维护模式的请求，`container` 和 `label` 都为空时对所有容器生效
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceRequest {
+    pub container: Option<String>,
+    /// `key` 或 `key=value`
+    pub label: Option<String>,
+    /// 持续的秒数，与 `until` 二选一
+    pub duration: Option<u64>,
+    pub until: Option<DateTime<Local>>,
+    pub reason: Option<String>,
+}
+
+/// 一个维护窗口，期间不自动重启匹配的容器
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_by: Option<String>,
+    pub started: DateTime<Local>,
+    pub expires: DateTime<Local>,
+}
+
+impl MaintenanceWindow {
+    pub fn is_global(&self) -> bool {
+        self.container.is_none() && self.label.is_none()
+    }
+
+    /// 容器按 ID 或名称匹配，标签为 `key` 时只要求存在
+    pub fn matches(&self, id: &str, name: &str, labels: &HashMap<String, String>) -> bool {
+        if let Some(container) = &self.container {
+            return container == name || (container.len() >= 12 && id.starts_with(container.as_str()));
+        }
+        if let Some(label) = &self.label {
+            return match label.split_once('=') {
+                Some((key, value)) => labels.get(key).is_some_and(|v| v == value),
+                None => labels.contains_key(label),
+            };
+        }
+        true
+    }
+
+    pub fn scope(&self) -> String {
+        match (&self.container, &self.label) {
+            (Some(container), _) => format!("容器 {}", container),
+            (None, Some(label)) => format!("标签 {}", label),
+            (None, None) => "全部容器".to_string(),
+        }
+    }
+}
+
+/// 维护窗口，保存在数据目录下的 maintenance.json
+pub struct MaintenanceManager {
+    windows: Mutex<Vec<MaintenanceWindow>>,
+}
+
+impl MaintenanceManager {
+    pub fn load() -> Result<Self> {
+        let windows: Vec<MaintenanceWindow> = storage::load_json(MAINTENANCE_FILE)?;
+        Ok(Self { windows: Mutex::new(windows) })
+    }
+
+    /// 当前生效的维护窗口，顺便清理已到期的
+    pub fn active(&self) -> Vec<MaintenanceWindow> {
+        let mut windows = self.windows.lock().unwrap();
+        let now = Local::now();
+        let before = windows.len();
+        windows.retain(|w| {
+            let active = w.expires > now;
+            if !active {
+                println!("维护模式 #{} ({}) 已到期", w.id, w.scope());
+            }
+            active
+        });
+        if windows.len() != before {
+            if let Err(e) = storage::save_json(MAINTENANCE_FILE, &*windows) {
+                eprintln!("保存维护模式失败: {}", e);
+            }
+        }
+        windows.clone()
+    }
+
+    /// 匹配该容器的维护窗口
+    pub fn find(&self, id: &str, name: &str, labels: &HashMap<String, String>) -> Option<MaintenanceWindow> {
+        self.active().into_iter().find(|w| w.matches(id, name, labels))
+    }
+
+    pub fn global(&self) -> Option<MaintenanceWindow> {
+        self.active().into_iter().find(|w| w.is_global())
+    }
+
+    /// 容器改名后，按原名称开启的维护窗口改为新名称
+    pub fn rename(&self, old: &str, new: &str) {
+        let mut windows = self.windows.lock().unwrap();
+        let mut changed = false;
+        for window in windows.iter_mut().filter(|w| w.container.as_deref() == Some(old)) {
+            window.container = Some(new.to_string());
+            changed = true;
+        }
+        if changed {
+            println!("容器 {} 的维护模式已改为 {}", old, new);
+            if let Err(e) = storage::save_json(MAINTENANCE_FILE, &*windows) {
+                eprintln!("保存维护模式失败: {}", e);
+            }
+        }
+    }
+
+    /// `container` 需要由调用方解析为容器名
+    pub fn start(&self, request: MaintenanceRequest, started_by: Option<String>) -> Result<MaintenanceWindow> {
+        let container = request.container.filter(|c| !c.is_empty());
+        let label = request.label.filter(|l| !l.is_empty());
+        if container.is_some() && label.is_some() {
+            return Err(ApiError::invalid("container 和 label 只能指定一个").into());
+        }
+        let now = Local::now();
+        let expires = match (request.duration, request.until) {
+            (Some(duration), None) => now + Duration::seconds(duration.min(MAX_DURATION_SECS as u64 + 1) as i64),
+            (None, Some(until)) => until,
+            _ => return Err(ApiError::invalid("需要指定 duration 或 until 中的一个").into()),
+        };
+        if expires <= now {
+            return Err(ApiError::invalid("结束时间必须晚于当前时间").into());
+        }
+        if expires > now + Duration::seconds(MAX_DURATION_SECS) {
+            return Err(ApiError::invalid(format!("维护模式最长 {} 天", MAX_DURATION_SECS / 86400)).into());
+        }
+
+        self.active();
+        let mut windows = self.windows.lock().unwrap();
+        let window = MaintenanceWindow {
+            id: windows.iter().map(|w| w.id).max().unwrap_or(0) + 1,
+            container,
+            label,
+            reason: request.reason.filter(|r| !r.is_empty()),
+            started_by,
+            started: now,
+            expires,
+        };
+        windows.push(window.clone());
+        storage::save_json(MAINTENANCE_FILE, &*windows)?;
+        println!("已开启维护模式 #{} ({})，到 {} 结束", window.id, window.scope(), window.expires.format("%Y-%m-%d %H:%M:%S"));
+        Ok(window)
+    }
+
+    /// 提前结束维护模式
+    pub fn end(&self, id: u64) -> Result<MaintenanceWindow> {
+        let mut windows = self.windows.lock().unwrap();
+        let index = windows.iter().position(|w| w.id == id)
+            .ok_or_else(|| ApiError::not_found(format!("维护模式 #{} 不存在", id)))?;
+        let window = windows.remove(index);
+        storage::save_json(MAINTENANCE_FILE, &*windows)?;
+        println!("已结束维护模式 #{} ({})", window.id, window.scope());
+        Ok(window)
+    }
+}
@@ -1,6 +1,12 @@
-use bollard::models::HostConfig;
+use bollard::models::{
+    ContainerInspectResponse, HostConfig, Mount, MountBindOptions, MountBindOptionsPropagationEnum,
+    MountPoint, MountPointTypeEnum, MountTmpfsOptions, MountTypeEnum, MountVolumeOptions,
+    MountVolumeOptionsDriverConfig,
+};
 use bollard::container::Config;
 use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::str::FromStr;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ContainerConfig {
@@ -9,7 +15,10 @@ pub struct ContainerConfig {
     pub image: String,
     pub host_config: Option<HostConfig>,
     pub config: Option<Config<String>>,
-    pub ip_address: Option<String>,
+    #[serde(default)]
+    pub networks: Vec<ContainerNetwork>,
+    #[serde(default)]
+    pub mounts: Vec<MountSpec>,
 }
 
 impl ContainerConfig {
@@ -26,7 +35,410 @@ impl ContainerConfig {
             image,
             host_config,
             config,
-            ip_address: None,
+            networks: Vec::new(),
+            mounts: Vec::new(),
         }
     }
-} 
\ No newline at end of file
+}
+
+/// 容器在某个网络中的连接信息
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ContainerNetwork {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipv6_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mac_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+impl ContainerNetwork {
+    pub fn from_inspect(inspect: &ContainerInspectResponse) -> Vec<Self> {
+        let non_empty = |value: &Option<String>| value.clone().filter(|v| !v.is_empty());
+        let mut networks: Vec<Self> = inspect.network_settings.as_ref()
+            .and_then(|settings| settings.networks.as_ref())
+            .map(|networks| {
+                networks.iter()
+                    .map(|(name, endpoint)| ContainerNetwork {
+                        name: name.clone(),
+                        network_id: non_empty(&endpoint.network_id),
+                        ip_address: non_empty(&endpoint.ip_address),
+                        ipv6_address: non_empty(&endpoint.global_ipv6_address),
+                        mac_address: non_empty(&endpoint.mac_address),
+                        gateway: non_empty(&endpoint.gateway),
+                        aliases: endpoint.aliases.clone().unwrap_or_default(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        networks.sort_by(|a, b| a.name.cmp(&b.name));
+        networks
+    }
+
+    /// 容器的主 IP：优先 bridge 网络，否则取第一个有 IP 的网络
+    pub fn primary_ip(networks: &[Self]) -> Option<String> {
+        networks.iter()
+            .find(|n| n.name == "bridge" && n.ip_address.is_some())
+            .or_else(|| networks.iter().find(|n| n.ip_address.is_some()))
+            .and_then(|n| n.ip_address.clone())
+    }
+}
+
+/// 挂载点配置，与 `docker run --mount` 支持的三种类型一一对应
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MountSpec {
+    Bind {
+        source: String,
+        target: String,
+        #[serde(default)]
+        read_only: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        propagation: Option<MountBindOptionsPropagationEnum>,
+    },
+    Volume {
+        /// 卷名称，为空时由 Docker 创建匿名卷
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+        target: String,
+        #[serde(default)]
+        read_only: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        driver: Option<String>,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        driver_opts: HashMap<String, String>,
+        #[serde(default)]
+        no_copy: bool,
+    },
+    Tmpfs {
+        target: String,
+        /// 大小上限（字节）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<i64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mode: Option<i64>,
+    },
+}
+
+impl MountSpec {
+    pub fn target(&self) -> &str {
+        match self {
+            MountSpec::Bind { target, .. }
+            | MountSpec::Volume { target, .. }
+            | MountSpec::Tmpfs { target, .. } => target,
+        }
+    }
+
+    /// Docker 只接受绝对路径作为绑定挂载的来源，相对路径需要调用方先解析
+    pub fn check_bind_source(&self) -> anyhow::Result<()> {
+        match self {
+            MountSpec::Bind { source, .. } if !source.starts_with('/') => {
+                Err(anyhow::anyhow!("绑定挂载的来源必须是绝对路径: {}", source))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 转换为创建容器时使用的 `HostConfig.Mounts` 条目
+    pub fn to_mount(&self) -> Mount {
+        match self {
+            MountSpec::Bind { source, target, read_only, propagation } => Mount {
+                typ: Some(MountTypeEnum::BIND),
+                source: Some(source.clone()),
+                target: Some(target.clone()),
+                read_only: Some(*read_only),
+                bind_options: propagation.map(|p| MountBindOptions {
+                    propagation: Some(p),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            MountSpec::Volume { source, target, read_only, driver, driver_opts, no_copy } => Mount {
+                typ: Some(MountTypeEnum::VOLUME),
+                source: source.clone(),
+                target: Some(target.clone()),
+                read_only: Some(*read_only),
+                volume_options: Some(MountVolumeOptions {
+                    no_copy: Some(*no_copy),
+                    driver_config: driver.as_ref().map(|name| MountVolumeOptionsDriverConfig {
+                        name: Some(name.clone()),
+                        options: if driver_opts.is_empty() { None } else { Some(driver_opts.clone()) },
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            MountSpec::Tmpfs { target, size, mode } => Mount {
+                typ: Some(MountTypeEnum::TMPFS),
+                target: Some(target.clone()),
+                tmpfs_options: Some(MountTmpfsOptions {
+                    size_bytes: *size,
+                    mode: *mode,
+                }),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// 从 inspect 结果中的 `Mounts` 还原实际生效的挂载点
+    pub fn from_mount_point(point: &MountPoint, declared: Option<&Mount>) -> Option<Self> {
+        let target = point.destination.clone()?;
+        let read_only = point.rw == Some(false);
+        match point.typ? {
+            MountPointTypeEnum::BIND => Some(MountSpec::Bind {
+                source: point.source.clone().unwrap_or_default(),
+                target,
+                read_only,
+                propagation: point.propagation.as_deref()
+                    .filter(|p| !p.is_empty())
+                    .and_then(|p| p.parse().ok()),
+            }),
+            MountPointTypeEnum::VOLUME => {
+                let driver_opts = declared
+                    .and_then(|m| m.volume_options.as_ref())
+                    .and_then(|o| o.driver_config.as_ref())
+                    .and_then(|c| c.options.clone())
+                    .unwrap_or_default();
+                let no_copy = declared
+                    .and_then(|m| m.volume_options.as_ref())
+                    .and_then(|o| o.no_copy)
+                    .unwrap_or(false);
+                Some(MountSpec::Volume {
+                    source: point.name.clone(),
+                    target,
+                    read_only,
+                    driver: point.driver.clone().filter(|d| !d.is_empty()),
+                    driver_opts,
+                    no_copy,
+                })
+            }
+            MountPointTypeEnum::TMPFS => {
+                let options = declared.and_then(|m| m.tmpfs_options.as_ref());
+                Some(MountSpec::Tmpfs {
+                    target,
+                    size: options.and_then(|o| o.size_bytes),
+                    mode: options.and_then(|o| o.mode),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// 汇总容器的所有挂载点，包括 `--tmpfs` 声明的（这类挂载不会出现在 `Mounts` 中）
+    pub fn from_inspect(inspect: &ContainerInspectResponse) -> Vec<Self> {
+        let declared = inspect.host_config.as_ref().and_then(|h| h.mounts.as_ref());
+        let mut mounts: Vec<Self> = inspect.mounts.iter()
+            .flatten()
+            .filter_map(|point| {
+                let mount = declared.and_then(|list| {
+                    list.iter().find(|m| m.target.is_some() && m.target == point.destination)
+                });
+                Self::from_mount_point(point, mount)
+            })
+            .collect();
+
+        if let Some(tmpfs) = inspect.host_config.as_ref().and_then(|h| h.tmpfs.as_ref()) {
+            for (target, options) in tmpfs {
+                if mounts.iter().any(|m| m.target() == target) {
+                    continue;
+                }
+                let mut size = None;
+                let mut mode = None;
+                for option in options.split(',') {
+                    match option.split_once('=') {
+                        Some(("size", value)) => size = parse_size(value).ok(),
+                        Some(("mode", value)) => mode = i64::from_str_radix(value, 8).ok(),
+                        _ => {}
+                    }
+                }
+                mounts.push(MountSpec::Tmpfs { target: target.clone(), size, mode });
+            }
+        }
+
+        mounts
+    }
+
+    /// 解析 `-v` 风格的简写：`[来源:]容器路径[:选项]`。
+    /// 以 `.` 或 `~` 开头的来源是相对路径的绑定挂载，由调用方按所在目录解析（见 compose）
+    fn parse_volume_arg(arg: &str) -> anyhow::Result<Self> {
+        let parts: Vec<&str> = arg.split(':').collect();
+        let (source, target, options) = match parts.as_slice() {
+            [target] => (None, *target, ""),
+            [source, target] => (Some(*source), *target, ""),
+            [source, target, options] => (Some(*source), *target, *options),
+            _ => return Err(anyhow::anyhow!("无效的挂载配置: {}", arg)),
+        };
+        if target.is_empty() {
+            return Err(anyhow::anyhow!("挂载点缺少容器路径: {}", arg));
+        }
+
+        let mut read_only = false;
+        let mut propagation = None;
+        let mut no_copy = false;
+        for option in options.split(',').filter(|o| !o.is_empty()) {
+            match option {
+                "ro" => read_only = true,
+                "rw" => read_only = false,
+                "nocopy" => no_copy = true,
+                // SELinux 标签由 Docker 在 binds 中处理，Mounts 不支持，忽略即可
+                "z" | "Z" => {}
+                other => {
+                    propagation = Some(other.parse::<MountBindOptionsPropagationEnum>()
+                        .map_err(|_| anyhow::anyhow!("未知的挂载选项: {}", other))?);
+                }
+            }
+        }
+
+        match source {
+            Some(source) if source.starts_with('/') || source.starts_with('.') || source.starts_with('~') => {
+                Ok(MountSpec::Bind {
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    read_only,
+                    propagation,
+                })
+            }
+            source => {
+                if propagation.is_some() {
+                    return Err(anyhow::anyhow!("只有绑定挂载支持传播选项: {}", arg));
+                }
+                Ok(MountSpec::Volume {
+                    source: source.filter(|s| !s.is_empty()).map(|s| s.to_string()),
+                    target: target.to_string(),
+                    read_only,
+                    driver: None,
+                    driver_opts: HashMap::new(),
+                    no_copy,
+                })
+            }
+        }
+    }
+
+    /// 解析 `--mount` 风格的键值对：`type=volume,source=data,target=/data,ro`
+    fn parse_mount_arg(arg: &str) -> anyhow::Result<Self> {
+        let mut typ = "volume".to_string();
+        let mut source = None;
+        let mut target = None;
+        let mut read_only = false;
+        let mut propagation = None;
+        let mut driver = None;
+        let mut driver_opts = HashMap::new();
+        let mut no_copy = false;
+        let mut size = None;
+        let mut mode = None;
+
+        for field in arg.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let (key, value) = match field.split_once('=') {
+                Some((key, value)) => (key, Some(value)),
+                None => (field, None),
+            };
+            let flag = || -> anyhow::Result<bool> {
+                match value {
+                    None | Some("true") | Some("1") => Ok(true),
+                    Some("false") | Some("0") => Ok(false),
+                    Some(v) => Err(anyhow::anyhow!("无效的布尔值 {}={}", key, v)),
+                }
+            };
+            let required = || value.map(|v| v.to_string())
+                .ok_or_else(|| anyhow::anyhow!("挂载选项 {} 缺少取值", key));
+
+            match key {
+                "type" => typ = required()?,
+                "source" | "src" => source = Some(required()?),
+                "target" | "destination" | "dst" => target = Some(required()?),
+                "readonly" | "ro" => read_only = flag()?,
+                "bind-propagation" => {
+                    propagation = Some(required()?.parse::<MountBindOptionsPropagationEnum>()
+                        .map_err(|e| anyhow::anyhow!("无效的传播模式: {}", e))?);
+                }
+                "volume-driver" => driver = Some(required()?),
+                "volume-opt" => {
+                    let opt = required()?;
+                    let (k, v) = opt.split_once('=')
+                        .ok_or_else(|| anyhow::anyhow!("volume-opt 格式应为 key=value: {}", opt))?;
+                    driver_opts.insert(k.to_string(), v.to_string());
+                }
+                "volume-nocopy" => no_copy = flag()?,
+                "tmpfs-size" => size = Some(parse_size(&required()?)?),
+                "tmpfs-mode" => {
+                    let value = required()?;
+                    mode = Some(i64::from_str_radix(&value, 8)
+                        .map_err(|_| anyhow::anyhow!("无效的 tmpfs-mode: {}", value))?);
+                }
+                other => return Err(anyhow::anyhow!("未知的挂载选项: {}", other)),
+            }
+        }
+
+        let target = target.ok_or_else(|| anyhow::anyhow!("挂载点缺少 target: {}", arg))?;
+        let volume_only = driver.is_some() || !driver_opts.is_empty() || no_copy;
+        let tmpfs_only = size.is_some() || mode.is_some();
+
+        match typ.as_str() {
+            "bind" => {
+                if volume_only || tmpfs_only {
+                    return Err(anyhow::anyhow!("绑定挂载不支持 volume/tmpfs 选项: {}", arg));
+                }
+                Ok(MountSpec::Bind {
+                    source: source.ok_or_else(|| anyhow::anyhow!("绑定挂载缺少 source: {}", arg))?,
+                    target,
+                    read_only,
+                    propagation,
+                })
+            }
+            "volume" => {
+                if propagation.is_some() || tmpfs_only {
+                    return Err(anyhow::anyhow!("卷挂载不支持 bind/tmpfs 选项: {}", arg));
+                }
+                Ok(MountSpec::Volume { source, target, read_only, driver, driver_opts, no_copy })
+            }
+            "tmpfs" => {
+                if source.is_some() || propagation.is_some() || volume_only || read_only {
+                    return Err(anyhow::anyhow!("tmpfs 挂载只支持 target、tmpfs-size 和 tmpfs-mode: {}", arg));
+                }
+                Ok(MountSpec::Tmpfs { target, size, mode })
+            }
+            other => Err(anyhow::anyhow!("不支持的挂载类型: {}", other)),
+        }
+    }
+}
+
+impl FromStr for MountSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.contains('=') {
+            Self::parse_mount_arg(s)
+        } else {
+            Self::parse_volume_arg(s)
+        }
+    }
+}
+
+/// 解析 `64m`、`1g`、`512k` 这类大小写法，返回字节数
+pub fn parse_size(value: &str) -> anyhow::Result<i64> {
+    let lower = value.trim().to_ascii_lowercase();
+    let digits = lower.trim_end_matches('b');
+    let (number, unit) = match digits.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((idx, _)) => digits.split_at(idx),
+        None => (digits, ""),
+    };
+    let multiplier = match unit {
+        "" => 1,
+        "k" => 1024,
+        "m" => 1024 * 1024,
+        "g" => 1024 * 1024 * 1024,
+        _ => return Err(anyhow::anyhow!("无效的大小: {}", value)),
+    };
+    number.parse::<i64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| anyhow::anyhow!("无效的大小: {}", value))
+}
@@ -1,29 +1,107 @@
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::{SystemTime, Duration};
 use bollard::Docker;
-use bollard::container::{ListContainersOptions, RemoveContainerOptions};
-use bollard::models::{HostConfig, EventMessageTypeEnum};
+use bollard::container::{Config, CreateContainerOptions, ListContainersOptions, RemoveContainerOptions, RenameContainerOptions, RestartContainerOptions};
+use bollard::image::CommitContainerOptions;
+use bollard::network::ConnectNetworkOptions;
+use chrono::Local;
+use bollard::models::{EventMessage, EventMessageTypeEnum, HealthStatusEnum};
 use bollard::system::EventsOptions;
 use futures::StreamExt;
-use futures::TryStreamExt;
-use crate::models::ContainerConfig;
-use crate::restart::ContainerRestarter;
+use crate::models::{ContainerConfig, ContainerNetwork, MountSpec};
 use crate::docker::DockerClient;
+use crate::images::{ImageManager, ImageReferences, ImageInfo, PullPolicy, PullProgress, PruneReport, RemoveOutcome, normalize_image_name};
+use crate::registry::RegistryStore;
+use crate::networks::{NetworkAttachment, NetworkManager};
+use crate::backups::{BackupInfo, BackupManager, BackupSettings, RestoreReport, LABEL_HELPER};
+use crate::compose::{self, ComposeProject, ComposeService, DependencyCondition};
+use crate::export::{self, ExportFormat, ExportedContainer};
+use crate::health::{ContainerHealth, HealthSettings, HealthSupervisor, UnhealthyAction};
+use crate::probes::{ProbeInfo, ProbeManager, ProbeSpec};
+use crate::oom::{OomAction, OomRecord, OomSettings, OomTracker};
+use crate::groups::{GroupInfo, GroupManager, GroupMember};
+use crate::stacks::{StackDefinition, StackInfo, StackManager, StackSource};
+use crate::volumes::{VolumeInfo, VolumeManager, VolumeReferences, VolumeRemoveOutcome, VolumeSpec};
+use crate::updater::{ImageUpdater, UpdateRecord, UpdateStatus, is_enabled_for, is_monitor_only};
+use crate::audit::{self, AuditEntry, AuditLog};
+use crate::exec::ExecManager;
+use crate::files::FileManager;
+use crate::snapshots::{self, CommitRequest, Snapshot, SnapshotStore};
+use crate::maintenance::{MaintenanceManager, MaintenanceRequest, MaintenanceWindow};
+use crate::status::{self, BuildInfo, DockerStatus, ManagerStatus, StatusTracker, StreamState};
+use crate::scheduler::{self, RunStatus, RunTrigger, Schedule, ScheduleAction, ScheduleInfo, ScheduleRun, Scheduler, TargetResult};
+use crate::events::{EventPage, EventQuery, EventRecord, EventSettings, EventStore};
+use crate::notify::{Channel, EventKind, Notification, NotificationCenter, NotificationRecord, NotifySettings};
+use crate::error::ApiError;
+use tokio::sync::mpsc::UnboundedSender;
+
+// 没有事件时检查 Docker 连接的间隔，同时作为事件循环的心跳
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+// 容器配置与 Docker 全量对账的间隔
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(300);
 
 pub struct ContainerMonitor {
     docker: Docker,
-    restarter: Arc<ContainerRestarter>,
+    images: ImageManager,
+    registry: Arc<RegistryStore>,
+    networks: NetworkManager,
+    volumes: VolumeManager,
+    backups: BackupManager,
+    stacks: StackManager,
+    groups: GroupManager,
+    health: HealthSupervisor,
+    probes: ProbeManager,
+    oom: OomTracker,
+    updater: ImageUpdater,
+    notifier: NotificationCenter,
+    audit: AuditLog,
+    events: EventStore,
+    exec: ExecManager,
+    files: FileManager,
+    snapshots: SnapshotStore,
+    scheduler: Scheduler,
+    maintenance: MaintenanceManager,
+    status: StatusTracker,
+    // 由管理器主动停止的容器，收到停止事件时不再自动重启
+    expected_stops: Mutex<HashSet<String>>,
+    // 正在等待恢复、准备级联重启依赖者的容器
+    cascades: Mutex<HashSet<String>>,
+    // 自动重启的次数统计，崩溃和健康检查失败共用
+    restart_records: Mutex<HashMap<String, RestartRecord>>,
     pub container_configs: Mutex<HashMap<String, ContainerConfig>>,
 }
 
 impl ContainerMonitor {
     pub async fn new() -> anyhow::Result<Self> {
         let docker = DockerClient::get().await?;
-        let restarter = Arc::new(ContainerRestarter::new(docker.clone()).await?);
+        let registry = Arc::new(RegistryStore::load()?);
         let monitor = Self { 
             docker: docker.clone(), 
-            restarter,
+            images: ImageManager::new(docker.clone(), registry.clone()),
+            registry,
+            networks: NetworkManager::new(docker.clone()),
+            volumes: VolumeManager::new(docker.clone()),
+            backups: BackupManager::load(docker.clone())?,
+            stacks: StackManager::load(docker.clone())?,
+            groups: GroupManager::new(docker.clone()),
+            health: HealthSupervisor::load()?,
+            probes: ProbeManager::load(docker.clone())?,
+            oom: OomTracker::load()?,
+            updater: ImageUpdater::load()?,
+            notifier: NotificationCenter::load()?,
+            audit: AuditLog::new(),
+            events: EventStore::load()?,
+            exec: ExecManager::load(docker.clone())?,
+            files: FileManager::load(docker.clone())?,
+            snapshots: SnapshotStore::load()?,
+            scheduler: Scheduler::load()?,
+            maintenance: MaintenanceManager::load()?,
+            status: StatusTracker::new(),
+            expected_stops: Mutex::new(HashSet::new()),
+            cascades: Mutex::new(HashSet::new()),
+            restart_records: Mutex::new(HashMap::new()),
             container_configs: Mutex::new(HashMap::new()),
         };
         
@@ -50,6 +128,8 @@ impl ContainerMonitor {
                 println!("正在加载容器配置: {}", id);
                 if let Ok(inspect) = self.docker.inspect_container(id, None).await {
 
+                    let mounts = MountSpec::from_inspect(&inspect);
+                    let networks = ContainerNetwork::from_inspect(&inspect);
                     let image = container.image.clone().unwrap_or_default();
                     let name = inspect.name.unwrap_or_default().trim_start_matches('/').to_string();
                     println!("容器名称: {}, 镜像: {}", name, image);
@@ -81,7 +161,8 @@ impl ContainerMonitor {
                             labels: c.labels.clone(),
                             ..Default::default()
                         }),
-                        ip_address: self.get_container_ip(id).await,
+                        networks,
+                        mounts,
                     };
 
                     let mut configs = self.container_configs.lock().unwrap();
@@ -113,40 +194,213 @@ impl ContainerMonitor {
         Ok(())
     }
 
-    pub async fn start_monitoring(&self) -> anyhow::Result<()> {
-        let mut events = self.docker.events(None::<EventsOptions<String>>);
-        let mut restart_records: HashMap<String, RestartRecord> = HashMap::new();
-
+    pub async fn start_monitoring(self: Arc<Self>) -> anyhow::Result<()> {
         println!("开始监控容器状态...");
+        // 从这里开始接收事件，检查已有容器期间发生的事件不会遗漏；
+        // 断线重连时从上一个事件之后继续
+        let mut since = event_timestamp(chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default());
 
         // 先检查现有的已停止容器
         self.check_stopped_containers().await?;
+        self.check_unhealthy_containers().await?;
+
+        loop {
+            let mut events = self.docker.events(Some(EventsOptions::<String> {
+                since: Some(since.clone()),
+                ..Default::default()
+            }));
+            self.status.connected();
+            let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
 
-        while let Some(event) = events.next().await {
-            match event {
-                Ok(event) => {
-                    if let Some(EventMessageTypeEnum::CONTAINER) = event.typ {
-                        if let Some(status) = event.action {
-                            if let Some(id) = event.actor.and_then(|a| a.id) {
-                                match status.as_str() {
-                                    "die" | "stop" | "kill" => {
-                                        println!("检测到容器停止: {}", id);
-                                        if let Err(e) = self.handle_container_stop(&id, &mut restart_records).await {
-                                            eprintln!("处理容器停止事件失败: {}", e);
-                                        }
-                                    }
-                                    _ => {}
-                                }
+            let error = loop {
+                tokio::select! {
+                    event = events.next() => match event {
+                        Some(Ok(event)) => {
+                            self.status.event_received();
+                            if let Some(nanos) = event.time_nano {
+                                since = event_timestamp(nanos + 1);
                             }
+                            self.handle_event(event).await;
                         }
+                        Some(Err(e)) => break e.to_string(),
+                        None => break "事件流已结束".to_string(),
+                    },
+                    // 没有事件时也定时确认 Docker 仍然可用，避免事件流静默断开
+                    _ = heartbeat.tick() => match tokio::time::timeout(HEARTBEAT_INTERVAL, self.ensure_docker_connection()).await {
+                        Ok(Ok(())) => self.status.heartbeat(),
+                        Ok(Err(e)) => break e.to_string(),
+                        Err(_) => break "检查 Docker 连接超时".to_string(),
+                    },
+                }
+            };
+
+            eprintln!("监控事件错误: {}，{} 秒后重新连接", error, RECONNECT_DELAY.as_secs());
+            self.status.disconnected(error);
+            loop {
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                self.status.heartbeat();
+                match tokio::time::timeout(HEARTBEAT_INTERVAL, self.ensure_docker_connection()).await {
+                    Ok(Ok(())) => break,
+                    Ok(Err(e)) => eprintln!("重新连接 Docker 失败: {}", e),
+                    Err(_) => eprintln!("重新连接 Docker 超时"),
+                }
+            }
+            println!("已重新连接 Docker 事件流");
+            if let Err(e) = self.reconcile_containers().await {
+                eprintln!("同步容器配置失败: {}", e);
+            }
+        }
+    }
+
+    async fn handle_event(self: &Arc<Self>, event: EventMessage) {
+        if let Some(record) = EventRecord::from_docker(&event) {
+            self.events.record(record);
+        }
+        let Some(EventMessageTypeEnum::CONTAINER) = event.typ else {
+            return;
+        };
+        let Some(status) = event.action else {
+            return;
+        };
+        let actor = event.actor.unwrap_or_default();
+        // 备份恢复用的辅助容器退出是预期行为
+        let is_helper = actor.attributes.as_ref()
+            .is_some_and(|a| a.contains_key(LABEL_HELPER));
+        let Some(id) = actor.id else {
+            return;
+        };
+        match status.as_str() {
+            "die" | "stop" | "kill" if !is_helper => {
+                println!("检测到容器停止: {}", id);
+                match self.handle_container_stop(&id).await {
+                    // 等待依赖恢复可能很久，不能阻塞事件循环
+                    Ok(Some(restarted)) => {
+                        tokio::spawn(self.clone().cascade_restart(restarted));
                     }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("处理容器停止事件失败: {}", e),
                 }
-                Err(e) => {
-                    eprintln!("监控事件错误: {}", e);
+            }
+            // 容器内任意进程被 OOM killer 终止都会产生 oom 事件，容器不一定退出；
+            // 是否因 OOM 退出以 die 之后 inspect 中的 OOMKilled 为准
+            "oom" if !is_helper => {
+                println!("检测到容器内存不足: {}", id);
+            }
+            // Docker 不会重启健康检查失败的容器，由管理器处理
+            "health_status: unhealthy" if !is_helper => {
+                println!("检测到容器不健康: {}", id);
+                tokio::spawn(self.clone().supervise_unhealthy(id));
+            }
+            // 管理器之外创建、改名和修改的容器也要同步到保存的配置
+            "create" | "rename" | "update" if !is_helper => {
+                println!("检测到容器{}: {}", match status.as_str() {
+                    "create" => "创建",
+                    "rename" => "改名",
+                    _ => "配置修改",
+                }, id);
+                if let Err(e) = self.sync_container(&id).await {
+                    eprintln!("同步容器 {} 的配置失败: {}", id, e);
+                }
+            }
+            // 容器重新运行后，之前的预期停止和计划停止都不再适用
+            "start" if !is_helper => {
+                self.expected_stops.lock().unwrap().remove(&id);
+                self.scheduler.mark_stopped(&id, false);
+            }
+            "destroy" => self.forget_container(&id),
+            _ => {}
+        }
+    }
+
+    // 按 inspect 结果更新保存的配置，容器已不存在时删除配置
+    async fn sync_container(&self, id: &str) -> anyhow::Result<()> {
+        let inspect = match self.docker.inspect_container(id, None).await {
+            Ok(inspect) => inspect,
+            Err(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. }) => {
+                self.forget_container(id);
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let image = inspect.config.as_ref()
+            .and_then(|c| c.image.clone())
+            .unwrap_or_default();
+        let config = Self::config_from_inspect(id, &inspect, image);
+        let previous = self.container_configs.lock().unwrap().insert(id.to_string(), config.clone());
+        match previous {
+            None => println!("发现新容器 {} ({})", config.name, id),
+            Some(previous) if previous.name != config.name => {
+                println!("容器 {} 已改名为 {}", previous.name, config.name);
+                // 重建时旧容器临时改名，探测和维护模式仍属于原名称
+                if !is_backup_name(&config.name, &previous.name) {
+                    if let Err(e) = self.probes.rename(&previous.name, &config.name) {
+                        eprintln!("更新容器 {} 的存活探测失败: {}", config.name, e);
+                    }
+                    self.maintenance.rename(&previous.name, &config.name);
                 }
             }
+            Some(_) => {}
+        }
+        Ok(())
+    }
+
+    // 容器已被删除，清理保存的配置和状态
+    fn forget_container(&self, id: &str) {
+        if let Some(config) = self.container_configs.lock().unwrap().remove(id) {
+            println!("容器 {} ({}) 已被删除", config.name, id);
+            self.probes.reset(&config.name);
+        }
+        self.expected_stops.lock().unwrap().remove(id);
+        self.restart_records.lock().unwrap().remove(id);
+        self.scheduler.mark_stopped(id, false);
+    }
+
+    /// 定时与 Docker 的容器列表全量对账，补上遗漏的事件
+    pub async fn start_reconciliation(self: Arc<Self>) {
+        let mut ticker = tokio::time::interval(RECONCILE_INTERVAL);
+        // 启动时刚加载过配置，跳过第一次
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.reconcile_containers().await {
+                eprintln!("同步容器配置失败: {}", e);
+            }
+        }
+    }
+
+    async fn reconcile_containers(&self) -> anyhow::Result<()> {
+        // 先记下已有的配置，对账期间新建的容器不会被误删
+        let known = self.managed_container_ids();
+        let containers = self.docker.list_containers(Some(ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        })).await?;
+
+        let mut present = HashSet::new();
+        let mut added = 0;
+        for container in containers {
+            if container.labels.as_ref().is_some_and(|l| l.contains_key(LABEL_HELPER)) {
+                continue;
+            }
+            let Some(id) = container.id else {
+                continue;
+            };
+            if !known.contains(&id) {
+                added += 1;
+            }
+            if let Err(e) = self.sync_container(&id).await {
+                eprintln!("同步容器 {} 的配置失败: {}", id, e);
+            }
+            present.insert(id);
         }
 
+        let removed: Vec<&String> = known.iter().filter(|id| !present.contains(*id)).collect();
+        for id in &removed {
+            self.forget_container(id);
+        }
+        if added > 0 || !removed.is_empty() {
+            println!("容器配置对账完成：新增 {} 个，移除 {} 个", added, removed.len());
+        }
         Ok(())
     }
 
@@ -165,19 +419,37 @@ impl ContainerMonitor {
         println!("发现 {} 个已停止的容器", containers.len());
         
         for container in containers {
+            if container.labels.as_ref().is_some_and(|l| l.contains_key(LABEL_HELPER)) {
+                continue;
+            }
             if let Some(id) = container.id {
+                if self.scheduler.is_stopped(&id) {
+                    println!("容器 {} 由计划任务停止，跳过重启", id);
+                    continue;
+                }
+                let name = container.names.as_ref()
+                    .and_then(|n| n.first())
+                    .map(|n| n.trim_start_matches('/').to_string())
+                    .unwrap_or_default();
+                if self.skip_for_maintenance(&id, &name, container.labels.as_ref(), "restart_stopped") {
+                    continue;
+                }
                 println!("准备重启容器: {}", id);
                 
                 // 保存镜像名称
                 let image = container.image.clone().unwrap_or_default();
                 
                 // 尝试重启容器
-                match self.docker.start_container::<String>(&id, None).await {
+                let result = self.docker.start_container::<String>(&id, None).await;
+                self.audit_action("restart_stopped", &id, serde_json::json!({ "image": image }), &result);
+                match result {
                     Ok(_) => {
                         println!("✅ 容器 {} 重启成功", id);
                         
                         // 更新配置
                         if let Ok(inspect) = self.docker.inspect_container(&id, None).await {
+                            let mounts = MountSpec::from_inspect(&inspect);
+                            let networks = ContainerNetwork::from_inspect(&inspect);
                             let config = ContainerConfig {
                                 container_id: id.clone(),
                                 name: inspect.name.unwrap_or_default().trim_start_matches('/').to_string(),
@@ -205,7 +477,8 @@ impl ContainerMonitor {
                                     labels: c.labels.clone(),
                                     ..Default::default()
                                 }),
-                                ip_address: self.get_container_ip(&id).await,
+                                networks,
+                                mounts,
                             };
 
                             let mut configs = self.container_configs.lock().unwrap();
@@ -223,15 +496,285 @@ impl ContainerMonitor {
         Ok(())
     }
 
-    async fn handle_container_stop(
-        &self,
-        container_id: &str,
-        restart_records: &mut HashMap<String, RestartRecord>,
-    ) -> anyhow::Result<()> {
+    /// 重启意外停止的容器，返回重启后的容器 ID（OOM 后提高内存限制时会重建容器）
+    async fn handle_container_stop(self: &Arc<Self>, container_id: &str) -> anyhow::Result<Option<String>> {
         println!("处理容器停止事件: {}", container_id);
 
+        if self.expected_stops.lock().unwrap().contains(container_id) {
+            println!("容器 {} 由管理器主动停止，跳过重启", container_id);
+            return Ok(None);
+        }
+
+        // 事件可能在容器已经被重新启动之后才处理到
+        let inspect = self.docker.inspect_container(container_id, None).await?;
+        let state = inspect.state.clone().unwrap_or_default();
+        if state.running.unwrap_or(false) {
+            println!("容器 {} 已经在运行，跳过重启", container_id);
+            return Ok(None);
+        }
+        let name = inspect.name.clone().unwrap_or_default().trim_start_matches('/').to_string();
+        let labels = inspect.config.as_ref().and_then(|c| c.labels.as_ref());
+        if self.skip_for_maintenance(container_id, &name, labels, "restart_crashed") {
+            return Ok(None);
+        }
+
+        let count = self.record_restart(container_id);
+        if state.oom_killed.unwrap_or(false) {
+            // 提高内存限制需要重建容器，不能阻塞事件循环
+            let monitor = self.clone();
+            tokio::spawn(async move {
+                match monitor.handle_oom(&inspect, count).await {
+                    Ok(new_id) => monitor.clone().cascade_restart(new_id).await,
+                    Err(e) => eprintln!("处理容器停止事件失败: {}", e),
+                }
+            });
+            return Ok(None);
+        }
+        println!("尝试重启容器 {} (第 {} 次尝试)", container_id, count);
+
+        let exit = match state.error.as_deref().filter(|e| !e.is_empty()) {
+            Some(error) => format!("容器意外退出，退出码 {}: {}", state.exit_code.unwrap_or_default(), error),
+            None => format!("容器意外退出，退出码 {}", state.exit_code.unwrap_or_default()),
+        };
+        let result = self.docker.start_container::<String>(container_id, None).await;
+        self.audit_action(
+            "restart_crashed", &name,
+            serde_json::json!({ "container_id": container_id, "exit_code": state.exit_code, "attempt": count }),
+            &result,
+        );
+        match result {
+            Ok(_) => {
+                println!("✅ 容器 {} 重启成功", container_id);
+                self.notifier.notify(Notification::new(
+                    EventKind::Crash, container_id, &name,
+                    format!("{}，已自动重启 (10 分钟内第 {} 次)", exit, count),
+                ));
+                if let Ok(inspect) = self.docker.inspect_container(container_id, None).await {
+                    let image = inspect.config.as_ref()
+                        .and_then(|c| c.image.clone())
+                        .unwrap_or_default();
+                    
+                    self.update_container_config(container_id, &inspect, image).await?;
+                }
+            }
+            Err(e) => {
+                eprintln!("❌ 容器 {} 重启失败: {}", container_id, e);
+                self.notifier.notify(Notification::new(
+                    EventKind::RestartFailed, container_id, &name,
+                    format!("{}，自动重启失败: {}", exit, e),
+                ));
+                return Err(anyhow::anyhow!("重启容器失败: {}", e));
+            }
+        }
+        
+        Ok(Some(container_id.to_string()))
+    }
+
+    /// 容器因内存不足退出：按设置提高内存限制并重建，否则按原来的限制重启
+    async fn handle_oom(&self, inspect: &bollard::models::ContainerInspectResponse, count: u32) -> anyhow::Result<String> {
+        let id = inspect.id.clone().unwrap_or_default();
+        let name = inspect.name.clone().unwrap_or_default().trim_start_matches('/').to_string();
+        let memory = inspect.host_config.as_ref().and_then(|h| h.memory).unwrap_or(0);
+        let labels = inspect.config.as_ref().and_then(|c| c.labels.as_ref());
+        println!("⚠️ 容器 {} 因内存不足退出 (第 {} 次重启)，内存限制 {}", name, count, memory);
+
+        let mut record = OomRecord {
+            container_id: id.clone(),
+            name: name.clone(),
+            memory,
+            new_memory: None,
+            new_container_id: None,
+            action: OomAction::Restarted,
+            message: None,
+            timestamp: Local::now(),
+        };
+        let bump = if self.oom.auto_bump_for(labels) {
+            let next = self.oom.next_limit(memory);
+            if next.is_none() {
+                record.action = OomAction::CeilingReached;
+                record.message = Some(if memory > 0 {
+                    "内存限制已达到上限".to_string()
+                } else {
+                    "容器没有内存限制".to_string()
+                });
+            }
+            next
+        } else {
+            None
+        };
+
+        let result = match bump {
+            Some(new_memory) => self.bump_memory(&id, memory, new_memory).await
+                .inspect(|new_id| {
+                    record.action = OomAction::Bumped;
+                    record.new_memory = Some(new_memory);
+                    record.new_container_id = Some(new_id.clone());
+                }),
+            None => self.docker.start_container::<String>(&id, None).await
+                .map(|_| id.clone())
+                .map_err(Into::into),
+        };
+        if let Err(e) = &result {
+            record.action = OomAction::Failed;
+            record.message = Some(e.to_string());
+        }
+
+        match &result {
+            Ok(new_id) => {
+                println!("✅ 容器 {} 已重启: {:?}", name, record.action);
+                if let Ok(inspect) = self.docker.inspect_container(new_id, None).await {
+                    let image = inspect.config.as_ref()
+                        .and_then(|c| c.image.clone())
+                        .unwrap_or_default();
+                    self.update_container_config(new_id, &inspect, image).await?;
+                }
+            }
+            Err(e) => eprintln!("❌ 处理容器 {} 的 OOM 失败: {}", name, e),
+        }
+
+        let message = match record.action {
+            OomAction::Restarted => "因内存不足被终止，已按原来的限制重启".to_string(),
+            OomAction::Bumped => format!(
+                "因内存不足被终止，内存限制已从 {} MiB 提高到 {} MiB",
+                memory / 1024 / 1024, record.new_memory.unwrap_or_default() / 1024 / 1024,
+            ),
+            OomAction::CeilingReached | OomAction::Failed => format!(
+                "因内存不足被终止，{}",
+                record.message.as_deref().unwrap_or_default(),
+            ),
+        };
+        let event = if record.action == OomAction::Failed { EventKind::RestartFailed } else { EventKind::Oom };
+        let notified_id = record.new_container_id.clone().unwrap_or_else(|| id.clone());
+        self.notifier.notify(Notification::new(event, notified_id, &name, message).with_details(&record));
+        self.audit_action("oom_recover", &name, serde_json::to_value(&record).unwrap_or_default(), &result);
+        self.oom.record(record);
+        result
+    }
+
+    // 用新的内存限制重建并启动容器
+    async fn bump_memory(&self, id: &str, memory: i64, new_memory: i64) -> anyhow::Result<String> {
+        let (image, original) = {
+            let mut configs = self.container_configs.lock().unwrap();
+            let stored = configs.get_mut(id)
+                .ok_or_else(|| anyhow::anyhow!("未找到容器 {} 的配置", id))?;
+            let original = stored.host_config.clone();
+            let host = stored.host_config.get_or_insert_with(Default::default);
+            host.memory = Some(new_memory);
+            // 保持可用的交换空间大小不变，-1 表示不限制
+            if let Some(swap) = host.memory_swap.filter(|s| *s > 0) {
+                host.memory_swap = Some(swap + new_memory - memory);
+            }
+            (stored.image.clone(), original)
+        };
+        println!("提高容器 {} 的内存限制: {} -> {}", id, memory, new_memory);
+        match self.recreate_container(id, &image).await {
+            Ok(new_id) => Ok(new_id),
+            Err(e) => {
+                // 重建失败时旧容器已恢复，按原来的限制启动它
+                if let Some(stored) = self.container_configs.lock().unwrap().get_mut(id) {
+                    stored.host_config = original;
+                }
+                if let Err(start_error) = self.docker.start_container::<String>(id, None).await {
+                    eprintln!("❌ 启动容器 {} 失败: {}", id, start_error);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    pub fn oom_settings(&self) -> OomSettings {
+        self.oom.settings()
+    }
+
+    pub fn update_oom_settings(&self, settings: OomSettings) -> anyhow::Result<()> {
+        self.oom.update_settings(settings)
+    }
+
+    pub fn oom_history(&self) -> Vec<OomRecord> {
+        self.oom.history()
+    }
+
+    /// 定时检查反复崩溃或重启失败的容器是否已经恢复
+    pub async fn start_notifications(self: Arc<Self>) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(30));
+        loop {
+            ticker.tick().await;
+            for container in self.notifier.quiet_incidents() {
+                match self.docker.inspect_container(&container, None).await {
+                    Ok(inspect) if inspect.state.as_ref().and_then(|s| s.running).unwrap_or(false) => {
+                        println!("容器 {} 已恢复运行", container);
+                        self.notifier.notify(Notification::new(
+                            EventKind::Recovery, inspect.id.unwrap_or_default(), &container, "容器已恢复并稳定运行",
+                        ));
+                    }
+                    Ok(_) => {}
+                    // 容器已被删除
+                    Err(_) => self.notifier.resolve(&container),
+                }
+            }
+        }
+    }
+
+    pub fn exec(&self) -> &ExecManager {
+        &self.exec
+    }
+
+    pub fn files(&self) -> &FileManager {
+        &self.files
+    }
+
+    pub fn snapshots(&self) -> &SnapshotStore {
+        &self.snapshots
+    }
+
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    // 记录管理器自动执行的操作，同时写入审计日志和事件历史
+    fn audit_action<T, E: std::fmt::Display>(&self, action: &str, target: &str, mut params: serde_json::Value, result: &Result<T, E>) {
+        audit::redact(&mut params);
+        self.events.record(EventRecord::decision(action, target, &params, result));
+        self.audit.record(AuditEntry::monitor(action, target, params, result));
+    }
+
+    pub fn event_settings(&self) -> EventSettings {
+        self.events.settings()
+    }
+
+    pub fn update_event_settings(&self, settings: EventSettings) -> anyhow::Result<()> {
+        self.events.update_settings(settings)
+    }
+
+    pub async fn event_history(&self, query: &EventQuery) -> anyhow::Result<EventPage> {
+        self.events.query(query).await
+    }
+
+    pub fn notification_settings(&self) -> NotifySettings {
+        self.notifier.masked_settings()
+    }
+
+    pub fn update_notification_settings(&self, settings: NotifySettings) -> anyhow::Result<()> {
+        self.notifier.update_settings(settings)
+    }
+
+    pub fn notification_history(&self) -> Vec<NotificationRecord> {
+        self.notifier.history()
+    }
+
+    pub fn notification_channel(&self, name: &str) -> Option<Channel> {
+        self.notifier.channel(name)
+    }
+
+    pub async fn send_test_notification(&self, channel: &Channel) -> anyhow::Result<()> {
+        self.notifier.send_test(channel).await
+    }
+
+    // 记录一次自动重启，返回最近 10 分钟内的重启次数
+    fn record_restart(&self, container_id: &str) -> u32 {
         let now = SystemTime::now();
-        let record = restart_records
+        let mut records = self.restart_records.lock().unwrap();
+        let record = records
             .entry(container_id.to_string())
             .or_insert(RestartRecord {
                 last_restart: now,
@@ -246,50 +789,282 @@ impl ContainerMonitor {
 
         record.restart_count += 1;
         record.last_restart = now;
+        record.restart_count
+    }
 
-        println!(
-            "尝试重启容器 {} (第 {} 次尝试)",
-            container_id,
-            record.restart_count
-        );
+    // 启动时已经处于不健康状态的容器不会再产生事件，需要主动检查
+    async fn check_unhealthy_containers(self: &Arc<Self>) -> anyhow::Result<()> {
+        let mut filters = HashMap::new();
+        filters.insert("health", vec!["unhealthy"]);
+        let containers = self.docker.list_containers(Some(ListContainersOptions {
+            filters,
+            ..Default::default()
+        })).await?;
 
-        match self.docker.start_container::<String>(container_id, None).await {
-            Ok(_) => {
-                println!("✅ 容器 {} 重启成功", container_id);
-                if let Ok(inspect) = self.docker.inspect_container(container_id, None).await {
-                    let image = inspect.config.as_ref()
-                        .and_then(|c| c.image.clone())
-                        .unwrap_or_default();
-                    
-                    self.update_container_config(container_id, &inspect, image).await?;
+        for container in containers {
+            if container.labels.as_ref().is_some_and(|l| l.contains_key(LABEL_HELPER)) {
+                continue;
+            }
+            if let Some(id) = container.id {
+                println!("发现不健康的容器: {}", id);
+                tokio::spawn(self.clone().supervise_unhealthy(id));
+            }
+        }
+        Ok(())
+    }
+
+    /// 跟踪不健康的容器，宽限期过后且连续失败次数达到阈值时重启
+    async fn supervise_unhealthy(self: Arc<Self>, id: String) {
+        if !self.health.watch(&id) {
+            return;
+        }
+        let mut restarted = false;
+        // 同一个维护窗口只记录一次跳过
+        let mut skipped_window = None;
+        loop {
+            if self.expected_stops.lock().unwrap().contains(&id) {
+                break;
+            }
+            let inspect = match self.docker.inspect_container(&id, None).await {
+                Ok(inspect) => inspect,
+                Err(e) => {
+                    eprintln!("检查容器 {} 的健康状态失败: {}", id, e);
+                    break;
+                }
+            };
+            match self.health.evaluate(&inspect) {
+                UnhealthyAction::Recovered => {
+                    println!("容器 {} 已恢复健康", id);
+                    break;
+                }
+                UnhealthyAction::Skip => break,
+                UnhealthyAction::Wait(delay) => tokio::time::sleep(delay).await,
+                UnhealthyAction::Restart => {
+                    // 维护期间继续跟踪，结束后仍不健康再重启
+                    let name = inspect.name.as_deref().unwrap_or_default().trim_start_matches('/');
+                    let labels = inspect.config.as_ref().and_then(|c| c.labels.as_ref());
+                    if let Some(window) = self.active_maintenance(&id, name, labels) {
+                        if skipped_window != Some(window.id) {
+                            self.record_maintenance_skip(&id, name, &window, "restart_unhealthy");
+                            skipped_window = Some(window.id);
+                        }
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                        continue;
+                    }
+                    match self.restart_failing(&id, EventKind::Unhealthy, "健康检查持续失败").await {
+                        Ok(()) => restarted = true,
+                        Err(e) => eprintln!("❌ 重启不健康的容器 {} 失败: {}", id, e),
+                    }
+                    break;
                 }
             }
+        }
+        self.health.unwatch(&id);
+        if restarted {
+            self.cascade_restart(id).await;
+        }
+    }
+
+    /// 重启仍在运行但已经失效的容器，与崩溃重启共用计数
+    async fn restart_failing(&self, id: &str, event: EventKind, reason: &str) -> anyhow::Result<()> {
+        let count = self.record_restart(id);
+        println!("容器 {} {}，尝试重启 (第 {} 次尝试)", id, reason, count);
+        let name = self.container_name(id).await.unwrap_or_else(|_| id.to_string());
+        // 重启过程中的停止事件不应再触发一次重启
+        let expected = ExpectedStop::new(&self.expected_stops, id);
+        let result = self.docker.restart_container(id, Some(RestartContainerOptions { t: 10 })).await;
+        drop(expected);
+        self.audit_action(
+            &format!("restart_{}", event.as_str()), &name,
+            serde_json::json!({ "container_id": id, "reason": reason, "attempt": count }),
+            &result,
+        );
+        if let Err(e) = &result {
+            self.notifier.notify(Notification::new(
+                EventKind::RestartFailed, id, &name, format!("{}，重启失败: {}", reason, e),
+            ));
+        }
+        result?;
+        println!("✅ 容器 {} 已重启", id);
+        self.notifier.notify(Notification::new(
+            event, id, &name, format!("{}，已重启 (10 分钟内第 {} 次)", reason, count),
+        ));
+
+        if let Ok(inspect) = self.docker.inspect_container(id, None).await {
+            let image = inspect.config.as_ref()
+                .and_then(|c| c.image.clone())
+                .unwrap_or_default();
+            self.update_container_config(id, &inspect, image).await?;
+        }
+        Ok(())
+    }
+
+    /// 定时执行管理器定义的存活探测
+    pub async fn start_probes(self: Arc<Self>) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            ticker.tick().await;
+            for (container, spec) in self.probes.due() {
+                tokio::spawn(self.clone().run_probe(container, spec));
+            }
+        }
+    }
+
+    async fn run_probe(self: Arc<Self>, container: String, spec: ProbeSpec) {
+        let inspect = match self.docker.inspect_container(&container, None).await {
+            Ok(inspect) => inspect,
+            // 容器可能已被删除，等它以同样的名字重新创建
             Err(e) => {
-                eprintln!("❌ 容器 {} 重启失败: {}", container_id, e);
-                return Err(anyhow::anyhow!("重启容器失败: {}", e));
+                eprintln!("存活探测获取容器 {} 失败: {}", container, e);
+                return;
             }
+        };
+        let id = inspect.id.clone().unwrap_or_default();
+        let state = inspect.state.clone().unwrap_or_default();
+        // 停止的容器由停止事件处理，主动停止的容器不探测
+        if !state.running.unwrap_or(false) || state.paused.unwrap_or(false)
+            || self.expected_stops.lock().unwrap().contains(&id)
+        {
+            self.probes.clear_failures(&container);
+            return;
+        }
+        let uptime = state.started_at.as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .and_then(|t| (chrono::Utc::now() - t.with_timezone(&chrono::Utc)).to_std().ok())
+            .unwrap_or_default();
+        if uptime < Duration::from_secs(spec.initial_delay) {
+            return;
+        }
+
+        let host_network = inspect.host_config.as_ref()
+            .and_then(|h| h.network_mode.as_deref())
+            .is_some_and(|mode| mode == "host");
+        let ip = if host_network {
+            Some("127.0.0.1".to_string())
+        } else {
+            self.get_container_ip(&id).await
+        };
+        let result = self.probes.check(&id, ip.as_deref(), &spec).await;
+        if let Err(e) = &result {
+            println!("容器 {} 存活探测失败: {}", container, e);
+        }
+        if !self.probes.record(&container, &spec, &result) {
+            return;
+        }
+        let name = inspect.name.as_deref().unwrap_or_default().trim_start_matches('/');
+        let labels = inspect.config.as_ref().and_then(|c| c.labels.as_ref());
+        if self.skip_for_maintenance(&id, name, labels, "restart_probe_failed") {
+            return;
+        }
+
+        match self.restart_failing(&id, EventKind::ProbeFailed, "存活探测连续失败").await {
+            Ok(()) => {
+                self.probes.record_restart(&container);
+                self.cascade_restart(id).await;
+            }
+            Err(e) => eprintln!("❌ 重启容器 {} 失败: {}", container, e),
+        }
+    }
+
+    pub fn list_probes(&self) -> Vec<ProbeInfo> {
+        self.probes.list()
+    }
+
+    pub async fn get_probe(&self, id: &str) -> anyhow::Result<ProbeInfo> {
+        let name = self.container_name(id).await?;
+        self.probes.get(&name)
+            .ok_or_else(|| ApiError::not_found(format!("容器 {} 没有配置存活探测", name)).into())
+    }
+
+    pub async fn set_probe(&self, id: &str, spec: ProbeSpec) -> anyhow::Result<()> {
+        let name = self.container_name(id).await?;
+        self.probes.set(&name, spec)
+    }
+
+    pub async fn remove_probe(&self, id: &str) -> anyhow::Result<()> {
+        let name = self.container_name(id).await?;
+        self.probes.remove(&name)
+    }
+
+    // 探测按容器名保存，接口中可以传容器 ID 或名称；容器不存在时按名称处理，
+    // 以便查看和删除已删除容器留下的探测
+    async fn container_name(&self, id: &str) -> anyhow::Result<String> {
+        match self.docker.inspect_container(id, None).await {
+            Ok(inspect) => Ok(inspect.name.unwrap_or_default().trim_start_matches('/').to_string()),
+            Err(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. }) => Ok(id.to_string()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn health_settings(&self) -> HealthSettings {
+        self.health.settings()
+    }
+
+    pub fn update_health_settings(&self, settings: HealthSettings) -> anyhow::Result<()> {
+        self.health.update_settings(settings)
+    }
+
+    pub fn networks(&self) -> &NetworkManager {
+        &self.networks
+    }
+
+    pub fn managed_container_ids(&self) -> HashSet<String> {
+        self.container_configs.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub async fn connect_container_network(&self, id: &str, attachment: &NetworkAttachment) -> anyhow::Result<()> {
+        self.networks.connect(id, attachment).await?;
+        self.refresh_container_networks(id).await
+    }
+
+    pub async fn disconnect_container_network(&self, id: &str, network: &str, force: bool) -> anyhow::Result<()> {
+        self.networks.disconnect(id, network, force).await?;
+        self.refresh_container_networks(id).await
+    }
+
+    // 网络连接变化后更新保存的容器配置
+    async fn refresh_container_networks(&self, id: &str) -> anyhow::Result<()> {
+        let inspect = self.docker.inspect_container(id, None).await?;
+        let networks = ContainerNetwork::from_inspect(&inspect);
+        let host_config = inspect.host_config;
+        let mut configs = self.container_configs.lock().unwrap();
+        if let Some(config) = configs.get_mut(id) {
+            config.networks = networks;
+            config.host_config = host_config;
         }
-        
         Ok(())
     }
 
+    pub fn updater(&self) -> &ImageUpdater {
+        &self.updater
+    }
+
+    pub fn registry_store(&self) -> &RegistryStore {
+        &self.registry
+    }
+
     pub fn get_container_configs(&self) -> &Mutex<HashMap<String, ContainerConfig>> {
         &self.container_configs
     }
 
     pub async fn get_container_status(&self, container_id: &str) -> anyhow::Result<String> {
+        Ok(self.get_container_state(container_id).await?.0)
+    }
+
+    /// 容器状态和健康检查状态
+    pub async fn get_container_state(&self, container_id: &str) -> anyhow::Result<(String, Option<ContainerHealth>)> {
         let inspect = self.docker.inspect_container(container_id, None).await?;
-        if let Some(state) = inspect.state {
-            if let Some(status) = state.status {
-                return Ok(status.to_string());
-            }
-        }
-        Ok("unknown".to_string())
+        let Some(state) = inspect.state else {
+            return Ok(("unknown".to_string(), None));
+        };
+        let status = state.status.map(|s| s.to_string()).unwrap_or_else(|| "unknown".to_string());
+        Ok((status, ContainerHealth::from_state(&state)))
     }
 
     // 删除容器的方法
     pub async fn remove_container(&self, id: &str) -> anyhow::Result<()> {
         let docker = &self.docker;
+        let expected = ExpectedStop::new(&self.expected_stops, id);
         // 停止容器
         let _ = docker.stop_container(id, None).await;
         
@@ -301,6 +1076,7 @@ impl ContainerMonitor {
                 ..Default::default()
             }),
         ).await?;
+        expected.keep();
         
         // 从配置中移除容器
         let mut configs = self.get_container_configs().lock().unwrap();
@@ -315,62 +1091,18 @@ impl ContainerMonitor {
         name: &str,
         image: &str,
         ports: &[String],
-        mounts: &[String],
+        mounts: &[MountSpec],
         env: &[String],
+        pull_policy: PullPolicy,
     ) -> anyhow::Result<()> {
         let docker = &self.docker;
         
         // 确保镜像名称包含标签
-        let image = if !image.contains(":") {
-            format!("{}:latest", image)
-        } else {
-            image.to_string()
-        };
-        
-        println!("开始拉取镜像: {}", image);
-        let pull_opts = bollard::image::CreateImageOptions::<String> {
-            from_image: image.clone(),
-            ..Default::default()
-        };
+        let image = normalize_image_name(image);
         
-        println!("开始下载镜像层...");
-        let mut stream = docker.create_image(Some(pull_opts), None, None);
-        let mut last_progress = std::collections::HashMap::new();
-        
-        while let Some(result) = stream.next().await {
-            match result {
-                Ok(info) => {
-                    match (info.id, info.status) {
-                        (Some(id), Some(status)) => {
-                            let progress = info.progress.as_ref().map(|p| p.as_str()).unwrap_or_default();
-                            let current_progress = format!("{} - {}", status, progress);
-                            if last_progress.get(&id) != Some(&current_progress) {
-                                if status.contains("Pull complete") {
-                                    println!("✅ 层 [{}] 下载完成", id);
-                                } else if status.contains("Downloading") {
-                                    println!("⏳ 层 [{}] {}", id, progress);
-                                } else if status.contains("Extracting") {
-                                    println!("📦 层 [{}] 正在解压 {}", id, progress);
-                                } else {
-                                    println!("层 [{}] {}", id, status);
-                                }
-                                last_progress.insert(id, current_progress);
-                            }
-                        }
-                        (None, Some(status)) => {
-                            println!("状态更新: {}", status);
-                        }
-                        _ => {}
-                    }
-                }
-                Err(e) => {
-                    eprintln!("❌ 拉取镜像出错: {}", e);
-                    return Err(anyhow::anyhow!("拉取镜像失败: {}", e));
-                }
-            }
-        }
+        self.images.ensure_image(&image, pull_policy, None).await?;
 
-        println!("✨ 镜像拉取完成，开始创建容器");
+        println!("✨ 镜像已就绪，开始创建容器");
 
         // 创建端口绑定配置
         let mut port_bindings = HashMap::new();
@@ -391,8 +1123,8 @@ impl ContainerMonitor {
         println!("暴露端口: {:?}", exposed_ports);
 
         // 创建挂载点配置
-        let binds = Some(mounts.to_vec());
-        println!("挂载点: {:?}", binds);
+        let mounts: Vec<_> = mounts.iter().map(MountSpec::to_mount).collect();
+        println!("挂载点: {:?}", mounts);
 
         // 创建容器配置
         let config = bollard::container::Config {
@@ -401,7 +1133,7 @@ impl ContainerMonitor {
             exposed_ports: Some(exposed_ports),
             host_config: Some(bollard::models::HostConfig {
                 port_bindings: Some(port_bindings),
-                binds,
+                mounts: Some(mounts),
                 ..Default::default()
             }),
             ..Default::default()
@@ -436,9 +1168,10 @@ impl ContainerMonitor {
                 container_id: container.id.clone(),
                 name: name.to_string(),
                 image: image.to_string(),
+                mounts: MountSpec::from_inspect(&inspect),
+                networks: ContainerNetwork::from_inspect(&inspect),
                 host_config: inspect.host_config,
                 config: Some(config),
-                ip_address: self.get_container_ip(&container.id).await,
             };
 
             let mut configs = self.container_configs.lock().unwrap();
@@ -451,49 +1184,1289 @@ impl ContainerMonitor {
     }
 
     // 更新容器的方法
+    #[allow(clippy::too_many_arguments)]
     pub async fn update_container(
         &self,
         id: &str,
         name: &str,
         image: &str,
         ports: &[String],
-        mounts: &[String],
+        mounts: &[MountSpec],
         env: &[String],
+        pull_policy: PullPolicy,
     ) -> anyhow::Result<()> {
+        // 先准备好镜像，拉取失败时保留旧容器
+        let image = normalize_image_name(image);
+        self.images.ensure_image(&image, pull_policy, None).await?;
         // 先停止并删除旧容器
         self.remove_container(id).await?;
-        // 创建新容器
-        self.create_container(name, image, ports, mounts, env).await?;
+        // 创建新容器，镜像已经就绪，无需再次拉取
+        self.create_container(name, &image, ports, mounts, env, PullPolicy::Missing).await?;
         Ok(())
     }
 
-    async fn get_container_ip(&self, container_id: &str) -> Option<String> {
-        if let Ok(inspect) = self.docker.inspect_container(container_id, None).await {
-            if let Some(network_settings) = inspect.network_settings {
-                if let Some(networks) = network_settings.networks {
-                    // 优先获取 bridge 网络的 IP
-                    if let Some(bridge) = networks.get("bridge") {
-                        return bridge.ip_address.clone();
+    /// 使用保存的容器配置和新镜像重建容器，返回新容器的 ID。
+    /// 旧容器先改名保留，新容器启动失败时恢复旧容器。
+    pub async fn recreate_container(&self, id: &str, image: &str) -> anyhow::Result<String> {
+        let docker = &self.docker;
+        let stored = self.container_configs.lock().unwrap().get(id).cloned()
+            .ok_or_else(|| anyhow::anyhow!("未找到容器 {} 的配置", id))?;
+        let old = docker.inspect_container(id, None).await?;
+        let name = old.name.clone().unwrap_or_default().trim_start_matches('/').to_string();
+        let was_running = old.state.as_ref().and_then(|s| s.running).unwrap_or(false);
+
+        let mut config = stored.config.clone().unwrap_or_default();
+        // 去掉从旧镜像继承来的默认值，让新镜像的默认值生效
+        if let Some(old_image) = old.image.as_deref() {
+            self.strip_image_defaults(&mut config, old_image).await;
+        }
+        // 默认主机名就是容器 ID 的前缀，沿用会让新容器使用旧 ID 作为主机名
+        if config.hostname.as_deref().is_some_and(|h| id.starts_with(h)) {
+            config.hostname = None;
+        }
+        config.image = Some(image.to_string());
+        config.host_config = stored.host_config.clone();
+
+        println!("开始重建容器 {} ({})，使用镜像 {}", name, id, image);
+        let expected = ExpectedStop::new(&self.expected_stops, id);
+        if was_running {
+            docker.stop_container(id, None).await?;
+        }
+        let backup_name = backup_name(&name);
+        docker.rename_container(id, RenameContainerOptions { name: backup_name.as_str() }).await?;
+
+        let result = self.create_recreated(&name, config, &old).await;
+        match result {
+            Ok(new_id) => {
+                expected.keep();
+                if let Err(e) = docker.remove_container(id, Some(RemoveContainerOptions { force: true, ..Default::default() })).await {
+                    eprintln!("删除旧容器 {} 失败: {}", id, e);
+                }
+                self.container_configs.lock().unwrap().remove(id);
+                if let Ok(inspect) = docker.inspect_container(&new_id, None).await {
+                    self.update_container_config(&new_id, &inspect, image.to_string()).await?;
+                }
+                println!("✅ 容器 {} 已重建: {} -> {}", name, id, new_id);
+                Ok(new_id)
+            }
+            Err(e) => {
+                eprintln!("❌ 重建容器 {} 失败，恢复旧容器: {}", name, e);
+                let _ = docker.remove_container(&name, Some(RemoveContainerOptions { force: true, ..Default::default() })).await;
+                docker.rename_container(id, RenameContainerOptions { name: name.as_str() }).await?;
+                drop(expected);
+                if was_running {
+                    docker.start_container::<String>(id, None).await?;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// 去掉配置中与镜像默认值相同的部分，只保留创建容器时显式指定的设置
+    async fn strip_image_defaults(&self, config: &mut Config<String>, image: &str) {
+        let Ok(inspect) = self.docker.inspect_image(image).await else {
+            return;
+        };
+        let Some(defaults) = inspect.config else {
+            return;
+        };
+        if let (Some(env), Some(default_env)) = (config.env.as_mut(), defaults.env.as_ref()) {
+            env.retain(|e| !default_env.contains(e));
+        }
+        if let (Some(labels), Some(default_labels)) = (config.labels.as_mut(), defaults.labels.as_ref()) {
+            labels.retain(|k, v| default_labels.get(k) != Some(v));
+        }
+        if config.cmd == defaults.cmd {
+            config.cmd = None;
+        }
+        if config.entrypoint == defaults.entrypoint {
+            config.entrypoint = None;
+        }
+        if config.working_dir == defaults.working_dir {
+            config.working_dir = None;
+        }
+        if config.user == defaults.user {
+            config.user = None;
+        }
+        if config.stop_signal == defaults.stop_signal {
+            config.stop_signal = None;
+        }
+        if config.healthcheck == defaults.healthcheck {
+            config.healthcheck = None;
+        }
+    }
+
+    // 创建并启动重建后的容器，同时连接旧容器所在的其他网络
+    async fn create_recreated(
+        &self,
+        name: &str,
+        config: Config<String>,
+        old: &bollard::models::ContainerInspectResponse,
+    ) -> anyhow::Result<String> {
+        let docker = &self.docker;
+        let primary_network = config.host_config.as_ref()
+            .and_then(|h| h.network_mode.clone())
+            .unwrap_or_default();
+        let created = docker.create_container(
+            Some(CreateContainerOptions { name, ..Default::default() }),
+            config,
+        ).await?;
+
+        let old_id = old.id.clone().unwrap_or_default();
+        let networks = old.network_settings.as_ref()
+            .and_then(|n| n.networks.clone())
+            .unwrap_or_default();
+        for (network, endpoint) in networks {
+            if network == primary_network || (primary_network == "default" && network == "bridge") {
+                continue;
+            }
+            // 旧容器 ID 前缀会作为默认别名出现，重建后不再适用
+            let aliases = endpoint.aliases.map(|aliases| {
+                aliases.into_iter().filter(|a| !old_id.starts_with(a.as_str())).collect::<Vec<_>>()
+            });
+            docker.connect_network(&network, ConnectNetworkOptions {
+                container: created.id.as_str(),
+                endpoint_config: bollard::models::EndpointSettings {
+                    aliases,
+                    ..Default::default()
+                },
+            }).await?;
+        }
+
+        docker.start_container::<String>(&created.id, None).await?;
+        Ok(created.id)
+    }
+
+    /// 检查开启了自动更新的容器，镜像有变化时拉取并重建。
+    /// `dry_run` 为空时使用设置中的值。
+    pub async fn check_for_updates(&self, dry_run: Option<bool>) -> anyhow::Result<Vec<UpdateRecord>> {
+        let _guard = self.updater.run_lock.lock().await;
+        let settings = self.updater.settings();
+        let dry_run = dry_run.unwrap_or(settings.dry_run);
+        self.updater.mark_run(Local::now());
+
+        let candidates: Vec<ContainerConfig> = {
+            let configs = self.container_configs.lock().unwrap();
+            configs.values()
+                .filter(|c| is_enabled_for(c.config.as_ref().and_then(|c| c.labels.as_ref())))
+                .cloned()
+                .collect()
+        };
+        println!("开始检查镜像更新，共 {} 个容器开启了自动更新", candidates.len());
+
+        let mut records = Vec::new();
+        for config in candidates {
+            match self.update_image(&config, dry_run).await {
+                Ok(Some(record)) => records.push(record),
+                Ok(None) => {}
+                Err(e) => eprintln!("获取容器 {} 信息失败: {}", config.name, e),
+            }
+        }
+
+        println!("镜像更新检查完成");
+        Ok(records)
+    }
+
+    // 拉取容器的最新镜像，有更新时重建；镜像已固定或已是最新时返回 None。
+    // 调用方需要持有 updater.run_lock
+    // 比较仓库中标签的摘要和容器当前镜像的 RepoDigests，有更新时返回仓库摘要
+    async fn registry_update(&self, image: &str, old_image_id: &str) -> anyhow::Result<Option<String>> {
+        let digest = self.images.registry_digest(image).await?;
+        let repo_digests = self.docker.inspect_image(old_image_id).await?
+            .repo_digests
+            .unwrap_or_default();
+        if repo_digests.iter().any(|d| d.ends_with(&format!("@{}", digest))) {
+            Ok(None)
+        } else {
+            Ok(Some(digest))
+        }
+    }
+
+    async fn update_image(&self, config: &ContainerConfig, dry_run: bool) -> anyhow::Result<Option<UpdateRecord>> {
+        // 按摘要或镜像 ID 固定的容器不会有新版本
+        if config.image.contains('@') || config.image.starts_with("sha256:") {
+            println!("容器 {} 的镜像已固定版本，跳过", config.name);
+            return Ok(None);
+        }
+        let image = normalize_image_name(&config.image);
+        let old_image_id = self.docker.inspect_container(&config.container_id, None).await?
+            .image
+            .unwrap_or_default();
+
+        let mut record = UpdateRecord {
+            container_id: config.container_id.clone(),
+            name: config.name.clone(),
+            image: image.clone(),
+            old_image_id: old_image_id.clone(),
+            new_image_id: String::new(),
+            new_container_id: None,
+            status: UpdateStatus::Failed,
+            message: None,
+            timestamp: Local::now(),
+        };
+
+        let labels = config.config.as_ref().and_then(|c| c.labels.as_ref());
+        let check_only = dry_run || is_monitor_only(labels);
+        let latest = if check_only {
+            // 只检查时不拉取镜像，否则本地标签会指向新镜像，下次重建时会悄悄用上
+            self.registry_update(&image, &old_image_id).await
+        } else {
+            match self.images.pull_image(&image, None).await {
+                Ok(()) => self.docker.inspect_image(&image).await
+                    .map(|latest| latest.id.filter(|id| *id != old_image_id))
+                    .map_err(anyhow::Error::from),
+                Err(e) => Err(e),
+            }
+        };
+        match latest {
+            Ok(latest) => {
+                let Some(latest) = latest else {
+                    println!("容器 {} 的镜像已是最新", config.name);
+                    return Ok(None);
+                };
+                record.new_image_id = latest;
+                if check_only {
+                    println!("🔔 容器 {} 有可用的镜像更新: {}", config.name, image);
+                    record.status = UpdateStatus::Available;
+                } else {
+                    let result = self.recreate_container(&config.container_id, &image).await;
+                    self.audit_action(
+                        "auto_update", &config.name,
+                        serde_json::json!({
+                            "image": image,
+                            "old_image_id": old_image_id,
+                            "new_image_id": record.new_image_id,
+                        }),
+                        &result,
+                    );
+                    match result {
+                        Ok(new_id) => {
+                            record.status = UpdateStatus::Updated;
+                            record.new_container_id = Some(new_id);
+                        }
+                        Err(e) => record.message = Some(e.to_string()),
                     }
-                    // 如果没有 bridge 网络，返回第一个找到的 IP
-                    for network in networks.values() {
-                        if let Some(ip) = &network.ip_address {
-                            if !ip.is_empty() {
-                                return Some(ip.clone());
-                            }
+                }
+            }
+            Err(e) => {
+                eprintln!("检查容器 {} 的镜像更新失败: {}", config.name, e);
+                record.message = Some(e.to_string());
+            }
+        }
+
+        self.updater.record(record.clone());
+        let (event, message) = match record.status {
+            UpdateStatus::Available => (EventKind::UpdateAvailable, format!("镜像 {} 有可用的更新", image)),
+            UpdateStatus::Updated => (EventKind::Updated, format!("已使用最新的镜像 {} 重建", image)),
+            UpdateStatus::Failed => (
+                EventKind::UpdateFailed,
+                format!("更新镜像 {} 失败: {}", image, record.message.as_deref().unwrap_or_default()),
+            ),
+        };
+        self.notifier.notify(Notification::new(event, &record.container_id, &record.name, message).with_details(&record));
+        Ok(Some(record))
+    }
+
+    /// 按设置的间隔和时间窗口定时检查镜像更新
+    pub async fn start_auto_update(self: Arc<Self>) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            ticker.tick().await;
+            if self.updater.is_due(Local::now()) {
+                if let Err(e) = self.check_for_updates(None).await {
+                    eprintln!("自动更新检查失败: {}", e);
+                }
+            }
+        }
+    }
+
+    pub fn maintenance_windows(&self) -> Vec<MaintenanceWindow> {
+        self.maintenance.active()
+    }
+
+    /// 开启维护模式，指定的容器解析为容器名，重建后仍然生效
+    pub async fn start_maintenance(&self, mut request: MaintenanceRequest, user: Option<String>) -> anyhow::Result<MaintenanceWindow> {
+        if let Some(container) = request.container.as_deref().filter(|c| !c.is_empty()) {
+            let name = self.container_name(container).await?;
+            request.container = Some(name);
+        }
+        self.maintenance.start(request, user)
+    }
+
+    pub fn end_maintenance(&self, id: u64) -> anyhow::Result<MaintenanceWindow> {
+        self.maintenance.end(id)
+    }
+
+    // 容器处于维护模式时记录跳过的自动重启
+    fn skip_for_maintenance(&self, id: &str, name: &str, labels: Option<&HashMap<String, String>>, action: &str) -> bool {
+        let Some(window) = self.active_maintenance(id, name, labels) else {
+            return false;
+        };
+        self.record_maintenance_skip(id, name, &window, action);
+        true
+    }
+
+    fn active_maintenance(&self, id: &str, name: &str, labels: Option<&HashMap<String, String>>) -> Option<MaintenanceWindow> {
+        let empty = HashMap::new();
+        self.maintenance.find(id, name, labels.unwrap_or(&empty))
+    }
+
+    fn record_maintenance_skip(&self, id: &str, name: &str, window: &MaintenanceWindow, action: &str) {
+        println!("容器 {} 处于维护模式 #{} ({})，跳过自动重启", name, window.id, window.scope());
+        let params = serde_json::json!({ "container_id": id, "skipped": action, "maintenance": window.id });
+        self.events.record(EventRecord::decision("maintenance_skip", name, &params, &Ok::<(), String>(())));
+    }
+
+    /// 管理器自身的状态，Docker 不可用、事件流断开或事件循环卡住时 `healthy` 为 false
+    pub async fn status(&self) -> ManagerStatus {
+        let mut docker = DockerStatus::default();
+        match tokio::time::timeout(Duration::from_secs(5), self.docker.version()).await {
+            Ok(Ok(version)) => {
+                docker.reachable = true;
+                docker.version = version.version;
+                docker.api_version = version.api_version;
+            }
+            Ok(Err(e)) => docker.error = Some(e.to_string()),
+            Err(_) => docker.error = Some("连接 Docker 超时".to_string()),
+        }
+        let event_stream = self.status.stream();
+        let stalled = self.status.stalled();
+
+        let mut problems = Vec::new();
+        if let Some(error) = &docker.error {
+            problems.push(format!("Docker 不可用: {}", error));
+        }
+        if event_stream.state != StreamState::Connected {
+            problems.push("Docker 事件流未连接".to_string());
+        }
+        problems.extend(stalled.clone());
+
+        let started = self.status.started();
+        ManagerStatus {
+            healthy: problems.is_empty(),
+            live: stalled.is_none(),
+            problems,
+            build: BuildInfo::current(),
+            started,
+            uptime_secs: (Local::now() - started).num_seconds(),
+            docker,
+            event_stream,
+            managed_containers: self.container_configs.lock().unwrap().len(),
+            maintenance: self.maintenance.active(),
+            config_version: status::config_version(),
+        }
+    }
+
+    /// 事件循环卡住时返回原因，用于存活检查
+    pub fn liveness(&self) -> Result<(), String> {
+        match self.status.stalled() {
+            Some(reason) => Err(reason),
+            None => Ok(()),
+        }
+    }
+
+    pub fn schedules(&self) -> Vec<ScheduleInfo> {
+        self.scheduler.list()
+    }
+
+    pub fn schedule(&self, name: &str) -> anyhow::Result<ScheduleInfo> {
+        self.scheduler.get(name)
+    }
+
+    /// 新建计划，或替换名为 `replace` 的计划。执行命令的计划记录保存的用户，每次执行时重新检查
+    pub fn save_schedule(&self, mut schedule: Schedule, replace: Option<&str>, user: Option<String>) -> anyhow::Result<()> {
+        if let ScheduleAction::Exec(request) = &schedule.action {
+            if request.command.is_some() && !self.exec.settings().allow_commands {
+                return Err(ApiError::invalid("不允许执行任意命令，请使用预先定义的操作").into());
+            }
+            self.exec.check_command_user(user.as_deref())?;
+        }
+        schedule.saved_by = user;
+        self.scheduler.save(schedule, replace)
+    }
+
+    pub fn remove_schedule(&self, name: &str) -> anyhow::Result<()> {
+        self.scheduler.remove(name)
+    }
+
+    pub fn schedule_history(&self, schedule: Option<&str>, limit: Option<usize>) -> Vec<ScheduleRun> {
+        self.scheduler.history(schedule, limit)
+    }
+
+    /// 立即执行一次计划，不等待执行完成。执行命令的计划同时检查触发的用户
+    pub fn trigger_schedule(self: &Arc<Self>, name: &str, user: Option<&str>) -> anyhow::Result<()> {
+        if let ScheduleAction::Exec(_) = self.scheduler.get(name)?.schedule.action {
+            self.exec.check_command_user(user)?;
+        }
+        let due = self.scheduler.manual(name)?;
+        tokio::spawn(self.clone().run_schedule(due.schedule, due.scheduled_for, due.trigger));
+        Ok(())
+    }
+
+    /// 按 cron 表达式执行计划任务
+    pub async fn start_scheduler(self: Arc<Self>) {
+        loop {
+            let now = chrono::Utc::now();
+            for due in self.scheduler.due(now) {
+                tokio::spawn(self.clone().run_schedule(due.schedule, due.scheduled_for, due.trigger));
+            }
+            // 最多 30 秒检查一次，新建和修改的计划也能及时生效
+            let wait = self.scheduler.next_wake()
+                .and_then(|next| (next - chrono::Utc::now()).to_std().ok())
+                .unwrap_or_default()
+                .clamp(Duration::from_secs(1), Duration::from_secs(30));
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    async fn run_schedule(self: Arc<Self>, schedule: Schedule, scheduled_for: chrono::DateTime<chrono::Utc>, trigger: RunTrigger) {
+        if !self.scheduler.begin(&schedule.name) {
+            println!("计划任务 {} 上一次执行尚未结束，跳过", schedule.name);
+            let mut run = ScheduleRun::new(&schedule, trigger, scheduled_for, RunStatus::Skipped);
+            run.message = Some("上一次执行尚未结束".to_string());
+            self.scheduler.finish(run, false);
+            return;
+        }
+        if trigger != RunTrigger::Manual {
+            tokio::time::sleep(scheduler::jitter(&schedule.name, schedule.jitter)).await;
+        }
+
+        let mut run = ScheduleRun::new(&schedule, trigger, scheduled_for, RunStatus::Success);
+        println!("执行计划任务 {}: {}", schedule.name, schedule.action.as_str());
+        match self.schedule_targets(&schedule).await {
+            Ok(targets) if targets.is_empty() => {
+                run.status = RunStatus::Skipped;
+                run.message = Some("没有匹配的容器".to_string());
+            }
+            Ok(targets) => {
+                for (id, name) in targets {
+                    let result = self.run_schedule_action(&schedule, &id, &name).await;
+                    self.audit_action(
+                        &format!("schedule_{}", schedule.action.as_str()), &name,
+                        serde_json::json!({ "container_id": id, "schedule": schedule.name }),
+                        &result,
+                    );
+                    if result.is_err() {
+                        run.status = RunStatus::Failure;
+                    }
+                    run.targets.push(match result {
+                        Ok(message) => TargetResult { container: name, success: true, message },
+                        Err(e) => TargetResult { container: name, success: false, message: Some(e.to_string()) },
+                    });
+                }
+            }
+            Err(e) => {
+                run.status = RunStatus::Failure;
+                run.message = Some(e.to_string());
+            }
+        }
+        if run.status == RunStatus::Failure {
+            eprintln!("计划任务 {} 执行失败", schedule.name);
+        } else {
+            println!("计划任务 {} 执行完成", schedule.name);
+        }
+        self.scheduler.finish(run, true);
+    }
+
+    // 计划的目标容器 (ID, 名称)，不包括管理器自己的辅助容器
+    async fn schedule_targets(&self, schedule: &Schedule) -> anyhow::Result<Vec<(String, String)>> {
+        if let Some(container) = schedule.container.as_deref().filter(|c| !c.is_empty()) {
+            let inspect = self.docker.inspect_container(container, None).await?;
+            let id = inspect.id.unwrap_or_default();
+            let name = inspect.name.unwrap_or_default().trim_start_matches('/').to_string();
+            return Ok(vec![(id, name)]);
+        }
+        let mut filters = HashMap::new();
+        filters.insert("label", vec![schedule.label.as_deref().unwrap_or_default()]);
+        let containers = self.docker.list_containers(Some(ListContainersOptions {
+            all: true,
+            filters,
+            ..Default::default()
+        })).await?;
+        Ok(containers.into_iter()
+            .filter(|c| !c.labels.as_ref().is_some_and(|l| l.contains_key(LABEL_HELPER)))
+            .filter_map(|c| {
+                let name = c.names.as_ref()
+                    .and_then(|n| n.first())
+                    .map(|n| n.trim_start_matches('/').to_string())?;
+                Some((c.id?, name))
+            })
+            .collect())
+    }
+
+    // 对一个容器执行计划的操作，成功时返回附加说明
+    async fn run_schedule_action(&self, schedule: &Schedule, id: &str, name: &str) -> anyhow::Result<Option<String>> {
+        match &schedule.action {
+            ScheduleAction::Restart => {
+                let expected = ExpectedStop::new(&self.expected_stops, id);
+                let result = self.docker.restart_container(id, Some(RestartContainerOptions { t: 10 })).await;
+                drop(expected);
+                self.scheduler.mark_stopped(id, false);
+                result?;
+                Ok(None)
+            }
+            ScheduleAction::Stop => {
+                // 计划停止的容器在下一次启动前保持停止，管理器重启后也不自动拉起；
+                // 停止失败时撤销标记，容器之后退出仍按策略重启
+                let expected = ExpectedStop::new(&self.expected_stops, id);
+                let was_stopped = self.scheduler.is_stopped(id);
+                self.scheduler.mark_stopped(id, true);
+                let result = async {
+                    if !self.is_running(id).await? {
+                        return Ok(Some("容器已停止".to_string()));
+                    }
+                    self.docker.stop_container(id, None).await?;
+                    Ok(None)
+                }.await;
+                match result {
+                    Ok(_) => expected.keep(),
+                    Err(_) if !was_stopped => self.scheduler.mark_stopped(id, false),
+                    Err(_) => {}
+                }
+                result
+            }
+            ScheduleAction::Start => {
+                self.expected_stops.lock().unwrap().remove(id);
+                self.scheduler.mark_stopped(id, false);
+                if self.is_running(id).await? {
+                    return Ok(Some("容器已在运行".to_string()));
+                }
+                self.docker.start_container::<String>(id, None).await?;
+                Ok(None)
+            }
+            ScheduleAction::Exec(request) => {
+                // 与 API 相同的检查，保存计划的用户被移出允许列表后不再执行
+                let output = self.exec.run_command(id, request, schedule.saved_by.as_deref()).await?;
+                if output.timed_out {
+                    anyhow::bail!("命令超时");
+                }
+                match output.exit_code {
+                    Some(0) => Ok(Some("退出码 0".to_string())),
+                    code => {
+                        let stderr = output.stderr.trim();
+                        let tail = &stderr[stderr.floor_char_boundary(stderr.len().saturating_sub(500))..];
+                        anyhow::bail!("退出码 {}: {}", code.map_or("未知".to_string(), |c| c.to_string()), tail)
+                    }
+                }
+            }
+            ScheduleAction::Update => {
+                let config = self.container_configs.lock().unwrap().get(id).cloned()
+                    .ok_or_else(|| anyhow::anyhow!("容器 {} 不受管理器管理", name))?;
+                let _guard = self.updater.run_lock.lock().await;
+                let dry_run = self.updater.settings().dry_run;
+                match self.update_image(&config, dry_run).await? {
+                    None => Ok(Some("镜像已是最新".to_string())),
+                    Some(record) => match record.status {
+                        UpdateStatus::Updated => Ok(Some("已使用最新镜像重建".to_string())),
+                        UpdateStatus::Available => Ok(Some("有可用的镜像更新".to_string())),
+                        UpdateStatus::Failed => Err(anyhow::anyhow!(record.message.unwrap_or_default())),
+                    },
+                }
+            }
+        }
+    }
+
+    async fn is_running(&self, id: &str) -> anyhow::Result<bool> {
+        let inspect = self.docker.inspect_container(id, None).await?;
+        Ok(inspect.state.and_then(|s| s.running).unwrap_or(false))
+    }
+
+    // 受管容器及其配置所引用的镜像，删除和清理镜像时需要保留
+    fn image_references(&self) -> ImageReferences {
+        let configs = self.container_configs.lock().unwrap();
+        ImageReferences {
+            managed_containers: configs.keys().cloned().collect(),
+            image_names: configs.values()
+                .filter(|c| !c.image.is_empty())
+                .map(|c| if c.image.starts_with("sha256:") { c.image.clone() } else { normalize_image_name(&c.image) })
+                .collect(),
+        }
+    }
+
+    pub async fn list_images(&self) -> anyhow::Result<Vec<ImageInfo>> {
+        self.images.list_images(&self.image_references()).await
+    }
+
+    pub async fn pull_image(&self, image: &str, progress: Option<&UnboundedSender<PullProgress>>) -> anyhow::Result<()> {
+        self.images.pull_image(&normalize_image_name(image), progress).await
+    }
+
+    pub async fn remove_image(&self, image: &str, force: bool) -> anyhow::Result<RemoveOutcome> {
+        self.images.remove_image(image, force, &self.image_references()).await
+    }
+
+    pub async fn prune_images(&self, all: bool) -> anyhow::Result<PruneReport> {
+        self.images.prune_images(all, &self.image_references()).await
+    }
+
+    fn volume_references(&self) -> VolumeReferences {
+        let configs = self.container_configs.lock().unwrap();
+        VolumeReferences {
+            managed_containers: configs.keys().cloned().collect(),
+            volume_names: configs.values()
+                .flat_map(|c| c.mounts.iter())
+                .filter_map(|m| match m {
+                    MountSpec::Volume { source: Some(source), .. } => Some(source.clone()),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    pub async fn list_volumes(&self) -> anyhow::Result<Vec<VolumeInfo>> {
+        self.volumes.list_volumes(&self.volume_references()).await
+    }
+
+    pub async fn inspect_volume(&self, name: &str) -> anyhow::Result<VolumeInfo> {
+        self.volumes.inspect_volume(name, &self.volume_references()).await
+    }
+
+    pub async fn create_volume(&self, spec: &VolumeSpec) -> anyhow::Result<String> {
+        self.volumes.create_volume(spec).await
+    }
+
+    pub async fn remove_volume(&self, name: &str) -> anyhow::Result<VolumeRemoveOutcome> {
+        self.volumes.remove_volume(name, &self.volume_references()).await
+    }
+
+    pub fn backup_settings(&self) -> BackupSettings {
+        self.backups.settings()
+    }
+
+    pub fn update_backup_settings(&self, settings: BackupSettings) -> anyhow::Result<()> {
+        self.backups.update_settings(settings)
+    }
+
+    pub fn list_backups(&self, volume: Option<&str>) -> anyhow::Result<Vec<BackupInfo>> {
+        self.backups.list_backups(volume)
+    }
+
+    pub fn remove_backup(&self, file: &str) -> anyhow::Result<()> {
+        self.backups.remove_backup(file)
+    }
+
+    pub async fn backup_volume(&self, volume: &str, pause: bool) -> anyhow::Result<BackupInfo> {
+        self.images.ensure_image(&self.backups.settings().helper_image, PullPolicy::Missing, None).await?;
+        self.backups.backup_volume(volume, pause).await
+    }
+
+    /// 备份容器配置中挂载的所有命名卷；`pause` 时整个过程中暂停该容器
+    pub async fn backup_container(&self, id: &str, pause: bool) -> anyhow::Result<Vec<BackupInfo>> {
+        let stored = self.container_configs.lock().unwrap().get(id).cloned()
+            .ok_or_else(|| ApiError::not_found(format!("未找到容器 {} 的配置", id)))?;
+        let volumes: Vec<String> = stored.mounts.iter()
+            .filter_map(|m| match m {
+                MountSpec::Volume { source: Some(source), .. } => Some(source.clone()),
+                _ => None,
+            })
+            .collect();
+        if volumes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.images.ensure_image(&self.backups.settings().helper_image, PullPolicy::Missing, None).await?;
+        let running = self.docker.inspect_container(id, None).await?
+            .state.and_then(|s| s.running).unwrap_or(false);
+        let paused = pause && running && self.backups.pause(id).await;
+
+        let mut result = Vec::new();
+        let mut failure = None;
+        for volume in &volumes {
+            match self.backups.backup_volume(volume, false).await {
+                Ok(info) => result.push(info),
+                Err(e) => {
+                    failure = Some(anyhow::anyhow!("备份卷 {} 失败: {}", volume, e));
+                    break;
+                }
+            }
+        }
+
+        if paused {
+            self.backups.unpause(&[id.to_string()]).await;
+        }
+        match failure {
+            Some(e) => Err(e),
+            None => {
+                println!("已备份容器 {} 的 {} 个卷", stored.name, result.len());
+                Ok(result)
+            }
+        }
+    }
+
+    pub async fn restore_backup(&self, file: &str, volume: Option<&str>, replace: bool) -> anyhow::Result<RestoreReport> {
+        self.images.ensure_image(&self.backups.settings().helper_image, PullPolicy::Missing, None).await?;
+        self.backups.restore(file, volume, replace).await
+    }
+
+    pub async fn list_groups(&self) -> anyhow::Result<Vec<GroupInfo>> {
+        self.groups.list_groups().await
+    }
+
+    pub async fn group_info(&self, name: &str) -> anyhow::Result<GroupInfo> {
+        self.groups.group(name).await
+    }
+
+    /// 按依赖顺序启动分组中未运行的容器，需要时等待依赖健康或执行完成
+    pub async fn group_start(&self, name: &str) -> anyhow::Result<()> {
+        let group = self.ordered_group(name).await?;
+        for member in &group.members {
+            if member.is_running() {
+                continue;
+            }
+            self.wait_for_members(&group, member).await?;
+            self.start_member(&group, member).await?;
+        }
+        println!("✅ 分组 {} 已启动", group.name);
+        Ok(())
+    }
+
+    /// 按依赖的逆序停止分组中的容器，停止后不会被自动重启
+    pub async fn group_stop(&self, name: &str) -> anyhow::Result<()> {
+        let group = self.ordered_group(name).await?;
+        self.stop_members(&group).await?;
+        println!("✅ 分组 {} 已停止", group.name);
+        Ok(())
+    }
+
+    /// 先按逆序停止，再按依赖顺序启动分组中的所有容器
+    pub async fn group_restart(&self, name: &str) -> anyhow::Result<()> {
+        let group = self.ordered_group(name).await?;
+        let mut started = 0;
+        let result = async {
+            self.stop_members(&group).await?;
+            for member in &group.members {
+                self.wait_for_members(&group, member).await?;
+                self.start_member(&group, member).await?;
+                started += 1;
+            }
+            anyhow::Ok(())
+        }.await;
+        if let Err(e) = result {
+            // 重启前在运行、但还没有重新启动的成员
+            let pending: Vec<&GroupMember> = group.members[started..].iter()
+                .filter(|m| m.is_running())
+                .collect();
+            self.recover_members(&group, &pending).await;
+            return Err(e);
+        }
+        println!("✅ 分组 {} 已重启", group.name);
+        Ok(())
+    }
+
+    async fn ordered_group(&self, name: &str) -> anyhow::Result<GroupInfo> {
+        let group = self.groups.group(name).await?;
+        if let Some(error) = &group.error {
+            return Err(ApiError::conflict(format!("分组 {} 无法排序: {}", group.name, error)).into());
+        }
+        Ok(group)
+    }
+
+    async fn stop_members(&self, group: &GroupInfo) -> anyhow::Result<()> {
+        for member in group.members.iter().rev() {
+            if !member.is_running() {
+                continue;
+            }
+            let expected = ExpectedStop::new(&self.expected_stops, &member.container_id);
+            self.docker.stop_container(&member.container_id, None).await
+                .map_err(|e| anyhow::anyhow!("停止 {} 失败: {}", member.name, e))?;
+            expected.keep();
+            println!("已停止分组 {} 的成员 {}", group.name, member.name);
+        }
+        Ok(())
+    }
+
+    async fn start_member(&self, group: &GroupInfo, member: &GroupMember) -> anyhow::Result<()> {
+        // 与 compose 项目相同：被以 service_completed_successfully 依赖的成员退出后不自动重启
+        let one_shot = group.members.iter()
+            .flat_map(|m| m.depends_on.iter())
+            .any(|d| d.condition == DependencyCondition::ServiceCompletedSuccessfully
+                && group.member(&d.name).is_some_and(|m| m.container_id == member.container_id));
+        if one_shot {
+            self.expected_stops.lock().unwrap().insert(member.container_id.clone());
+        } else {
+            self.expected_stops.lock().unwrap().remove(&member.container_id);
+        }
+        self.docker.start_container::<String>(&member.container_id, None).await
+            .map_err(|e| anyhow::anyhow!("启动 {} 失败: {}", member.name, e))?;
+        println!("已启动分组 {} 的成员 {}", group.name, member.name);
+        Ok(())
+    }
+
+    // 重启中途失败时，不再等待依赖，直接把被停止的成员重新启动并撤销停止标记
+    async fn recover_members(&self, group: &GroupInfo, members: &[&GroupMember]) {
+        for member in members {
+            println!("恢复启动分组 {} 的成员 {}", group.name, member.name);
+            if let Err(e) = self.start_member(group, member).await {
+                eprintln!("❌ {}", e);
+                self.expected_stops.lock().unwrap().remove(&member.container_id);
+            }
+        }
+    }
+
+    async fn wait_for_members(&self, group: &GroupInfo, member: &GroupMember) -> anyhow::Result<()> {
+        for dependency in &member.depends_on {
+            if dependency.condition == DependencyCondition::ServiceStarted {
+                continue;
+            }
+            let Some(target) = group.member(&dependency.name) else {
+                continue;
+            };
+            println!("{} 等待依赖 {} 就绪", member.name, target.name);
+            self.wait_for_condition(&target.container_id, dependency.condition).await
+                .map_err(|e| anyhow::anyhow!("{} 的依赖 {} 未就绪: {}", member.name, target.name, e))?;
+        }
+        Ok(())
+    }
+
+    /// 容器崩溃并被重启后，等它恢复健康，再按依赖顺序重启声明了级联重启的依赖者
+    async fn cascade_restart(self: Arc<Self>, id: String) {
+        let group = match self.groups.group_of(&id).await {
+            Ok(Some(group)) if group.error.is_none() => group,
+            Ok(_) => return,
+            Err(e) => {
+                eprintln!("获取容器 {} 所在的分组失败: {}", id, e);
+                return;
+            }
+        };
+        let targets: Vec<GroupMember> = group.cascade_targets(&id)
+            .into_iter()
+            .filter(|m| m.is_running())
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+        // 依赖反复崩溃时只保留一次等待中的级联
+        if !self.cascades.lock().unwrap().insert(id.clone()) {
+            return;
+        }
+        let result = self.run_cascade(&group, &id, &targets).await;
+        self.cascades.lock().unwrap().remove(&id);
+        if let Err(e) = result {
+            eprintln!("❌ 分组 {} 级联重启失败: {}", group.name, e);
+        }
+    }
+
+    async fn run_cascade(&self, group: &GroupInfo, id: &str, targets: &[GroupMember]) -> anyhow::Result<()> {
+        let inspect = self.docker.inspect_container(id, None).await?;
+        let origin = inspect.name.as_deref().unwrap_or(id).trim_start_matches('/').to_string();
+        // 配置了健康检查时等到健康，否则容器启动即可
+        let has_healthcheck = inspect.config.and_then(|c| c.healthcheck)
+            .and_then(|h| h.test)
+            .is_some_and(|test| test.first().is_some_and(|t| t != "NONE"));
+        let condition = if has_healthcheck {
+            DependencyCondition::ServiceHealthy
+        } else {
+            DependencyCondition::ServiceStarted
+        };
+        self.wait_for_condition(id, condition).await
+            .map_err(|e| anyhow::anyhow!("{} 未恢复: {}", origin, e))?;
+
+        for target in targets {
+            println!("依赖 {} 已恢复，级联重启 {}", origin, target.name);
+            self.expected_stops.lock().unwrap().insert(target.container_id.clone());
+            if let Err(e) = self.docker.stop_container(&target.container_id, None).await {
+                eprintln!("停止 {} 失败: {}", target.name, e);
+            }
+            let result = match self.wait_for_members(group, target).await {
+                Ok(()) => self.start_member(group, target).await,
+                Err(e) => Err(e),
+            };
+            self.audit_action(
+                "cascade_restart", &target.container_name,
+                serde_json::json!({ "group": group.name, "dependency": origin }),
+                &result,
+            );
+            if let Err(e) = result {
+                self.recover_members(group, &[target]).await;
+                return Err(e);
+            }
+            self.notifier.notify(Notification::new(
+                EventKind::Restart, &target.container_id, &target.container_name,
+                format!("依赖 {} 恢复后级联重启", origin),
+            ));
+        }
+        println!("✅ 分组 {} 级联重启完成", group.name);
+        Ok(())
+    }
+
+    /// 把容器当前的文件系统提交为镜像，镜像标签和快照记录都指向来源容器及其配置
+    pub async fn commit_container(&self, id: &str, request: &CommitRequest) -> anyhow::Result<Snapshot> {
+        request.validate()?;
+        // 快照包含容器的整个文件系统，不允许访问文件的容器也不允许提交
+        self.files.check_container(id).await?;
+        let inspect = self.docker.inspect_container(id, None).await?;
+        let container_id = inspect.id.clone().unwrap_or_else(|| id.to_string());
+        let name = inspect.name.clone().unwrap_or_default().trim_start_matches('/').to_string();
+        let config = self.container_configs.lock().unwrap().get(&container_id).cloned()
+            .unwrap_or_else(|| {
+                let image = inspect.config.as_ref().and_then(|c| c.image.clone()).unwrap_or_default();
+                Self::config_from_inspect(&container_id, &inspect, image)
+            });
+
+        let changes = format!(
+            "LABEL {}={}\nLABEL {}={}",
+            snapshots::LABEL_SOURCE_ID, serde_json::to_string(&container_id)?,
+            snapshots::LABEL_SOURCE_NAME, serde_json::to_string(&name)?,
+        );
+        let response = self.docker.commit_container(CommitContainerOptions {
+            container: container_id.clone(),
+            repo: request.repository.clone(),
+            tag: request.tag.clone(),
+            comment: request.message.clone().unwrap_or_default(),
+            author: request.author.clone().unwrap_or_default(),
+            pause: request.pause,
+            changes: Some(changes),
+        }, Config::<String>::default()).await?;
+        let image_id = response.id
+            .ok_or_else(|| anyhow::anyhow!("Docker 没有返回镜像 ID"))?;
+
+        let snapshot = Snapshot {
+            image_id,
+            image: request.reference(),
+            message: request.message.clone(),
+            author: request.author.clone(),
+            created: chrono::Local::now(),
+            container_id,
+            container_name: name.clone(),
+            config,
+        };
+        self.snapshots.record(snapshot.clone())?;
+        println!("已将容器 {} 提交为镜像 {}", name, snapshot.image);
+        Ok(snapshot)
+    }
+
+    /// 导出单个容器的 compose 服务或 `docker run` 命令
+    pub async fn export_container(&self, id: &str, format: ExportFormat, show_secrets: bool) -> anyhow::Result<String> {
+        let inspect = self.docker.inspect_container(id, None).await?;
+        let exported = self.exported_container(&inspect).await;
+        Self::render_export(&[exported], format, show_secrets)
+    }
+
+    /// 导出主机上的所有容器（不包括备份用的辅助容器）
+    pub async fn export_all(&self, format: ExportFormat, show_secrets: bool) -> anyhow::Result<String> {
+        let containers = self.docker.list_containers(Some(ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        })).await?;
+
+        let mut exported = Vec::new();
+        for container in containers {
+            if container.labels.as_ref().is_some_and(|l| l.contains_key(LABEL_HELPER)) {
+                continue;
+            }
+            let Some(id) = container.id else {
+                continue;
+            };
+            match self.docker.inspect_container(&id, None).await {
+                Ok(inspect) => exported.push(self.exported_container(&inspect).await),
+                // 容器可能在列出后被删除
+                Err(e) => eprintln!("导出容器 {} 失败: {}", id, e),
+            }
+        }
+        exported.sort_by(|a, b| a.stored.name.cmp(&b.stored.name));
+        Self::render_export(&exported, format, show_secrets)
+    }
+
+    async fn exported_container(&self, inspect: &bollard::models::ContainerInspectResponse) -> ExportedContainer {
+        let id = inspect.id.clone().unwrap_or_default();
+        // 优先使用创建容器时指定的镜像名，而不是镜像 ID
+        let image = inspect.config.as_ref().and_then(|c| c.image.clone())
+            .or_else(|| inspect.image.clone())
+            .unwrap_or_default();
+        let stored = Self::config_from_inspect(&id, inspect, image);
+        let mut config = stored.config.clone().unwrap_or_default();
+        if let Some(image_id) = inspect.image.as_deref() {
+            self.strip_image_defaults(&mut config, image_id).await;
+        }
+        if config.hostname.as_deref().is_some_and(|h| id.starts_with(h)) {
+            config.hostname = None;
+        }
+        ExportedContainer { stored, config }
+    }
+
+    fn render_export(containers: &[ExportedContainer], format: ExportFormat, show_secrets: bool) -> anyhow::Result<String> {
+        match format {
+            ExportFormat::Compose => export::to_compose(containers, show_secrets),
+            ExportFormat::Run => Ok(export::to_run_commands(containers, show_secrets)),
+        }
+    }
+
+    pub async fn list_stacks(&self) -> anyhow::Result<Vec<StackInfo>> {
+        let mut result = Vec::new();
+        for definition in self.stacks.definitions() {
+            result.push(self.stacks.info(&definition).await?);
+        }
+        Ok(result)
+    }
+
+    pub async fn stack_info(&self, name: &str) -> anyhow::Result<(StackDefinition, StackInfo)> {
+        let definition = self.stacks.get(name)?;
+        let info = self.stacks.info(&definition).await?;
+        Ok((definition, info))
+    }
+
+    /// 保存 compose 项目并部署；`name` 对应的项目已存在时更新它
+    pub async fn deploy_stack(&self, name: &str, source: StackSource, pull_policy: PullPolicy) -> anyhow::Result<StackInfo> {
+        let definition = self.stacks.build_definition(name, source)?;
+        self.stacks.save(definition.clone())?;
+        println!("已保存项目 {}", definition.name);
+        self.stack_up(&definition.name, pull_policy).await?;
+        self.stacks.info(&definition).await
+    }
+
+    /// 按依赖顺序创建或启动项目中的服务，配置变化的服务会重建容器，
+    /// 已从 compose 文件中移除的服务会删除其容器
+    pub async fn stack_up(&self, name: &str, pull_policy: PullPolicy) -> anyhow::Result<()> {
+        let project = self.stacks.get(name)?.project()?;
+        println!("开始部署项目 {}", project.name);
+        self.stacks.prepare(&project, &self.networks, &self.volumes).await?;
+        let containers = self.stacks.containers(&project.name).await?;
+
+        // 先删除 compose 文件中已经不存在的服务
+        for (service, container) in &containers {
+            if project.service(service).is_some() {
+                continue;
+            }
+            if let Some(id) = &container.id {
+                self.remove_container(id).await?;
+                println!("服务 {} 已从 compose 文件中移除，已删除其容器", service);
+            }
+        }
+
+        for service in &project.services {
+            self.wait_for_dependencies(&project, service).await?;
+            let existing = containers.get(&service.name);
+            let hash = existing
+                .and_then(|c| c.labels.as_ref())
+                .and_then(|l| l.get(compose::LABEL_CONFIG_HASH));
+            match existing.and_then(|c| c.id.clone()) {
+                Some(id) if hash == Some(&service.config_hash) => {
+                    if existing.and_then(|c| c.state.as_deref()) != Some("running") {
+                        self.start_service(&project, service, &id).await?;
+                    }
+                }
+                Some(id) => {
+                    println!("服务 {} 的配置已变化，重建容器", service.name);
+                    self.images.ensure_image(&normalize_image_name(&service.image), pull_policy, None).await?;
+                    self.remove_container(&id).await?;
+                    self.create_service_container(&project, service, PullPolicy::Missing).await?;
+                }
+                None => {
+                    self.create_service_container(&project, service, pull_policy).await?;
+                }
+            }
+        }
+        println!("✅ 项目 {} 部署完成", project.name);
+        Ok(())
+    }
+
+    /// 按依赖的逆序删除项目中的容器和网络，`remove_volumes` 时同时删除项目创建的卷
+    pub async fn stack_down(&self, name: &str, remove_volumes: bool) -> anyhow::Result<()> {
+        let definition = self.stacks.get(name)?;
+        let project = definition.project();
+        let mut containers = self.stacks.containers(&definition.name).await?;
+
+        if let Ok(project) = &project {
+            for service in project.services.iter().rev() {
+                if let Some(id) = containers.remove(&service.name).and_then(|c| c.id) {
+                    self.remove_container(&id).await?;
+                    println!("已删除服务 {} 的容器", service.name);
+                }
+            }
+        }
+        // compose 文件中已经不存在的服务
+        for (service, container) in containers {
+            if let Some(id) = container.id {
+                self.remove_container(&id).await?;
+                println!("已删除服务 {} 的容器", service);
+            }
+        }
+
+        if let Ok(project) = &project {
+            self.stacks.cleanup(project, remove_volumes).await;
+        }
+        println!("项目 {} 已停止", definition.name);
+        Ok(())
+    }
+
+    /// 先按逆序停止，再按依赖顺序启动项目中已有的容器
+    pub async fn stack_restart(&self, name: &str) -> anyhow::Result<()> {
+        let project = self.stacks.get(name)?.project()?;
+        let containers = self.stacks.containers(&project.name).await?;
+        let container_id = |service: &ComposeService| containers.get(&service.name).and_then(|c| c.id.clone());
+
+        let mut started = 0;
+        let result = async {
+            for service in project.services.iter().rev() {
+                if let Some(id) = container_id(service) {
+                    let expected = ExpectedStop::new(&self.expected_stops, &id);
+                    self.docker.stop_container(&id, None).await
+                        .map_err(|e| anyhow::anyhow!("停止服务 {} 失败: {}", service.name, e))?;
+                    expected.keep();
+                }
+            }
+            for service in &project.services {
+                match container_id(service) {
+                    Some(id) => {
+                        self.wait_for_dependencies(&project, service).await?;
+                        self.start_service(&project, service, &id).await?;
+                    }
+                    None => println!("服务 {} 没有容器，跳过", service.name),
+                }
+                started += 1;
+            }
+            anyhow::Ok(())
+        }.await;
+        if let Err(e) = result {
+            // 重启前在运行、但还没有重新启动的服务，不再等待依赖，直接启动并撤销停止标记
+            for service in &project.services[started..] {
+                let Some(container) = containers.get(&service.name).filter(|c| c.state.as_deref() == Some("running")) else {
+                    continue;
+                };
+                let Some(id) = &container.id else {
+                    continue;
+                };
+                println!("恢复启动项目 {} 的服务 {}", project.name, service.name);
+                if let Err(e) = self.start_service(&project, service, id).await {
+                    eprintln!("❌ 启动服务 {} 失败: {}", service.name, e);
+                    self.expected_stops.lock().unwrap().remove(id);
+                }
+            }
+            return Err(e);
+        }
+        println!("✅ 项目 {} 已重启", project.name);
+        Ok(())
+    }
+
+    /// 用 compose 文件中最新的配置和镜像重建单个服务
+    pub async fn stack_update_service(&self, name: &str, service_name: &str, pull_policy: PullPolicy) -> anyhow::Result<()> {
+        let project = self.stacks.get(name)?.project()?;
+        let service = project.service(service_name)
+            .ok_or_else(|| ApiError::not_found(format!("项目 {} 中没有服务 {}", project.name, service_name)))?;
+        self.stacks.prepare(&project, &self.networks, &self.volumes).await?;
+
+        // 先准备好镜像，拉取失败时保留旧容器
+        self.images.ensure_image(&normalize_image_name(&service.image), pull_policy, None).await?;
+        let containers = self.stacks.containers(&project.name).await?;
+        if let Some(id) = containers.get(service_name).and_then(|c| c.id.clone()) {
+            self.remove_container(&id).await?;
+        }
+        self.wait_for_dependencies(&project, service).await?;
+        self.create_service_container(&project, service, PullPolicy::Missing).await?;
+        println!("✅ 已更新项目 {} 的服务 {}", project.name, service_name);
+        Ok(())
+    }
+
+    pub async fn remove_stack(&self, name: &str, remove_volumes: bool) -> anyhow::Result<()> {
+        self.stack_down(name, remove_volumes).await?;
+        self.stacks.remove(name)?;
+        println!("已删除项目 {}", name);
+        Ok(())
+    }
+
+    async fn create_service_container(&self, project: &ComposeProject, service: &ComposeService, pull_policy: PullPolicy) -> anyhow::Result<String> {
+        let image = normalize_image_name(&service.image);
+        self.images.ensure_image(&image, pull_policy, None).await?;
+
+        let mut config = service.config.clone();
+        config.image = Some(image.clone());
+        println!("正在创建服务 {} 的容器: {}", service.name, service.container_name);
+        let container = self.docker.create_container(
+            Some(CreateContainerOptions {
+                name: service.container_name.clone(),
+                platform: None,
+            }),
+            config,
+        ).await?;
+
+        // 创建容器时只能指定一个网络，其余网络在启动前连接
+        for network in service.networks.iter().skip(1) {
+            self.docker.connect_network(&network.name, ConnectNetworkOptions {
+                container: container.id.clone(),
+                endpoint_config: compose::endpoint_settings(network),
+            }).await?;
+        }
+
+        self.start_service(project, service, &container.id).await?;
+        let inspect = self.docker.inspect_container(&container.id, None).await?;
+        self.update_container_config(&container.id, &inspect, image).await?;
+        Ok(container.id)
+    }
+
+    async fn start_service(&self, project: &ComposeProject, service: &ComposeService, id: &str) -> anyhow::Result<()> {
+        // 被其他服务以 service_completed_successfully 依赖的服务是一次性任务，退出后不自动重启
+        let one_shot = project.services.iter()
+            .flat_map(|s| s.depends_on.iter())
+            .any(|d| d.service == service.name && d.condition == DependencyCondition::ServiceCompletedSuccessfully);
+        if one_shot {
+            self.expected_stops.lock().unwrap().insert(id.to_string());
+        } else {
+            self.expected_stops.lock().unwrap().remove(id);
+        }
+        self.docker.start_container::<String>(id, None).await?;
+        println!("已启动服务 {}", service.name);
+        Ok(())
+    }
+
+    async fn wait_for_dependencies(&self, project: &ComposeProject, service: &ComposeService) -> anyhow::Result<()> {
+        for dependency in &service.depends_on {
+            if dependency.condition == DependencyCondition::ServiceStarted {
+                continue;
+            }
+            let Some(target) = project.service(&dependency.service) else {
+                continue;
+            };
+            println!("服务 {} 等待依赖 {} 就绪", service.name, dependency.service);
+            self.wait_for_condition(&target.container_name, dependency.condition).await
+                .map_err(|e| anyhow::anyhow!("服务 {} 的依赖 {} 未就绪: {}", service.name, dependency.service, e))?;
+        }
+        Ok(())
+    }
+
+    async fn wait_for_condition(&self, container: &str, condition: DependencyCondition) -> anyhow::Result<()> {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(300);
+        loop {
+            let state = self.docker.inspect_container(container, None).await?.state.unwrap_or_default();
+            match condition {
+                DependencyCondition::ServiceStarted => return Ok(()),
+                DependencyCondition::ServiceHealthy => {
+                    let health = state.health.as_ref().and_then(|h| h.status);
+                    match health {
+                        Some(HealthStatusEnum::HEALTHY) => return Ok(()),
+                        Some(HealthStatusEnum::UNHEALTHY) => return Err(anyhow::anyhow!("健康检查失败")),
+                        None | Some(HealthStatusEnum::NONE) | Some(HealthStatusEnum::EMPTY) => {
+                            return Err(anyhow::anyhow!("没有配置健康检查"));
+                        }
+                        Some(HealthStatusEnum::STARTING) => {}
+                    }
+                }
+                DependencyCondition::ServiceCompletedSuccessfully => {
+                    if !state.running.unwrap_or(false) && state.finished_at.as_deref().is_some_and(|f| !f.starts_with("0001-")) {
+                        let code = state.exit_code.unwrap_or_default();
+                        if code == 0 {
+                            return Ok(());
                         }
+                        return Err(anyhow::anyhow!("退出码 {}", code));
                     }
                 }
             }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(anyhow::anyhow!("等待超时"));
+            }
+            tokio::time::sleep(Duration::from_secs(2)).await;
         }
-        None
     }
 
-    async fn update_container_config(&self, container_id: &str, inspect: &bollard::models::ContainerInspectResponse, image: String) -> anyhow::Result<()> {
-        let ip_address = self.get_container_ip(container_id).await;
+    pub async fn get_container_ip(&self, container_id: &str) -> Option<String> {
+        let inspect = self.docker.inspect_container(container_id, None).await.ok()?;
+        ContainerNetwork::primary_ip(&ContainerNetwork::from_inspect(&inspect))
+    }
+
+    /// 根据 inspect 结果构造保存的容器配置
+    fn config_from_inspect(container_id: &str, inspect: &bollard::models::ContainerInspectResponse, image: String) -> ContainerConfig {
         let image_clone = image.clone(); // 克隆 image 以在多处使用
         
-        let config = ContainerConfig {
+        ContainerConfig {
             container_id: container_id.to_string(),
             name: inspect.name.as_ref().unwrap_or(&String::new()).trim_start_matches('/').to_string(),
             image,
@@ -518,10 +2491,18 @@ impl ContainerMonitor {
                 network_disabled: c.network_disabled,
                 mac_address: c.mac_address.clone(),
                 labels: c.labels.clone(),
+                healthcheck: c.healthcheck.clone(),
+                stop_signal: c.stop_signal.clone(),
+                stop_timeout: c.stop_timeout,
                 ..Default::default()
             }),
-            ip_address,
-        };
+            networks: ContainerNetwork::from_inspect(inspect),
+            mounts: MountSpec::from_inspect(inspect),
+        }
+    }
+
+    async fn update_container_config(&self, container_id: &str, inspect: &bollard::models::ContainerInspectResponse, image: String) -> anyhow::Result<()> {
+        let config = Self::config_from_inspect(container_id, inspect, image);
 
         let mut configs = self.container_configs.lock().unwrap();
         configs.insert(container_id.to_string(), config);
@@ -531,6 +2512,49 @@ impl ContainerMonitor {
     }
 }
 
+// Docker 事件接口的 since 参数，格式为 秒.纳秒
+fn event_timestamp(nanos: i64) -> String {
+    format!("{}.{:09}", nanos.div_euclid(1_000_000_000), nanos.rem_euclid(1_000_000_000))
+}
+
+// 重建容器时旧容器的临时名称
+fn backup_name(name: &str) -> String {
+    format!("{}_old_{}", name, Local::now().timestamp())
+}
+
+fn is_backup_name(name: &str, original: &str) -> bool {
+    name.strip_prefix(original)
+        .and_then(|rest| rest.strip_prefix("_old_"))
+        .is_some_and(|ts| !ts.is_empty() && ts.bytes().all(|b| b.is_ascii_digit()))
+}
+
+// 主动停止容器期间的标记：操作中途出错时在 drop 中撤销，成功后调用 keep 保留。
+// 只撤销自己加上的标记，其他操作事先加上的保持不变
+struct ExpectedStop<'a> {
+    stops: &'a Mutex<HashSet<String>>,
+    id: String,
+    inserted: bool,
+}
+
+impl<'a> ExpectedStop<'a> {
+    fn new(stops: &'a Mutex<HashSet<String>>, id: &str) -> Self {
+        let inserted = stops.lock().unwrap().insert(id.to_string());
+        Self { stops, id: id.to_string(), inserted }
+    }
+
+    fn keep(mut self) {
+        self.inserted = false;
+    }
+}
+
+impl Drop for ExpectedStop<'_> {
+    fn drop(&mut self) {
+        if self.inserted {
+            self.stops.lock().unwrap().remove(&self.id);
+        }
+    }
+}
+
 #[derive(Clone)]
 struct RestartRecord {
     last_restart: SystemTime,
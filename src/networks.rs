@@ -0,0 +1,238 @@
+use bollard::Docker;
+use bollard::models::{EndpointIpamConfig, EndpointSettings, Ipam, IpamConfig};
+use bollard::network::{
+    ConnectNetworkOptions, CreateNetworkOptions, DisconnectNetworkOptions, InspectNetworkOptions,
+    ListNetworksOptions,
+};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use anyhow::Result;
+
+/// 网络列表中的单个网络
+#[derive(Clone, Debug, Serialize)]
+pub struct NetworkInfo {
+    pub id: String,
+    pub name: String,
+    pub driver: String,
+    pub scope: String,
+    pub internal: bool,
+    pub attachable: bool,
+    pub enable_ipv6: bool,
+    pub subnets: Vec<NetworkSubnet>,
+    pub labels: HashMap<String, String>,
+    pub containers: Vec<NetworkMember>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NetworkSubnet {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subnet: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_range: Option<String>,
+}
+
+/// 连接在网络上的容器
+#[derive(Clone, Debug, Serialize)]
+pub struct NetworkMember {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipv4_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipv6_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mac_address: Option<String>,
+    pub managed: bool,
+}
+
+/// 创建网络的参数
+#[derive(Clone, Debug, Deserialize)]
+pub struct NetworkSpec {
+    pub name: String,
+    #[serde(default = "default_driver")]
+    pub driver: String,
+    #[serde(default)]
+    pub subnet: Option<String>,
+    #[serde(default)]
+    pub gateway: Option<String>,
+    #[serde(default)]
+    pub ip_range: Option<String>,
+    #[serde(default)]
+    pub internal: bool,
+    #[serde(default)]
+    pub attachable: bool,
+    #[serde(default)]
+    pub enable_ipv6: bool,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+}
+
+fn default_driver() -> String {
+    "bridge".to_string()
+}
+
+/// 把容器连接到网络时的参数
+#[derive(Clone, Debug, Deserialize)]
+pub struct NetworkAttachment {
+    pub network: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub ipv4_address: Option<String>,
+    #[serde(default)]
+    pub ipv6_address: Option<String>,
+}
+
+pub struct NetworkManager {
+    docker: Docker,
+}
+
+impl NetworkManager {
+    pub fn new(docker: Docker) -> Self {
+        Self { docker }
+    }
+
+    pub async fn list_networks(&self, managed: &HashSet<String>) -> Result<Vec<NetworkInfo>> {
+        let networks = self.docker.list_networks(None::<ListNetworksOptions<String>>).await?;
+        let mut result = Vec::new();
+        for network in networks {
+            let Some(id) = network.id.clone() else {
+                continue;
+            };
+            // 列表接口不返回容器，需要逐个 inspect
+            let network = self.docker
+                .inspect_network(&id, None::<InspectNetworkOptions<String>>)
+                .await
+                .unwrap_or(network);
+            result.push(Self::to_info(network, managed));
+        }
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+
+    pub async fn inspect_network(&self, id: &str, managed: &HashSet<String>) -> Result<NetworkInfo> {
+        let network = self.docker.inspect_network(id, None::<InspectNetworkOptions<String>>).await?;
+        Ok(Self::to_info(network, managed))
+    }
+
+    pub async fn create_network(&self, spec: &NetworkSpec) -> Result<String> {
+        if spec.name.is_empty() {
+            return Err(anyhow::anyhow!("网络名称不能为空"));
+        }
+        let ipam_config = if spec.subnet.is_some() || spec.gateway.is_some() || spec.ip_range.is_some() {
+            Some(vec![IpamConfig {
+                subnet: spec.subnet.clone(),
+                gateway: spec.gateway.clone(),
+                ip_range: spec.ip_range.clone(),
+                ..Default::default()
+            }])
+        } else {
+            None
+        };
+
+        let response = self.docker.create_network(CreateNetworkOptions {
+            name: spec.name.clone(),
+            check_duplicate: true,
+            driver: spec.driver.clone(),
+            internal: spec.internal,
+            attachable: spec.attachable,
+            enable_ipv6: spec.enable_ipv6,
+            ipam: Ipam {
+                driver: Some("default".to_string()),
+                config: ipam_config,
+                ..Default::default()
+            },
+            labels: spec.labels.clone(),
+            options: spec.options.clone(),
+            ..Default::default()
+        }).await?;
+
+        let id = response.id.unwrap_or_default();
+        println!("已创建网络 {} ({})", spec.name, id);
+        Ok(id)
+    }
+
+    pub async fn remove_network(&self, id: &str) -> Result<()> {
+        self.docker.remove_network(id).await?;
+        println!("已删除网络: {}", id);
+        Ok(())
+    }
+
+    pub async fn connect(&self, container_id: &str, attachment: &NetworkAttachment) -> Result<()> {
+        let ipam_config = if attachment.ipv4_address.is_some() || attachment.ipv6_address.is_some() {
+            Some(EndpointIpamConfig {
+                ipv4_address: attachment.ipv4_address.clone(),
+                ipv6_address: attachment.ipv6_address.clone(),
+                ..Default::default()
+            })
+        } else {
+            None
+        };
+
+        self.docker.connect_network(&attachment.network, ConnectNetworkOptions {
+            container: container_id,
+            endpoint_config: EndpointSettings {
+                aliases: if attachment.aliases.is_empty() { None } else { Some(attachment.aliases.clone()) },
+                ipam_config,
+                ..Default::default()
+            },
+        }).await?;
+        println!("已将容器 {} 连接到网络 {}", container_id, attachment.network);
+        Ok(())
+    }
+
+    pub async fn disconnect(&self, container_id: &str, network: &str, force: bool) -> Result<()> {
+        self.docker.disconnect_network(network, DisconnectNetworkOptions {
+            container: container_id,
+            force,
+        }).await?;
+        println!("已将容器 {} 从网络 {} 断开", container_id, network);
+        Ok(())
+    }
+
+    fn to_info(network: bollard::models::Network, managed: &HashSet<String>) -> NetworkInfo {
+        let subnets = network.ipam.as_ref()
+            .and_then(|ipam| ipam.config.as_ref())
+            .map(|configs| {
+                configs.iter()
+                    .map(|c| NetworkSubnet {
+                        subnet: c.subnet.clone(),
+                        gateway: c.gateway.clone(),
+                        ip_range: c.ip_range.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+        let mut containers: Vec<NetworkMember> = network.containers.unwrap_or_default()
+            .into_iter()
+            .map(|(id, member)| NetworkMember {
+                managed: managed.contains(&id),
+                id,
+                name: member.name.unwrap_or_default(),
+                ipv4_address: non_empty(member.ipv4_address),
+                ipv6_address: non_empty(member.ipv6_address),
+                mac_address: non_empty(member.mac_address),
+            })
+            .collect();
+        containers.sort_by(|a, b| a.name.cmp(&b.name));
+
+        NetworkInfo {
+            id: network.id.unwrap_or_default(),
+            name: network.name.unwrap_or_default(),
+            driver: network.driver.unwrap_or_default(),
+            scope: network.scope.unwrap_or_default(),
+            internal: network.internal.unwrap_or(false),
+            attachable: network.attachable.unwrap_or(false),
+            enable_ipv6: network.enable_ipv6.unwrap_or(false),
+            subnets,
+            labels: network.labels.unwrap_or_default(),
+            containers,
+        }
+    }
+}
@@ -0,0 +1,695 @@
+use chrono::{DateTime, Local};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use crate::storage;
+
+pub(crate) const SETTINGS_FILE: &str = "notifications.json";
+const HISTORY_LIMIT: usize = 200;
+const DEFAULT_TEMPLATE: &str = "[{{event}}] {{container}}: {{message}}";
+// 读取设置时代替通道地址、请求头和邮箱密码，保存时收到该值表示沿用原来的值
+const MASKED: &str = "******";
+const RATE_WINDOW: Duration = Duration::from_secs(3600);
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 通知的事件类型
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// 容器意外退出并已自动重启
+    Crash,
+    /// 短时间内反复崩溃，恢复前不再发送该容器的崩溃通知
+    CrashLoop,
+    /// 自动重启失败
+    RestartFailed,
+    /// 依赖恢复后级联重启
+    Restart,
+    /// 反复崩溃或重启失败的容器已经恢复运行
+    Recovery,
+    /// 健康检查持续失败，已重启
+    Unhealthy,
+    /// 存活探测连续失败，已重启
+    ProbeFailed,
+    /// 因内存不足被终止
+    Oom,
+    UpdateAvailable,
+    Updated,
+    UpdateFailed,
+    /// 手动发送的测试通知
+    Test,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Crash => "crash",
+            Self::CrashLoop => "crash_loop",
+            Self::RestartFailed => "restart_failed",
+            Self::Restart => "restart",
+            Self::Recovery => "recovery",
+            Self::Unhealthy => "unhealthy",
+            Self::ProbeFailed => "probe_failed",
+            Self::Oom => "oom",
+            Self::UpdateAvailable => "update_available",
+            Self::Updated => "updated",
+            Self::UpdateFailed => "update_failed",
+            Self::Test => "test",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Crash => "容器崩溃",
+            Self::CrashLoop => "容器反复崩溃",
+            Self::RestartFailed => "重启失败",
+            Self::Restart => "级联重启",
+            Self::Recovery => "已恢复",
+            Self::Unhealthy => "健康检查失败",
+            Self::ProbeFailed => "存活探测失败",
+            Self::Oom => "内存不足",
+            Self::UpdateAvailable => "有可用更新",
+            Self::Updated => "已更新",
+            Self::UpdateFailed => "更新失败",
+            Self::Test => "测试通知",
+        }
+    }
+
+    // 计入反复崩溃判断的事件，都会导致一次自动重启
+    fn is_failure(&self) -> bool {
+        matches!(self, Self::Crash | Self::Oom | Self::Unhealthy | Self::ProbeFailed)
+    }
+}
+
+/// 一条待发送的通知
+#[derive(Clone, Debug, Serialize)]
+pub struct Notification {
+    pub event: EventKind,
+    pub container_id: String,
+    pub container: String,
+    pub message: String,
+    /// 事件的原始记录，如更新记录或 OOM 处理记录
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+    pub timestamp: DateTime<Local>,
+}
+
+impl Notification {
+    pub fn new(event: EventKind, container_id: impl Into<String>, container: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            event,
+            container_id: container_id.into(),
+            container: container.into(),
+            message: message.into(),
+            details: None,
+            timestamp: Local::now(),
+        }
+    }
+
+    pub fn with_details<T: Serialize>(mut self, details: &T) -> Self {
+        self.details = serde_json::to_value(details).ok();
+        self
+    }
+
+    /// 替换模板中的 `{{event}}`、`{{event_type}}`、`{{container}}`、`{{container_id}}`、`{{message}}` 和 `{{time}}`
+    pub fn render(&self, template: &str) -> String {
+        let short_id: String = self.container_id.chars().take(12).collect();
+        template
+            .replace("{{event}}", self.event.title())
+            .replace("{{event_type}}", self.event.as_str())
+            .replace("{{container}}", &self.container)
+            .replace("{{container_id}}", &short_id)
+            .replace("{{message}}", &self.message)
+            .replace("{{time}}", &self.timestamp.format("%Y-%m-%d %H:%M:%S").to_string())
+    }
+}
+
+/// 通知设置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifySettings {
+    pub channels: Vec<Channel>,
+    /// 同一容器的同类事件在该时间内（秒）只通知一次
+    pub dedup_window: u64,
+    /// 每个通道每小时最多发送的通知数，0 表示不限制
+    pub rate_limit: u32,
+    /// 在 `crash_loop_window` 秒内失败达到该次数视为反复崩溃，0 表示不检测
+    pub crash_loop_threshold: u32,
+    pub crash_loop_window: u64,
+}
+
+impl Default for NotifySettings {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            dedup_window: 300,
+            rate_limit: 30,
+            crash_loop_threshold: 3,
+            crash_loop_window: 600,
+        }
+    }
+}
+
+/// 通知通道
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Channel {
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub target: ChannelTarget,
+    /// 只发送这些类型的事件，为空表示全部
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<EventKind>,
+    /// 消息模板，为空时使用 `[{{event}}] {{container}}: {{message}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Channel {
+    fn accepts(&self, event: EventKind) -> bool {
+        self.enabled && (self.events.is_empty() || self.events.contains(&event))
+    }
+
+    fn render(&self, notification: &Notification) -> String {
+        notification.render(self.template.as_deref().unwrap_or(DEFAULT_TEMPLATE))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ChannelTarget {
+    /// 以 JSON 发送完整的通知，附带渲染后的 `text`
+    Webhook {
+        url: String,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        headers: HashMap<String, String>,
+    },
+    /// Slack 兼容的 Incoming Webhook，也适用于 Mattermost 和 Rocket.Chat
+    Slack { url: String },
+    Discord { url: String },
+    /// 飞书自定义机器人
+    Feishu { url: String },
+    /// 钉钉自定义机器人
+    Dingtalk { url: String },
+    Email {
+        smtp_host: String,
+        #[serde(default = "default_smtp_port")]
+        smtp_port: u16,
+        #[serde(default)]
+        security: SmtpSecurity,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        username: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+}
+
+impl ChannelTarget {
+    // 通道地址中通常带有令牌，和请求头、邮箱密码一样不在接口中返回
+    fn mask(&mut self) {
+        match self {
+            Self::Webhook { url, headers } => {
+                *url = MASKED.to_string();
+                headers.values_mut().for_each(|v| *v = MASKED.to_string());
+            }
+            Self::Slack { url } | Self::Discord { url } | Self::Feishu { url } | Self::Dingtalk { url } => {
+                *url = MASKED.to_string();
+            }
+            Self::Email { password: Some(password), .. } => *password = MASKED.to_string(),
+            Self::Email { .. } => {}
+        }
+    }
+
+    // 把提交的 MASKED 换回同名通道原来的值，类型不同或没有原值时保持不变，由校验报错
+    fn unmask(&mut self, previous: Option<&ChannelTarget>) {
+        match (self, previous) {
+            (Self::Webhook { url, headers }, Some(Self::Webhook { url: old_url, headers: old_headers })) => {
+                if url == MASKED {
+                    *url = old_url.clone();
+                }
+                for (key, value) in headers.iter_mut() {
+                    if value == MASKED {
+                        if let Some(old) = old_headers.get(key) {
+                            *value = old.clone();
+                        }
+                    }
+                }
+            }
+            (Self::Slack { url }, Some(Self::Slack { url: old }))
+            | (Self::Discord { url }, Some(Self::Discord { url: old }))
+            | (Self::Feishu { url }, Some(Self::Feishu { url: old }))
+            | (Self::Dingtalk { url }, Some(Self::Dingtalk { url: old })) if url == MASKED => {
+                *url = old.clone();
+            }
+            (Self::Email { password, .. }, previous) if password.as_deref() == Some(MASKED) => {
+                *password = match previous {
+                    Some(Self::Email { password, .. }) => password.clone(),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// 明文连接后升级为 TLS
+    #[default]
+    Starttls,
+    /// 直接使用 TLS，通常是 465 端口
+    Tls,
+    None,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Sent,
+    Failed,
+    /// 与最近发送过的通知重复
+    Deduplicated,
+    /// 超过通道的每小时发送上限
+    RateLimited,
+    /// 容器处于反复崩溃状态，已经发送过一次
+    Suppressed,
+}
+
+/// 一条通知的发送记录
+#[derive(Clone, Debug, Serialize)]
+pub struct NotificationRecord {
+    pub event: EventKind,
+    pub container: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    pub status: DeliveryStatus,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub timestamp: DateTime<Local>,
+}
+
+#[derive(Default)]
+struct DeliveryState {
+    // 每个事件类型和容器最近一次通知的时间
+    last_sent: HashMap<(EventKind, String), Instant>,
+    // 每个容器最近的失败时间
+    failures: HashMap<String, VecDeque<Instant>>,
+    // 尚未恢复的容器及其事件（反复崩溃或重启失败）
+    incidents: HashMap<String, EventKind>,
+    // 每个通道最近一小时的发送时间
+    sent: HashMap<String, VecDeque<Instant>>,
+    // 每个通道因频率限制没有发送的通知数
+    dropped: HashMap<String, u32>,
+}
+
+/// 按事件类型把通知分发到各个通道，并负责去重、合并反复崩溃和限制发送频率
+pub struct NotificationCenter {
+    settings: Mutex<NotifySettings>,
+    state: Mutex<DeliveryState>,
+    history: Arc<Mutex<VecDeque<NotificationRecord>>>,
+}
+
+impl NotificationCenter {
+    pub fn load() -> Result<Self> {
+        let settings = if storage::data_path(SETTINGS_FILE).exists() {
+            storage::load_json(SETTINGS_FILE)?
+        } else {
+            let settings = Self::migrate();
+            if !settings.channels.is_empty() {
+                storage::save_json(SETTINGS_FILE, &settings)?;
+                println!("已将原有的 Webhook 设置迁移为 {} 个通知通道", settings.channels.len());
+            }
+            settings
+        };
+        Ok(Self {
+            settings: Mutex::new(settings),
+            state: Mutex::new(DeliveryState::default()),
+            history: Arc::new(Mutex::new(VecDeque::new())),
+        })
+    }
+
+    // 早期版本在自动更新和 OOM 设置中各有一个 webhook_url，迁移为只接收对应事件的通道
+    fn migrate() -> NotifySettings {
+        let legacy: [(&str, &str, &[EventKind]); 2] = [
+            ("updater.json", "updates", &[EventKind::UpdateAvailable, EventKind::Updated, EventKind::UpdateFailed]),
+            ("oom.json", "oom", &[EventKind::Oom, EventKind::CrashLoop, EventKind::Recovery]),
+        ];
+        let mut settings = NotifySettings::default();
+        for (file, name, events) in legacy {
+            let value: serde_json::Value = storage::load_json(file).unwrap_or_default();
+            if let Some(url) = value.get("webhook_url").and_then(|u| u.as_str()).filter(|u| !u.is_empty()) {
+                settings.channels.push(Channel {
+                    name: name.to_string(),
+                    enabled: true,
+                    target: ChannelTarget::Webhook { url: url.to_string(), headers: HashMap::new() },
+                    events: events.to_vec(),
+                    template: None,
+                });
+            }
+        }
+        settings
+    }
+
+    pub fn settings(&self) -> NotifySettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    /// 隐藏通道地址、请求头和邮箱密码后的设置，用于接口返回
+    pub fn masked_settings(&self) -> NotifySettings {
+        let mut settings = self.settings();
+        for channel in &mut settings.channels {
+            channel.target.mask();
+        }
+        settings
+    }
+
+    pub fn update_settings(&self, mut settings: NotifySettings) -> Result<()> {
+        let current = self.settings();
+        for channel in &mut settings.channels {
+            let previous = current.channels.iter().find(|c| c.name == channel.name).map(|c| &c.target);
+            channel.target.unmask(previous);
+        }
+        Self::validate(&settings)?;
+        storage::save_json(SETTINGS_FILE, &settings)?;
+        *self.settings.lock().unwrap() = settings;
+        println!("已更新通知设置");
+        Ok(())
+    }
+
+    fn validate(settings: &NotifySettings) -> Result<()> {
+        if settings.crash_loop_threshold > 0 && settings.crash_loop_window == 0 {
+            return Err(anyhow::anyhow!("反复崩溃的统计时间必须大于 0"));
+        }
+        let mut names = HashSet::new();
+        for channel in &settings.channels {
+            if channel.name.trim().is_empty() {
+                return Err(anyhow::anyhow!("通道名称不能为空"));
+            }
+            if !names.insert(channel.name.as_str()) {
+                return Err(anyhow::anyhow!("通道名称重复: {}", channel.name));
+            }
+            match &channel.target {
+                ChannelTarget::Webhook { url, .. }
+                | ChannelTarget::Slack { url }
+                | ChannelTarget::Discord { url }
+                | ChannelTarget::Feishu { url }
+                | ChannelTarget::Dingtalk { url } => {
+                    if !url.starts_with("http://") && !url.starts_with("https://") {
+                        return Err(anyhow::anyhow!("通道 {} 的地址必须以 http:// 或 https:// 开头", channel.name));
+                    }
+                }
+                ChannelTarget::Email { smtp_host, from, to, .. } => {
+                    if smtp_host.is_empty() {
+                        return Err(anyhow::anyhow!("通道 {} 缺少 SMTP 服务器", channel.name));
+                    }
+                    if to.is_empty() {
+                        return Err(anyhow::anyhow!("通道 {} 缺少收件人", channel.name));
+                    }
+                    for address in std::iter::once(from).chain(to) {
+                        address.parse::<Mailbox>()
+                            .map_err(|e| anyhow::anyhow!("通道 {} 的邮箱地址 {} 无效: {}", channel.name, address, e))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn history(&self) -> Vec<NotificationRecord> {
+        self.history.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    /// 发送通知；同一事件在去重时间内、反复崩溃期间或超过频率限制时不会重复发送
+    pub fn notify(&self, mut notification: Notification) {
+        let settings = self.settings();
+        let now = Instant::now();
+        if let Some(status) = self.screen(&mut notification, &settings, now) {
+            self.record(&notification, None, status, None);
+            return;
+        }
+
+        for channel in settings.channels.iter().filter(|c| c.accepts(notification.event)) {
+            let dropped = {
+                let mut state = self.state.lock().unwrap();
+                let state = &mut *state;
+                let sent = state.sent.entry(channel.name.clone()).or_default();
+                while sent.front().is_some_and(|t| now.duration_since(*t) >= RATE_WINDOW) {
+                    sent.pop_front();
+                }
+                if settings.rate_limit > 0 && sent.len() >= settings.rate_limit as usize {
+                    *state.dropped.entry(channel.name.clone()).or_default() += 1;
+                    None
+                } else {
+                    sent.push_back(now);
+                    Some(state.dropped.remove(&channel.name).unwrap_or(0))
+                }
+            };
+            let Some(dropped) = dropped else {
+                self.record(&notification, Some(&channel.name), DeliveryStatus::RateLimited, None);
+                continue;
+            };
+
+            let mut text = channel.render(&notification);
+            if dropped > 0 {
+                text.push_str(&format!("\n（另有 {} 条通知因超过发送频率限制未发送）", dropped));
+            }
+            let channel = channel.clone();
+            let notification = notification.clone();
+            let history = self.history.clone();
+            // 发送可能很慢，不能阻塞事件处理
+            tokio::spawn(async move {
+                let (status, error) = match deliver(&channel, &notification, &text).await {
+                    Ok(()) => (DeliveryStatus::Sent, None),
+                    Err(e) => {
+                        eprintln!("发送通知到 {} 失败: {}", channel.name, e);
+                        (DeliveryStatus::Failed, Some(e.to_string()))
+                    }
+                };
+                push_record(&history, NotificationRecord {
+                    event: notification.event,
+                    container: notification.container,
+                    channel: Some(channel.name),
+                    status,
+                    message: text,
+                    error,
+                    timestamp: Local::now(),
+                });
+            });
+        }
+    }
+
+    // 合并反复崩溃并去重，不需要发送时返回原因
+    fn screen(&self, notification: &mut Notification, settings: &NotifySettings, now: Instant) -> Option<DeliveryStatus> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let container = notification.container.clone();
+
+        if notification.event.is_failure() {
+            let window = Duration::from_secs(settings.crash_loop_window);
+            let failures = state.failures.entry(container.clone()).or_default();
+            failures.push_back(now);
+            while failures.front().is_some_and(|t| now.duration_since(*t) > window) {
+                failures.pop_front();
+            }
+            let count = failures.len();
+            if state.incidents.get(&container) == Some(&EventKind::CrashLoop) {
+                return Some(DeliveryStatus::Suppressed);
+            }
+            if settings.crash_loop_threshold > 0 && count >= settings.crash_loop_threshold as usize {
+                state.incidents.insert(container.clone(), EventKind::CrashLoop);
+                notification.event = EventKind::CrashLoop;
+                notification.message = format!(
+                    "{} 秒内失败 {} 次，恢复前不再通知: {}",
+                    settings.crash_loop_window, count, notification.message,
+                );
+            }
+        }
+        match notification.event {
+            EventKind::RestartFailed => {
+                state.incidents.entry(container.clone()).or_insert(EventKind::RestartFailed);
+            }
+            // 恢复后同一容器的新故障需要重新通知
+            EventKind::Recovery => {
+                state.incidents.remove(&container);
+                state.failures.remove(&container);
+                state.last_sent.retain(|(_, c), _| *c != container);
+            }
+            _ => {}
+        }
+
+        let window = Duration::from_secs(settings.dedup_window);
+        state.last_sent.retain(|_, t| now.duration_since(*t) < window);
+        let key = (notification.event, container);
+        if state.last_sent.contains_key(&key) {
+            return Some(DeliveryStatus::Deduplicated);
+        }
+        state.last_sent.insert(key, now);
+        None
+    }
+
+    /// 立即向指定通道发送一条测试通知，不受去重和频率限制
+    pub async fn send_test(&self, channel: &Channel) -> Result<()> {
+        let notification = Notification::new(EventKind::Test, "", "docker-manager", "这是一条测试通知");
+        let text = channel.render(&notification);
+        let result = deliver(channel, &notification, &text).await;
+        self.record(
+            &notification,
+            Some(&channel.name),
+            if result.is_ok() { DeliveryStatus::Sent } else { DeliveryStatus::Failed },
+            result.as_ref().err().map(|e| e.to_string()),
+        );
+        result
+    }
+
+    pub fn channel(&self, name: &str) -> Option<Channel> {
+        self.settings.lock().unwrap().channels.iter().find(|c| c.name == name).cloned()
+    }
+
+    /// 最近一段时间没有再失败、可以检查是否已经恢复的容器
+    pub fn quiet_incidents(&self) -> Vec<String> {
+        let window = Duration::from_secs(self.settings().crash_loop_window);
+        let state = self.state.lock().unwrap();
+        state.incidents.keys()
+            .filter(|container| state.failures.get(*container)
+                .and_then(|f| f.back())
+                .is_none_or(|t| t.elapsed() > window))
+            .cloned()
+            .collect()
+    }
+
+    /// 容器已被删除，不再等待它恢复
+    pub fn resolve(&self, container: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.incidents.remove(container);
+        state.failures.remove(container);
+    }
+
+    fn record(&self, notification: &Notification, channel: Option<&str>, status: DeliveryStatus, error: Option<String>) {
+        push_record(&self.history, NotificationRecord {
+            event: notification.event,
+            container: notification.container.clone(),
+            channel: channel.map(str::to_string),
+            status,
+            message: notification.message.clone(),
+            error,
+            timestamp: Local::now(),
+        });
+    }
+}
+
+fn push_record(history: &Mutex<VecDeque<NotificationRecord>>, record: NotificationRecord) {
+    let mut history = history.lock().unwrap();
+    history.push_back(record);
+    while history.len() > HISTORY_LIMIT {
+        history.pop_front();
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    #[serde(flatten)]
+    notification: &'a Notification,
+    text: &'a str,
+}
+
+async fn deliver(channel: &Channel, notification: &Notification, text: &str) -> Result<()> {
+    match &channel.target {
+        ChannelTarget::Webhook { url, headers } => {
+            post_json(url, headers, &WebhookPayload { notification, text }).await?;
+        }
+        ChannelTarget::Slack { url } => {
+            post_json(url, &HashMap::new(), &serde_json::json!({ "text": text })).await?;
+        }
+        ChannelTarget::Discord { url } => {
+            post_json(url, &HashMap::new(), &serde_json::json!({ "content": text })).await?;
+        }
+        // 飞书和钉钉出错时也返回 200，需要检查响应中的错误码
+        ChannelTarget::Feishu { url } => {
+            let payload = serde_json::json!({ "msg_type": "text", "content": { "text": text } });
+            let response = post_json(url, &HashMap::new(), &payload).await?;
+            check_error_code(&response, "code", "msg")?;
+        }
+        ChannelTarget::Dingtalk { url } => {
+            let payload = serde_json::json!({ "msgtype": "text", "text": { "content": text } });
+            let response = post_json(url, &HashMap::new(), &payload).await?;
+            check_error_code(&response, "errcode", "errmsg")?;
+        }
+        ChannelTarget::Email { smtp_host, smtp_port, security, username, password, from, to } => {
+            let mut builder = match security {
+                SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp_host)?,
+                SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(smtp_host)?,
+                SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(smtp_host),
+            }
+            .port(*smtp_port)
+            .timeout(Some(SEND_TIMEOUT));
+            if let Some(username) = username {
+                builder = builder.credentials(Credentials::new(username.clone(), password.clone().unwrap_or_default()));
+            }
+
+            let mut message = Message::builder()
+                .from(from.parse()?)
+                .subject(format!("[docker-manager] {} {}", notification.event.title(), notification.container));
+            for address in to {
+                message = message.to(address.parse()?);
+            }
+            let mut body = text.to_string();
+            if let Some(details) = &notification.details {
+                body.push_str("\n\n");
+                body.push_str(&serde_json::to_string_pretty(details)?);
+            }
+            let message = message.header(ContentType::TEXT_PLAIN).body(body)?;
+            builder.build().send(message).await?;
+        }
+    }
+    Ok(())
+}
+
+// 向 Webhook 地址发送 JSON，返回响应内容
+async fn post_json<T: Serialize>(url: &str, headers: &HashMap<String, String>, payload: &T) -> Result<String> {
+    let client = reqwest::Client::builder()
+        .timeout(SEND_TIMEOUT)
+        .build()?;
+    let mut request = client.post(url).json(payload);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let response = request.send().await?;
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(anyhow::anyhow!("Webhook 返回状态码 {}: {}", status, body.trim()));
+    }
+    Ok(body)
+}
+
+fn check_error_code(body: &str, code_field: &str, message_field: &str) -> Result<()> {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        return Ok(());
+    };
+    match value.get(code_field).and_then(|c| c.as_i64()) {
+        Some(code) if code != 0 => Err(anyhow::anyhow!(
+            "返回错误码 {}: {}",
+            code,
+            value.get(message_field).and_then(|m| m.as_str()).unwrap_or_default(),
+        )),
+        _ => Ok(()),
+    }
+}
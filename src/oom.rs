@@ -0,0 +1,129 @@
+use chrono::{DateTime, Local};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use anyhow::Result;
+use crate::models::parse_size;
+use crate::storage;
+
+pub(crate) const SETTINGS_FILE: &str = "oom.json";
+const HISTORY_LIMIT: usize = 200;
+const MIB: i64 = 1024 * 1024;
+
+/// 覆盖全局设置：`true` 或 `false` 决定该容器 OOM 后是否提高内存限制
+pub const LABEL_AUTO_BUMP: &str = "docker-manager.oom.auto-bump";
+
+/// 内存不足（OOM）处理设置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OomSettings {
+    /// 容器因 OOM 退出后，按倍数提高内存限制并重建容器
+    pub auto_bump: bool,
+    /// 每次提高的倍数
+    pub factor: f64,
+    /// 内存限制的上限，如 `4g`
+    pub max_memory: String,
+}
+
+impl Default for OomSettings {
+    fn default() -> Self {
+        Self {
+            auto_bump: false,
+            factor: 1.5,
+            max_memory: "4g".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OomAction {
+    /// 按原来的限制重启
+    Restarted,
+    /// 提高内存限制后重建
+    Bumped,
+    /// 已经达到上限，按原来的限制重启
+    CeilingReached,
+    Failed,
+}
+
+/// 一次 OOM 的处理记录
+#[derive(Clone, Debug, Serialize)]
+pub struct OomRecord {
+    pub container_id: String,
+    pub name: String,
+    /// OOM 时的内存限制（字节），0 表示没有限制
+    pub memory: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_memory: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_container_id: Option<String>,
+    pub action: OomAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub timestamp: DateTime<Local>,
+}
+
+pub struct OomTracker {
+    settings: Mutex<OomSettings>,
+    history: Mutex<VecDeque<OomRecord>>,
+}
+
+impl OomTracker {
+    pub fn load() -> Result<Self> {
+        let settings: OomSettings = storage::load_json(SETTINGS_FILE)?;
+        Ok(Self {
+            settings: Mutex::new(settings),
+            history: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    pub fn settings(&self) -> OomSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update_settings(&self, settings: OomSettings) -> Result<()> {
+        if settings.factor <= 1.0 {
+            return Err(anyhow::anyhow!("倍数必须大于 1"));
+        }
+        parse_size(&settings.max_memory)
+            .map_err(|e| anyhow::anyhow!("无效的内存上限 {}: {}", settings.max_memory, e))?;
+        storage::save_json(SETTINGS_FILE, &settings)?;
+        *self.settings.lock().unwrap() = settings;
+        println!("已更新 OOM 处理设置");
+        Ok(())
+    }
+
+    pub fn history(&self) -> Vec<OomRecord> {
+        self.history.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    pub(crate) fn record(&self, record: OomRecord) {
+        let mut history = self.history.lock().unwrap();
+        history.push_back(record);
+        while history.len() > HISTORY_LIMIT {
+            history.pop_front();
+        }
+    }
+
+    /// 是否为该容器提高内存限制，容器标签优先于全局设置
+    pub fn auto_bump_for(&self, labels: Option<&HashMap<String, String>>) -> bool {
+        match labels.and_then(|l| l.get(LABEL_AUTO_BUMP)).map(|v| v.to_ascii_lowercase()) {
+            Some(value) if value == "true" || value == "1" => true,
+            Some(value) if value == "false" || value == "0" => false,
+            _ => self.settings().auto_bump,
+        }
+    }
+
+    /// 计算新的内存限制，按 MiB 向上取整；已经达到上限时返回 None
+    pub fn next_limit(&self, memory: i64) -> Option<i64> {
+        let settings = self.settings();
+        let ceiling = parse_size(&settings.max_memory).ok()?;
+        if memory <= 0 || memory >= ceiling {
+            return None;
+        }
+        let bumped = (memory as f64 * settings.factor).ceil() as i64;
+        let bumped = (bumped + MIB - 1) / MIB * MIB;
+        Some(bumped.min(ceiling))
+    }
+}
@@ -0,0 +1,351 @@
+use bollard::Docker;
+use bollard::exec::{CreateExecOptions, StartExecResults};
+use chrono::{DateTime, Local};
+use futures::StreamExt;
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use anyhow::Result;
+use crate::storage;
+use crate::error::ApiError;
+
+pub(crate) const PROBES_FILE: &str = "probes.json";
+// 错误信息中保留的响应或输出长度
+const MAX_OUTPUT: usize = 200;
+// 匹配 body_regex 时最多读取的响应长度
+const MAX_BODY: usize = 64 * 1024;
+
+/// 探测方式
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ProbeCheck {
+    /// 对容器 IP 发送 HTTP GET
+    Http {
+        port: u16,
+        #[serde(default = "default_path")]
+        path: String,
+        /// 期望的状态码，为空时接受任意 2xx
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expected_status: Option<u16>,
+        /// 响应内容需要匹配的正则表达式
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        body_regex: Option<String>,
+    },
+    /// 能建立 TCP 连接即为成功
+    Tcp { port: u16 },
+    /// 在容器内执行命令，退出码为 0 即为成功
+    Exec { command: Vec<String> },
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+/// 由管理器执行的存活探测，按容器名保存，容器重建后仍然有效
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProbeSpec {
+    #[serde(flatten)]
+    pub check: ProbeCheck,
+    /// 探测间隔（秒）
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    /// 单次探测超时（秒）
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// 连续失败多少次后重启容器
+    #[serde(default = "default_threshold")]
+    pub failure_threshold: u32,
+    /// 容器启动后等待多久才开始探测（秒）
+    #[serde(default)]
+    pub initial_delay: u64,
+}
+
+fn default_interval() -> u64 {
+    30
+}
+
+fn default_timeout() -> u64 {
+    5
+}
+
+fn default_threshold() -> u32 {
+    3
+}
+
+/// 探测的最近结果
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ProbeStatus {
+    pub consecutive_failures: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_checked: Option<DateTime<Local>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// 因探测失败而重启的次数
+    pub restarts: u32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProbeInfo {
+    pub container: String,
+    pub probe: ProbeSpec,
+    pub status: ProbeStatus,
+}
+
+pub struct ProbeManager {
+    docker: Docker,
+    specs: Mutex<HashMap<String, ProbeSpec>>,
+    status: Mutex<HashMap<String, ProbeStatus>>,
+    next_run: Mutex<HashMap<String, Instant>>,
+}
+
+impl ProbeManager {
+    pub fn load(docker: Docker) -> Result<Self> {
+        let specs: HashMap<String, ProbeSpec> = storage::load_json(PROBES_FILE)?;
+        Ok(Self {
+            docker,
+            specs: Mutex::new(specs),
+            status: Mutex::new(HashMap::new()),
+            next_run: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn list(&self) -> Vec<ProbeInfo> {
+        let specs = self.specs.lock().unwrap().clone();
+        let status = self.status.lock().unwrap();
+        let mut result: Vec<ProbeInfo> = specs.into_iter()
+            .map(|(container, probe)| ProbeInfo {
+                status: status.get(&container).cloned().unwrap_or_default(),
+                container,
+                probe,
+            })
+            .collect();
+        result.sort_by(|a, b| a.container.cmp(&b.container));
+        result
+    }
+
+    pub fn get(&self, container: &str) -> Option<ProbeInfo> {
+        let probe = self.specs.lock().unwrap().get(container).cloned()?;
+        Some(ProbeInfo {
+            container: container.to_string(),
+            probe,
+            status: self.status.lock().unwrap().get(container).cloned().unwrap_or_default(),
+        })
+    }
+
+    pub fn set(&self, container: &str, spec: ProbeSpec) -> Result<()> {
+        Self::validate(&spec)?;
+        let mut specs = self.specs.lock().unwrap().clone();
+        specs.insert(container.to_string(), spec);
+        storage::save_json(PROBES_FILE, &specs)?;
+        *self.specs.lock().unwrap() = specs;
+        // 配置变化后重新计数，并尽快执行一次
+        self.status.lock().unwrap().remove(container);
+        self.next_run.lock().unwrap().remove(container);
+        println!("已设置容器 {} 的存活探测", container);
+        Ok(())
+    }
+
+    pub fn remove(&self, container: &str) -> Result<()> {
+        let mut specs = self.specs.lock().unwrap().clone();
+        if specs.remove(container).is_none() {
+            return Err(ApiError::not_found(format!("容器 {} 没有配置存活探测", container)).into());
+        }
+        storage::save_json(PROBES_FILE, &specs)?;
+        *self.specs.lock().unwrap() = specs;
+        self.status.lock().unwrap().remove(container);
+        self.next_run.lock().unwrap().remove(container);
+        println!("已删除容器 {} 的存活探测", container);
+        Ok(())
+    }
+
+    /// 容器改名后，探测配置和状态跟随到新名称；新名称已有配置时不覆盖
+    pub fn rename(&self, old: &str, new: &str) -> Result<()> {
+        let mut specs = self.specs.lock().unwrap().clone();
+        if specs.contains_key(new) {
+            return Ok(());
+        }
+        let Some(spec) = specs.remove(old) else {
+            return Ok(());
+        };
+        specs.insert(new.to_string(), spec);
+        storage::save_json(PROBES_FILE, &specs)?;
+        *self.specs.lock().unwrap() = specs;
+        let mut status = self.status.lock().unwrap();
+        if let Some(entry) = status.remove(old) {
+            status.insert(new.to_string(), entry);
+        }
+        drop(status);
+        let mut next_run = self.next_run.lock().unwrap();
+        if let Some(next) = next_run.remove(old) {
+            next_run.insert(new.to_string(), next);
+        }
+        println!("容器 {} 的存活探测已改为 {}", old, new);
+        Ok(())
+    }
+
+    /// 容器被删除后清除运行状态，保留配置，同名容器重建后重新计数
+    pub fn reset(&self, container: &str) {
+        self.status.lock().unwrap().remove(container);
+        self.next_run.lock().unwrap().remove(container);
+    }
+
+    fn validate(spec: &ProbeSpec) -> Result<()> {
+        if spec.interval == 0 || spec.timeout == 0 || spec.failure_threshold == 0 {
+            return Err(ApiError::invalid("间隔、超时和失败次数必须大于 0").into());
+        }
+        // 超时不超过间隔，保证同一个探测不会同时运行多次
+        if spec.timeout > spec.interval {
+            return Err(ApiError::invalid("超时不能大于探测间隔").into());
+        }
+        match &spec.check {
+            ProbeCheck::Http { path, body_regex, .. } => {
+                if !path.starts_with('/') {
+                    return Err(ApiError::invalid("HTTP 路径必须以 / 开头").into());
+                }
+                if let Some(pattern) = body_regex {
+                    Regex::new(pattern)
+                        .map_err(|e| ApiError::invalid(format!("无效的正则表达式: {}", e)))?;
+                }
+            }
+            ProbeCheck::Exec { command } if command.is_empty() => {
+                return Err(ApiError::invalid("命令不能为空").into());
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// 到期需要执行的探测，同时安排下一次执行时间
+    pub fn due(&self) -> Vec<(String, ProbeSpec)> {
+        let now = Instant::now();
+        let specs = self.specs.lock().unwrap().clone();
+        let mut next_run = self.next_run.lock().unwrap();
+        specs.into_iter()
+            .filter(|(container, spec)| {
+                let due = next_run.get(container).is_none_or(|next| *next <= now);
+                if due {
+                    next_run.insert(container.clone(), now + Duration::from_secs(spec.interval));
+                }
+                due
+            })
+            .collect()
+    }
+
+    /// 执行一次探测；`ip` 用于 HTTP 和 TCP 探测
+    pub async fn check(&self, container_id: &str, ip: Option<&str>, spec: &ProbeSpec) -> Result<()> {
+        let timeout = Duration::from_secs(spec.timeout);
+        let address = |port: u16| -> Result<String> {
+            let ip = ip.ok_or_else(|| anyhow::anyhow!("容器没有可用的 IP 地址"))?;
+            // IPv6 地址需要加方括号
+            Ok(if ip.contains(':') { format!("[{}]:{}", ip, port) } else { format!("{}:{}", ip, port) })
+        };
+
+        match &spec.check {
+            ProbeCheck::Http { port, path, expected_status, body_regex } => {
+                let url = format!("http://{}{}", address(*port)?, path);
+                let client = reqwest::Client::builder().timeout(timeout).build()?;
+                let mut response = client.get(&url).send().await
+                    .map_err(|e| anyhow::anyhow!("请求 {} 失败: {}", url, e))?;
+                let status = response.status();
+                let status_ok = match expected_status {
+                    Some(expected) => status.as_u16() == *expected,
+                    None => status.is_success(),
+                };
+                if !status_ok {
+                    return Err(anyhow::anyhow!("{} 返回状态码 {}", url, status.as_u16()));
+                }
+                if let Some(pattern) = body_regex {
+                    let mut body = Vec::new();
+                    while let Some(chunk) = response.chunk().await? {
+                        let take = chunk.len().min(MAX_BODY - body.len());
+                        body.extend_from_slice(&chunk[..take]);
+                        if body.len() >= MAX_BODY {
+                            break;
+                        }
+                    }
+                    let body = String::from_utf8_lossy(&body);
+                    if !Regex::new(pattern)?.is_match(&body) {
+                        return Err(anyhow::anyhow!("响应内容不匹配 {}: {}", pattern, truncate(&body)));
+                    }
+                }
+            }
+            ProbeCheck::Tcp { port } => {
+                let address = address(*port)?;
+                tokio::time::timeout(timeout, tokio::net::TcpStream::connect(&address)).await
+                    .map_err(|_| anyhow::anyhow!("连接 {} 超时", address))?
+                    .map_err(|e| anyhow::anyhow!("连接 {} 失败: {}", address, e))?;
+            }
+            ProbeCheck::Exec { command } => {
+                let exec = self.docker.create_exec(container_id, CreateExecOptions {
+                    cmd: Some(command.to_vec()),
+                    attach_stdout: Some(true),
+                    attach_stderr: Some(true),
+                    ..Default::default()
+                }).await?;
+                // Docker 无法终止 exec，超时后命令可能仍在容器中运行
+                let (code, output) = tokio::time::timeout(timeout, self.exec(&exec.id)).await
+                    .map_err(|_| anyhow::anyhow!("命令执行超时"))??;
+                if code != 0 {
+                    return Err(anyhow::anyhow!("命令退出码 {}: {}", code, truncate(&output)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn exec(&self, exec_id: &str) -> Result<(i64, String)> {
+        let mut output = String::new();
+        if let StartExecResults::Attached { output: mut stream, .. } = self.docker.start_exec(exec_id, None).await? {
+            while let Some(chunk) = stream.next().await {
+                output.push_str(&chunk?.to_string());
+            }
+        }
+        let code = self.docker.inspect_exec(exec_id).await?.exit_code.unwrap_or(-1);
+        Ok((code, output))
+    }
+
+    /// 记录探测结果，连续失败次数达到阈值时返回 true
+    pub fn record(&self, container: &str, spec: &ProbeSpec, result: &Result<()>) -> bool {
+        let mut status = self.status.lock().unwrap();
+        let entry = status.entry(container.to_string()).or_default();
+        entry.last_checked = Some(Local::now());
+        match result {
+            Ok(()) => {
+                entry.consecutive_failures = 0;
+                entry.last_error = None;
+                false
+            }
+            Err(e) => {
+                entry.consecutive_failures += 1;
+                entry.last_error = Some(e.to_string());
+                entry.consecutive_failures >= spec.failure_threshold
+            }
+        }
+    }
+
+    /// 容器因探测失败被重启后重新计数
+    pub fn record_restart(&self, container: &str) {
+        let mut status = self.status.lock().unwrap();
+        let entry = status.entry(container.to_string()).or_default();
+        entry.consecutive_failures = 0;
+        entry.restarts += 1;
+    }
+
+    /// 容器没有运行时不计入失败
+    pub fn clear_failures(&self, container: &str) {
+        if let Some(entry) = self.status.lock().unwrap().get_mut(container) {
+            entry.consecutive_failures = 0;
+        }
+    }
+}
+
+fn truncate(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(MAX_OUTPUT) {
+        Some((index, _)) => format!("{}...", &text[..index]),
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,429 @@
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bollard::auth::DockerCredentials;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+use crate::storage;
+
+pub(crate) const STORE_FILE: &str = "registries.json";
+const KEY_FILE: &str = "secret.key";
+const DOCKER_HUB: &str = "docker.io";
+const DOCKER_HUB_ADDRESS: &str = "https://index.docker.io/v1/";
+// 凭据助手可能等待用户交互或网络，超时后终止，避免拉取镜像一直卡住
+const HELPER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 凭据来源
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialSource {
+    /// 通过管理器 API 保存
+    Manager,
+    /// `~/.docker/config.json` 中的 `auths`
+    DockerConfig,
+    /// `credsStore` 或 `credHelpers` 指定的凭据助手
+    Helper,
+}
+
+/// 对外展示的凭据条目，不包含任何密码或令牌
+#[derive(Clone, Debug, Serialize)]
+pub struct RegistryEntry {
+    pub registry: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    pub source: CredentialSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub helper: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+}
+
+// 保存在磁盘上的凭据，secret 为 base64(nonce || 密文)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct StoredCredential {
+    registry: String,
+    username: String,
+    secret: String,
+    #[serde(default)]
+    identity_token: bool,
+    #[serde(default)]
+    email: Option<String>,
+    created_at: u64,
+}
+
+// ~/.docker/config.json 中与凭据相关的部分
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DockerConfigFile {
+    #[serde(default)]
+    auths: HashMap<String, DockerConfigAuth>,
+    #[serde(default)]
+    creds_store: Option<String>,
+    #[serde(default)]
+    cred_helpers: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DockerConfigAuth {
+    #[serde(default)]
+    auth: Option<String>,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    identitytoken: Option<String>,
+}
+
+// 凭据助手 `docker-credential-<name> get` 的输出
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HelperCredential {
+    username: String,
+    secret: String,
+}
+
+/// 镜像仓库凭据存储，按仓库地址保存，密码加密后落盘
+pub struct RegistryStore {
+    cipher: Aes256Gcm,
+    entries: Mutex<HashMap<String, StoredCredential>>,
+}
+
+impl RegistryStore {
+    pub fn load() -> Result<Self> {
+        let cipher = Aes256Gcm::new(&Self::load_key()?);
+        let stored: Vec<StoredCredential> = storage::load_json(STORE_FILE)?;
+        let entries = stored.into_iter()
+            .map(|c| (c.registry.clone(), c))
+            .collect::<HashMap<_, _>>();
+        println!("已加载 {} 个镜像仓库凭据", entries.len());
+        Ok(Self { cipher, entries: Mutex::new(entries) })
+    }
+
+    // 优先使用 DOCKER_MANAGER_SECRET 派生密钥，否则在数据目录中生成随机密钥
+    fn load_key() -> Result<Key<Aes256Gcm>> {
+        if let Ok(secret) = std::env::var("DOCKER_MANAGER_SECRET") {
+            let digest = Sha256::digest(secret.as_bytes());
+            return Ok(*Key::<Aes256Gcm>::from_slice(&digest));
+        }
+
+        let path = storage::data_path(KEY_FILE);
+        if path.exists() {
+            let encoded = std::fs::read_to_string(&path)?;
+            let bytes = STANDARD.decode(encoded.trim())?;
+            if bytes.len() != 32 {
+                return Err(anyhow::anyhow!("密钥文件 {} 无效", path.display()));
+            }
+            return Ok(*Key::<Aes256Gcm>::from_slice(&bytes));
+        }
+
+        let key = Aes256Gcm::generate_key(OsRng);
+        storage::write_private(KEY_FILE, STANDARD.encode(key).as_bytes())?;
+        println!("已生成凭据加密密钥: {}", path.display());
+        Ok(key)
+    }
+
+    fn encrypt(&self, plain: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let cipher_text = self.cipher.encrypt(&nonce, plain.as_bytes())
+            .map_err(|_| anyhow::anyhow!("加密凭据失败"))?;
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&cipher_text);
+        Ok(STANDARD.encode(payload))
+    }
+
+    fn decrypt(&self, encoded: &str) -> Result<String> {
+        let payload = STANDARD.decode(encoded)?;
+        if payload.len() < 12 {
+            return Err(anyhow::anyhow!("凭据数据已损坏"));
+        }
+        let (nonce, cipher_text) = payload.split_at(12);
+        let plain = self.cipher.decrypt(Nonce::from_slice(nonce), cipher_text)
+            .map_err(|_| anyhow::anyhow!("解密凭据失败，密钥可能已更换"))?;
+        Ok(String::from_utf8(plain)?)
+    }
+
+    fn persist(&self, entries: &HashMap<String, StoredCredential>) -> Result<()> {
+        let mut list: Vec<&StoredCredential> = entries.values().collect();
+        list.sort_by(|a, b| a.registry.cmp(&b.registry));
+        storage::save_json(STORE_FILE, &list)
+    }
+
+    /// 列出所有可用的凭据（管理器保存的以及 Docker 客户端配置中的）
+    pub fn list(&self) -> Vec<RegistryEntry> {
+        let mut result: Vec<RegistryEntry> = {
+            let entries = self.entries.lock().unwrap();
+            entries.values()
+                .map(|c| RegistryEntry {
+                    registry: c.registry.clone(),
+                    username: Some(c.username.clone()),
+                    source: CredentialSource::Manager,
+                    helper: None,
+                    created_at: Some(c.created_at),
+                })
+                .collect()
+        };
+
+        let docker_config = read_docker_config();
+        for (server, auth) in &docker_config.auths {
+            let registry = normalize_registry(server);
+            if result.iter().any(|e| e.registry == registry) {
+                continue;
+            }
+            // 使用 credsStore 时 auths 中只有空占位，实际凭据在助手里
+            let has_inline = auth.auth.as_deref().is_some_and(|a| !a.is_empty())
+                || auth.identitytoken.is_some()
+                || auth.password.is_some();
+            let (source, helper) = if has_inline {
+                (CredentialSource::DockerConfig, None)
+            } else if let Some(store) = &docker_config.creds_store {
+                (CredentialSource::Helper, Some(store.clone()))
+            } else {
+                continue;
+            };
+            result.push(RegistryEntry {
+                registry,
+                username: decode_config_auth(auth).map(|(user, _)| user),
+                source,
+                helper,
+                created_at: None,
+            });
+        }
+        for (server, helper) in &docker_config.cred_helpers {
+            let registry = normalize_registry(server);
+            if result.iter().any(|e| e.registry == registry) {
+                continue;
+            }
+            result.push(RegistryEntry {
+                registry,
+                username: None,
+                source: CredentialSource::Helper,
+                helper: Some(helper.clone()),
+                created_at: None,
+            });
+        }
+
+        result.sort_by(|a, b| a.registry.cmp(&b.registry));
+        result
+    }
+
+    /// 新增或替换某个仓库的凭据；以 `<token>` 作为用户名时密码视为 identity token
+    pub fn save(&self, registry: &str, username: &str, password: &str, email: Option<String>) -> Result<()> {
+        let registry = normalize_registry(registry);
+        if registry.is_empty() || username.is_empty() || password.is_empty() {
+            return Err(anyhow::anyhow!("仓库地址、用户名和密码不能为空"));
+        }
+        let credential = StoredCredential {
+            registry: registry.clone(),
+            username: username.to_string(),
+            secret: self.encrypt(password)?,
+            identity_token: username == "<token>",
+            email,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        let previous = entries.insert(registry.clone(), credential);
+        if let Err(e) = self.persist(&entries) {
+            // 写入失败时恢复原来的凭据，内存和文件保持一致
+            match previous {
+                Some(previous) => entries.insert(registry.clone(), previous),
+                None => entries.remove(&registry),
+            };
+            return Err(e);
+        }
+        println!("已保存镜像仓库 {} 的凭据", registry);
+        Ok(())
+    }
+
+    pub fn remove(&self, registry: &str) -> Result<bool> {
+        let registry = normalize_registry(registry);
+        let mut entries = self.entries.lock().unwrap();
+        let Some(previous) = entries.remove(&registry) else {
+            return Ok(false);
+        };
+        if let Err(e) = self.persist(&entries) {
+            entries.insert(registry, previous);
+            return Err(e);
+        }
+        println!("已删除镜像仓库 {} 的凭据", registry);
+        Ok(true)
+    }
+
+    /// 查找拉取某个镜像时应使用的凭据：管理器保存的 > credHelpers > auths > credsStore
+    pub fn credentials_for_image(&self, image: &str) -> Option<DockerCredentials> {
+        let registry = registry_host(image);
+        let server_address = if registry == DOCKER_HUB {
+            DOCKER_HUB_ADDRESS.to_string()
+        } else {
+            registry.clone()
+        };
+
+        let stored = self.entries.lock().unwrap().get(&registry).cloned();
+        if let Some(stored) = stored {
+            match self.decrypt(&stored.secret) {
+                Ok(secret) => {
+                    return Some(build_credentials(&stored.username, secret, stored.email, server_address));
+                }
+                Err(e) => eprintln!("读取仓库 {} 的凭据失败: {}", registry, e),
+            }
+        }
+
+        let docker_config = read_docker_config();
+        let helper = docker_config.cred_helpers.iter()
+            .find(|(server, _)| normalize_registry(server) == registry)
+            .map(|(_, helper)| helper.clone());
+        if let Some(helper) = helper {
+            return run_credential_helper(&helper, &server_address)
+                .map(|(user, secret)| build_credentials(&user, secret, None, server_address));
+        }
+
+        let inline = docker_config.auths.iter()
+            .find(|(server, _)| normalize_registry(server) == registry)
+            .map(|(_, auth)| auth);
+        if let Some(auth) = inline {
+            if let Some(token) = &auth.identitytoken {
+                return Some(DockerCredentials {
+                    identitytoken: Some(token.clone()),
+                    serveraddress: Some(server_address),
+                    ..Default::default()
+                });
+            }
+            if let Some((user, password)) = decode_config_auth(auth) {
+                return Some(build_credentials(&user, password, None, server_address));
+            }
+        }
+
+        docker_config.creds_store.as_deref()
+            .and_then(|store| run_credential_helper(store, &server_address))
+            .map(|(user, secret)| build_credentials(&user, secret, None, server_address))
+    }
+}
+
+fn build_credentials(username: &str, secret: String, email: Option<String>, server_address: String) -> DockerCredentials {
+    if username == "<token>" {
+        DockerCredentials {
+            identitytoken: Some(secret),
+            serveraddress: Some(server_address),
+            ..Default::default()
+        }
+    } else {
+        DockerCredentials {
+            username: Some(username.to_string()),
+            password: Some(secret),
+            email,
+            serveraddress: Some(server_address),
+            ..Default::default()
+        }
+    }
+}
+
+/// 从镜像名中解析仓库地址，没有显式仓库时为 Docker Hub
+pub fn registry_host(image: &str) -> String {
+    match image.split_once('/') {
+        Some((first, _)) if first.contains('.') || first.contains(':') || first == "localhost" => {
+            normalize_registry(first)
+        }
+        _ => DOCKER_HUB.to_string(),
+    }
+}
+
+// 统一仓库地址写法：去掉协议和路径，Docker Hub 的各种别名归一为 docker.io
+fn normalize_registry(server: &str) -> String {
+    let host = server.trim()
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let host = host.split('/').next().unwrap_or(host).to_ascii_lowercase();
+    match host.as_str() {
+        "index.docker.io" | "registry-1.docker.io" | "registry.hub.docker.com" => DOCKER_HUB.to_string(),
+        _ => host,
+    }
+}
+
+fn docker_config_path() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var("DOCKER_CONFIG") {
+        return Some(PathBuf::from(dir).join("config.json"));
+    }
+    std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .ok()
+        .map(|home| PathBuf::from(home).join(".docker").join("config.json"))
+}
+
+fn read_docker_config() -> DockerConfigFile {
+    let Some(path) = docker_config_path() else {
+        return DockerConfigFile::default();
+    };
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return DockerConfigFile::default();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        eprintln!("解析 {} 失败: {}", path.display(), e);
+        DockerConfigFile::default()
+    })
+}
+
+fn decode_config_auth(auth: &DockerConfigAuth) -> Option<(String, String)> {
+    if let (Some(user), Some(password)) = (&auth.username, &auth.password) {
+        return Some((user.clone(), password.clone()));
+    }
+    let decoded = STANDARD.decode(auth.auth.as_deref()?).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (user, password) = decoded.split_once(':')?;
+    Some((user.to_string(), password.to_string()))
+}
+
+// 调用 `docker-credential-<helper> get`，从标准输入传入仓库地址
+fn run_credential_helper(helper: &str, server_address: &str) -> Option<(String, String)> {
+    let program = format!("docker-credential-{}", helper);
+    let mut child = Command::new(&program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| eprintln!("无法运行凭据助手 {}: {}", program, e))
+        .ok()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(server_address.as_bytes());
+    }
+    // 在单独的线程里读取输出，避免输出填满管道时助手无法退出
+    let mut stdout = child.stdout.take()?;
+    let reader = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = stdout.read_to_end(&mut output);
+        output
+    });
+
+    let deadline = Instant::now() + HELPER_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+            Ok(None) => {
+                eprintln!("凭据助手 {} 超过 {} 秒没有返回，已终止", program, HELPER_TIMEOUT.as_secs());
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            Err(e) => {
+                eprintln!("等待凭据助手 {} 失败: {}", program, e);
+                return None;
+            }
+        }
+    };
+    let output = reader.join().ok()?;
+    if !status.success() {
+        return None;
+    }
+    let credential: HelperCredential = serde_json::from_slice(&output).ok()?;
+    Some((credential.username, credential.secret))
+}
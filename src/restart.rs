@@ -1,15 +1,11 @@
 use bollard::Docker;
-use bollard::container::{CreateContainerOptions, Config, StartContainerOptions, KillContainerOptions};
+use bollard::container::Config;
 use bollard::service::ContainerInspectResponse;
-use crate::models::ContainerConfig;
+use crate::models::{ContainerConfig, ContainerNetwork, MountSpec};
 use std::collections::HashMap;
 use std::sync::Mutex;
 use anyhow::Result;
-use tokio::time::timeout;
-use std::time::Duration;
 use std::sync::Arc;
-use std::time::SystemTime;
-use crate::docker::DockerClient;
 
 pub struct ContainerRestarter {
     docker: Docker,
@@ -37,6 +33,8 @@ impl ContainerRestarter {
         }
 
         let image = inspect.config.as_ref().and_then(|c| c.image.clone()).unwrap_or_default();
+        let mounts = MountSpec::from_inspect(&inspect);
+        let networks = ContainerNetwork::from_inspect(&inspect);
         
         // 转换 ContainerConfig 类型
         let config = if let Some(conf) = inspect.config {
@@ -72,7 +70,8 @@ impl ContainerRestarter {
             image,
             config,
             host_config: inspect.host_config,
-            ip_address: None,
+            networks,
+            mounts,
         };
 
         let mut configs = self.container_configs.lock().unwrap();
@@ -96,6 +95,8 @@ impl ContainerRestarter {
         
         // 获取容器配置
         if let Ok(inspect) = docker.inspect_container(container_id, None).await {
+            let mounts = MountSpec::from_inspect(&inspect);
+            let networks = ContainerNetwork::from_inspect(&inspect);
             let name = inspect.name.unwrap_or_default().trim_start_matches('/').to_string();
             let image = inspect.config.as_ref()
                 .and_then(|c| c.image.clone())
@@ -128,7 +129,8 @@ impl ContainerRestarter {
                     labels: c.labels.clone(),
                     ..Default::default()
                 }),
-                ip_address: None,
+                networks,
+                mounts,
             };
         }
         Ok(())
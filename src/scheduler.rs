@@ -0,0 +1,451 @@
+use chrono::{DateTime, Local, Utc};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::Mutex;
+use anyhow::Result;
+use crate::cron::{CronExpr, Zone};
+use crate::exec::CommandRequest;
+use crate::storage;
+use crate::error::ApiError;
+
+pub(crate) const SCHEDULES_FILE: &str = "schedules.json";
+const STATE_FILE: &str = "schedule_runs.json";
+const HISTORY_LIMIT: usize = 500;
+const MAX_JITTER: u64 = 3600;
+// 一次最多补算的错过次数，超过后直接跳到当前时间
+const MAX_OCCURRENCES: usize = 10000;
+
+/// 超过计划时间这么久才轮到的执行视为错过（管理器没有运行）
+pub const MISSED_TOLERANCE_SECS: i64 = 120;
+
+/// 定时执行的操作
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduleAction {
+    Restart,
+    Stop,
+    Start,
+    /// 执行命令，参数与 `POST /api/container/:id/exec` 相同
+    Exec(CommandRequest),
+    /// 拉取最新镜像，有更新时重建容器
+    Update,
+}
+
+impl ScheduleAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduleAction::Restart => "restart",
+            ScheduleAction::Stop => "stop",
+            ScheduleAction::Start => "start",
+            ScheduleAction::Exec(_) => "exec",
+            ScheduleAction::Update => "update",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedRun {
+    /// 跳过管理器停止期间错过的执行
+    #[default]
+    Skip,
+    /// 启动后补执行一次
+    RunOnce,
+}
+
+/// 一个计划任务，按容器名或标签选择目标容器
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Schedule {
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub cron: String,
+    /// 为空时使用主机时区
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// `key` 或 `key=value`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub action: ScheduleAction,
+    /// 每次执行前随机延迟 0 到该秒数，避免多个任务同时执行
+    #[serde(default)]
+    pub jitter: u64,
+    #[serde(default)]
+    pub missed: MissedRun,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 最后保存计划的用户，执行命令时按该用户检查 `allowed_users`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_by: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Schedule {
+    fn validate(&self) -> Result<(CronExpr, Zone)> {
+        if self.name.is_empty() || !self.name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)) {
+            return Err(ApiError::invalid(format!("无效的计划名: {}", self.name)).into());
+        }
+        let invalid = |e: anyhow::Error| ApiError::invalid(e.to_string());
+        let cron = CronExpr::parse(&self.cron).map_err(invalid)?;
+        let zone = Zone::parse(self.timezone.as_deref().unwrap_or_default()).map_err(invalid)?;
+        let container = self.container.as_deref().is_some_and(|c| !c.is_empty());
+        let label = self.label.as_deref().is_some_and(|l| !l.is_empty());
+        if container == label {
+            return Err(ApiError::invalid("需要指定 container 或 label 中的一个").into());
+        }
+        if self.jitter > MAX_JITTER {
+            return Err(ApiError::invalid(format!("随机延迟不能超过 {} 秒", MAX_JITTER)).into());
+        }
+        if let ScheduleAction::Exec(request) = &self.action {
+            if request.action.is_some() == request.command.is_some() {
+                return Err(ApiError::invalid("exec 需要指定 action 或 command 中的一个").into());
+            }
+            if request.action.is_some() && (request.user.is_some() || request.working_dir.is_some() || !request.env.is_empty()) {
+                return Err(ApiError::invalid("预定义操作不能指定 user、working_dir 或 env").into());
+            }
+        }
+        Ok((cron, zone))
+    }
+
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let (cron, zone) = self.validate().ok()?;
+        cron.next_after(after, &zone)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunTrigger {
+    Schedule,
+    /// 管理器启动后补执行错过的一次
+    CatchUp,
+    Manual,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Success,
+    Failure,
+    /// 没有匹配的容器或上一次执行尚未结束
+    Skipped,
+    /// 管理器停止期间错过的执行
+    Missed,
+}
+
+/// 对一个容器执行的结果
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TargetResult {
+    pub container: String,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// 一次执行记录
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduleRun {
+    pub schedule: String,
+    pub action: String,
+    pub trigger: RunTrigger,
+    pub scheduled_for: DateTime<Local>,
+    pub started: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished: Option<DateTime<Local>>,
+    pub status: RunStatus,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<TargetResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl ScheduleRun {
+    pub fn new(schedule: &Schedule, trigger: RunTrigger, scheduled_for: DateTime<Utc>, status: RunStatus) -> Self {
+        Self {
+            schedule: schedule.name.clone(),
+            action: schedule.action.as_str().to_string(),
+            trigger,
+            scheduled_for: scheduled_for.with_timezone(&Local),
+            started: Local::now(),
+            finished: None,
+            status,
+            targets: Vec::new(),
+            message: None,
+        }
+    }
+}
+
+/// 到期需要执行的计划
+pub struct DueRun {
+    pub schedule: Schedule,
+    pub scheduled_for: DateTime<Utc>,
+    pub trigger: RunTrigger,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScheduleInfo {
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_run: Option<DateTime<Local>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<ScheduleRun>,
+    pub running: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ScheduleState {
+    // 每个计划最近一次到期的时间，用于计算下一次执行和错过的执行
+    #[serde(default)]
+    last_scheduled: HashMap<String, DateTime<Utc>>,
+    // 被计划任务停止的容器，管理器重启后也不自动拉起
+    #[serde(default)]
+    stopped: BTreeSet<String>,
+    #[serde(default)]
+    history: VecDeque<ScheduleRun>,
+}
+
+pub struct Scheduler {
+    schedules: Mutex<Vec<Schedule>>,
+    state: Mutex<ScheduleState>,
+    running: Mutex<HashSet<String>>,
+}
+
+impl Scheduler {
+    pub fn load() -> Result<Self> {
+        let schedules: Vec<Schedule> = storage::load_json(SCHEDULES_FILE)?;
+        for schedule in &schedules {
+            if let Err(e) = schedule.validate() {
+                eprintln!("计划任务 {} 无效，不会执行: {}", schedule.name, e);
+            }
+        }
+        let state: ScheduleState = storage::load_json(STATE_FILE)?;
+        Ok(Self {
+            schedules: Mutex::new(schedules),
+            state: Mutex::new(state),
+            running: Mutex::new(HashSet::new()),
+        })
+    }
+
+    pub fn list(&self) -> Vec<ScheduleInfo> {
+        let schedules = self.schedules.lock().unwrap().clone();
+        schedules.into_iter().map(|s| self.info(s)).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Result<ScheduleInfo> {
+        let schedule = self.find(name)?;
+        Ok(self.info(schedule))
+    }
+
+    fn find(&self, name: &str) -> Result<Schedule> {
+        self.schedules.lock().unwrap().iter()
+            .find(|s| s.name == name)
+            .cloned()
+            .ok_or_else(|| ApiError::not_found(format!("计划任务 {} 不存在", name)).into())
+    }
+
+    fn info(&self, schedule: Schedule) -> ScheduleInfo {
+        let state = self.state.lock().unwrap();
+        let after = state.last_scheduled.get(&schedule.name).copied().unwrap_or_else(Utc::now);
+        let next_run = if schedule.enabled { schedule.next_after(after) } else { None };
+        ScheduleInfo {
+            next_run: next_run.map(|t| t.with_timezone(&Local)),
+            last_run: state.history.iter().rev().find(|r| r.schedule == schedule.name).cloned(),
+            running: self.running.lock().unwrap().contains(&schedule.name),
+            schedule,
+        }
+    }
+
+    /// 新建或替换计划；`replace` 为 None 时名称不能已存在
+    pub fn save(&self, schedule: Schedule, replace: Option<&str>) -> Result<()> {
+        schedule.validate()?;
+        let mut schedules = self.schedules.lock().unwrap();
+        let existing = schedules.iter().position(|s| s.name == schedule.name);
+        match replace {
+            None if existing.is_some() => {
+                return Err(ApiError::conflict(format!("计划任务 {} 已存在", schedule.name)).into());
+            }
+            None => schedules.push(schedule.clone()),
+            Some(old) => {
+                let index = schedules.iter().position(|s| s.name == old)
+                    .ok_or_else(|| ApiError::not_found(format!("计划任务 {} 不存在", old)))?;
+                if old != schedule.name && existing.is_some() {
+                    return Err(ApiError::conflict(format!("计划任务 {} 已存在", schedule.name)).into());
+                }
+                schedules[index] = schedule.clone();
+            }
+        }
+        storage::save_json(SCHEDULES_FILE, &*schedules)?;
+        drop(schedules);
+
+        // 从现在开始计算，修改前错过的执行不再补
+        let mut state = self.state.lock().unwrap();
+        if let Some(old) = replace {
+            state.last_scheduled.remove(old);
+        }
+        state.last_scheduled.insert(schedule.name.clone(), Utc::now());
+        storage::save_json(STATE_FILE, &*state)?;
+        println!("已保存计划任务 {}", schedule.name);
+        Ok(())
+    }
+
+    pub fn remove(&self, name: &str) -> Result<()> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let before = schedules.len();
+        schedules.retain(|s| s.name != name);
+        if schedules.len() == before {
+            return Err(ApiError::not_found(format!("计划任务 {} 不存在", name)).into());
+        }
+        storage::save_json(SCHEDULES_FILE, &*schedules)?;
+        drop(schedules);
+
+        let mut state = self.state.lock().unwrap();
+        state.last_scheduled.remove(name);
+        storage::save_json(STATE_FILE, &*state)?;
+        println!("已删除计划任务 {}", name);
+        Ok(())
+    }
+
+    /// 手动执行一次
+    pub fn manual(&self, name: &str) -> Result<DueRun> {
+        Ok(DueRun { schedule: self.find(name)?, scheduled_for: Utc::now(), trigger: RunTrigger::Manual })
+    }
+
+    /// 取出到期的计划，并记录管理器停止期间错过的执行
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<DueRun> {
+        let schedules = self.schedules.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+        let mut due = Vec::new();
+        let mut changed = false;
+
+        for schedule in schedules.into_iter().filter(|s| s.enabled) {
+            let Ok((cron, zone)) = schedule.validate() else {
+                continue;
+            };
+            let Some(last) = state.last_scheduled.get(&schedule.name).copied() else {
+                // 第一次看到的计划从现在开始计算
+                state.last_scheduled.insert(schedule.name.clone(), now);
+                changed = true;
+                continue;
+            };
+
+            let mut occurrences = Vec::new();
+            let mut cursor = last;
+            while let Some(next) = cron.next_after(cursor, &zone).filter(|t| *t <= now) {
+                occurrences.push(next);
+                cursor = next;
+                if occurrences.len() >= MAX_OCCURRENCES {
+                    cursor = now;
+                    break;
+                }
+            }
+            let Some(latest) = occurrences.last().copied() else {
+                continue;
+            };
+            state.last_scheduled.insert(schedule.name.clone(), cursor);
+            changed = true;
+
+            let late = (now - latest).num_seconds() > MISSED_TOLERANCE_SECS;
+            let mut missed = occurrences.len() - 1;
+            if !late {
+                due.push(DueRun { schedule: schedule.clone(), scheduled_for: latest, trigger: RunTrigger::Schedule });
+            } else if schedule.missed == MissedRun::RunOnce {
+                due.push(DueRun { schedule: schedule.clone(), scheduled_for: latest, trigger: RunTrigger::CatchUp });
+            } else {
+                missed += 1;
+            }
+            if missed > 0 {
+                println!("计划任务 {} 错过了 {} 次执行", schedule.name, missed);
+                let mut run = ScheduleRun::new(&schedule, RunTrigger::Schedule, occurrences[0], RunStatus::Missed);
+                run.message = Some(format!("管理器未运行，错过了 {} 次执行", missed));
+                Self::push_history(&mut state, run);
+            }
+        }
+
+        if changed {
+            if let Err(e) = storage::save_json(STATE_FILE, &*state) {
+                eprintln!("保存计划任务状态失败: {}", e);
+            }
+        }
+        due
+    }
+
+    /// 所有启用的计划中最早的下一次执行时间
+    pub fn next_wake(&self) -> Option<DateTime<Utc>> {
+        let schedules = self.schedules.lock().unwrap().clone();
+        let state = self.state.lock().unwrap();
+        schedules.iter()
+            .filter(|s| s.enabled)
+            .filter_map(|s| s.next_after(state.last_scheduled.get(&s.name).copied().unwrap_or_else(Utc::now)))
+            .min()
+    }
+
+    /// 标记计划开始执行，上一次执行尚未结束时返回 false
+    pub fn begin(&self, name: &str) -> bool {
+        self.running.lock().unwrap().insert(name.to_string())
+    }
+
+    pub fn finish(&self, mut run: ScheduleRun, began: bool) {
+        if began {
+            self.running.lock().unwrap().remove(&run.schedule);
+        }
+        run.finished = Some(Local::now());
+        let mut state = self.state.lock().unwrap();
+        Self::push_history(&mut state, run);
+        if let Err(e) = storage::save_json(STATE_FILE, &*state) {
+            eprintln!("保存计划任务状态失败: {}", e);
+        }
+    }
+
+    fn push_history(state: &mut ScheduleState, run: ScheduleRun) {
+        state.history.push_back(run);
+        while state.history.len() > HISTORY_LIMIT {
+            state.history.pop_front();
+        }
+    }
+
+    /// 执行记录，最新的在前
+    pub fn history(&self, schedule: Option<&str>, limit: Option<usize>) -> Vec<ScheduleRun> {
+        self.state.lock().unwrap().history.iter().rev()
+            .filter(|r| schedule.is_none_or(|name| r.schedule == name))
+            .take(limit.unwrap_or(100))
+            .cloned()
+            .collect()
+    }
+
+    /// 记录被计划任务停止或启动的容器
+    pub fn mark_stopped(&self, container_id: &str, stopped: bool) {
+        let mut state = self.state.lock().unwrap();
+        let changed = if stopped {
+            state.stopped.insert(container_id.to_string())
+        } else {
+            state.stopped.remove(container_id)
+        };
+        if changed {
+            if let Err(e) = storage::save_json(STATE_FILE, &*state) {
+                eprintln!("保存计划任务状态失败: {}", e);
+            }
+        }
+    }
+
+    pub fn is_stopped(&self, container_id: &str) -> bool {
+        self.state.lock().unwrap().stopped.contains(container_id)
+    }
+}
+
+/// 0 到 `max` 秒之间的随机延迟
+pub fn jitter(name: &str, max: u64) -> std::time::Duration {
+    if max == 0 {
+        return std::time::Duration::ZERO;
+    }
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    name.hash(&mut hasher);
+    Utc::now().timestamp_nanos_opt().hash(&mut hasher);
+    std::time::Duration::from_secs(hasher.finish() % (max + 1))
+}
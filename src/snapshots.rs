@@ -0,0 +1,114 @@
+use chrono::{DateTime, Local};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use std::sync::Mutex;
+use anyhow::Result;
+use crate::models::ContainerConfig;
+use crate::storage;
+use crate::error::ApiError;
+
+const SNAPSHOTS_FILE: &str = "snapshots.json";
+
+lazy_static! {
+    // 可以带仓库地址和端口，如 registry.example.com:5000/team/app
+    static ref REPOSITORY: Regex =
+        Regex::new(r"^(?:[a-zA-Z0-9.-]+(?::[0-9]+)?/)?[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*(?:/[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*)*$").unwrap();
+    static ref TAG: Regex = Regex::new(r"^[A-Za-z0-9_][A-Za-z0-9_.-]{0,127}$").unwrap();
+}
+
+/// 快照镜像上记录来源容器的标签
+pub const LABEL_SOURCE_ID: &str = "docker-manager.snapshot.container-id";
+pub const LABEL_SOURCE_NAME: &str = "docker-manager.snapshot.container-name";
+
+/// 把容器提交为镜像的参数
+#[derive(Clone, Debug, Deserialize)]
+pub struct CommitRequest {
+    pub repository: String,
+    #[serde(default = "default_tag")]
+    pub tag: String,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// 提交期间暂停容器，保证文件系统一致
+    #[serde(default = "default_pause")]
+    pub pause: bool,
+}
+
+fn default_tag() -> String {
+    "latest".to_string()
+}
+
+fn default_pause() -> bool {
+    true
+}
+
+impl CommitRequest {
+    pub fn validate(&self) -> Result<()> {
+        if !REPOSITORY.is_match(&self.repository) {
+            return Err(ApiError::invalid(format!("无效的仓库名: {}", self.repository)).into());
+        }
+        if !TAG.is_match(&self.tag) {
+            return Err(ApiError::invalid(format!("无效的标签: {}", self.tag)).into());
+        }
+        Ok(())
+    }
+
+    pub fn reference(&self) -> String {
+        format!("{}:{}", self.repository, self.tag)
+    }
+}
+
+/// 从容器提交的镜像，以及提交时容器的配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub image_id: String,
+    pub image: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub created: DateTime<Local>,
+    pub container_id: String,
+    pub container_name: String,
+    /// 提交时保存的容器配置，可以据此用快照镜像重建容器
+    pub config: ContainerConfig,
+}
+
+/// 快照记录，保存在数据目录下的 snapshots.json
+pub struct SnapshotStore {
+    snapshots: Mutex<Vec<Snapshot>>,
+}
+
+impl SnapshotStore {
+    pub fn load() -> Result<Self> {
+        let snapshots: Vec<Snapshot> = storage::load_json(SNAPSHOTS_FILE)?;
+        Ok(Self { snapshots: Mutex::new(snapshots) })
+    }
+
+    /// 最新的在前
+    pub fn list(&self) -> Vec<Snapshot> {
+        self.snapshots.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    /// 按镜像 ID（可以是前缀）或镜像名查找
+    pub fn find(&self, image: &str) -> Result<Snapshot> {
+        let id = image.strip_prefix("sha256:").unwrap_or(image);
+        self.snapshots.lock().unwrap().iter().rev()
+            .find(|s| {
+                s.image == image
+                    || (id.len() >= 12 && s.image_id.strip_prefix("sha256:").unwrap_or(&s.image_id).starts_with(id))
+            })
+            .cloned()
+            .ok_or_else(|| ApiError::not_found(format!("镜像 {} 不是容器快照", image)).into())
+    }
+
+    pub fn record(&self, snapshot: Snapshot) -> Result<()> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        // 同名镜像重新提交后旧记录指向的镜像已失去标签，只保留新的
+        snapshots.retain(|s| s.image != snapshot.image);
+        snapshots.push(snapshot);
+        storage::save_json(SNAPSHOTS_FILE, &*snapshots)
+    }
+}
@@ -0,0 +1,338 @@
+use bollard::Docker;
+use bollard::container::ListContainersOptions;
+use bollard::models::ContainerSummary;
+use chrono::{DateTime, Local};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::Result;
+use crate::compose::{self, ComposeProject, LABEL_PROJECT, LABEL_SERVICE};
+use crate::networks::NetworkManager;
+use crate::storage;
+use crate::volumes::VolumeManager;
+use crate::error::ApiError;
+
+pub(crate) const STACKS_FILE: &str = "stacks.json";
+
+/// 保存的 compose 项目
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StackDefinition {
+    pub name: String,
+    pub content: String,
+    /// 指向主机上的 compose 文件时，每次部署都重新读取
+    #[serde(default)]
+    pub path: Option<String>,
+    /// 解析 env_file、`.env` 和相对路径挂载的目录
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    /// 参与变量插值的额外变量
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>,
+}
+
+impl StackDefinition {
+    pub fn working_dir(&self) -> PathBuf {
+        self.working_dir.as_ref()
+            .map(PathBuf::from)
+            .or_else(|| self.path.as_ref().and_then(|p| Path::new(p).parent().map(Path::to_path_buf)))
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// 解析 compose 文件，指向主机文件时读取最新内容
+    pub fn project(&self) -> Result<ComposeProject> {
+        let content = match &self.path {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| ApiError::invalid(format!("读取 compose 文件 {} 失败: {}", path, e)))?,
+            None => self.content.clone(),
+        };
+        compose::parse(&self.name, &content, &self.working_dir(), &self.env)
+            .map_err(|e| ApiError::invalid(e.to_string()).into())
+    }
+}
+
+/// 项目及其服务的运行状态
+#[derive(Clone, Debug, Serialize)]
+pub struct StackInfo {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub working_dir: String,
+    pub services: Vec<StackService>,
+    /// compose 文件当前无法解析时的错误
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StackService {
+    pub service: String,
+    pub container_name: String,
+    pub image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+    /// 容器状态，容器不存在时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    /// 容器的配置摘要与 compose 文件不一致，需要重新部署
+    pub outdated: bool,
+}
+
+/// 创建或更新项目时提交的 compose 文件来源
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct StackSource {
+    /// compose 文件内容
+    #[serde(default)]
+    pub content: Option<String>,
+    /// 主机上的 compose 文件路径，与 content 二选一
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+}
+
+/// 保存 compose 项目的定义，并负责项目级的网络和卷
+pub struct StackManager {
+    docker: Docker,
+    stacks: Mutex<HashMap<String, StackDefinition>>,
+}
+
+impl StackManager {
+    pub fn load(docker: Docker) -> Result<Self> {
+        let stacks: HashMap<String, StackDefinition> = storage::load_json(STACKS_FILE)?;
+        Ok(Self {
+            docker,
+            stacks: Mutex::new(stacks),
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Result<StackDefinition> {
+        self.stacks.lock().unwrap().get(name).cloned()
+            .ok_or_else(|| ApiError::not_found(format!("项目 {} 不存在", name)).into())
+    }
+
+    pub fn definitions(&self) -> Vec<StackDefinition> {
+        let mut result: Vec<StackDefinition> = self.stacks.lock().unwrap().values().cloned().collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        result
+    }
+
+    pub fn save(&self, definition: StackDefinition) -> Result<()> {
+        let mut stacks = self.stacks.lock().unwrap();
+        let mut updated = stacks.clone();
+        updated.insert(definition.name.clone(), definition);
+        storage::save_json(STACKS_FILE, &updated)?;
+        *stacks = updated;
+        Ok(())
+    }
+
+    pub fn remove(&self, name: &str) -> Result<bool> {
+        let mut stacks = self.stacks.lock().unwrap();
+        if !stacks.contains_key(name) {
+            return Ok(false);
+        }
+        let mut updated = stacks.clone();
+        updated.remove(name);
+        storage::save_json(STACKS_FILE, &updated)?;
+        *stacks = updated;
+        Ok(true)
+    }
+
+    /// 按服务名列出项目中的容器
+    pub async fn containers(&self, project: &str) -> Result<HashMap<String, ContainerSummary>> {
+        let mut filters = HashMap::new();
+        filters.insert("label".to_string(), vec![format!("{}={}", LABEL_PROJECT, project)]);
+        let containers = self.docker.list_containers(Some(ListContainersOptions::<String> {
+            all: true,
+            filters,
+            ..Default::default()
+        })).await?;
+
+        Ok(containers.into_iter()
+            .filter_map(|c| {
+                let service = c.labels.as_ref()?.get(LABEL_SERVICE)?.clone();
+                Some((service, c))
+            })
+            .collect())
+    }
+
+    pub async fn info(&self, definition: &StackDefinition) -> Result<StackInfo> {
+        let containers = self.containers(&definition.name).await?;
+        let (services, error) = match definition.project() {
+            Ok(project) => {
+                let services = project.services.iter()
+                    .map(|service| {
+                        let container = containers.get(&service.name);
+                        let hash = container
+                            .and_then(|c| c.labels.as_ref())
+                            .and_then(|l| l.get(compose::LABEL_CONFIG_HASH));
+                        StackService {
+                            service: service.name.clone(),
+                            container_name: service.container_name.clone(),
+                            image: service.image.clone(),
+                            container_id: container.and_then(|c| c.id.clone()),
+                            state: container.and_then(|c| c.state.clone()),
+                            outdated: container.is_some() && hash != Some(&service.config_hash),
+                        }
+                    })
+                    .collect();
+                (services, None)
+            }
+            // 文件无法解析时仍然展示已有的容器
+            Err(e) => {
+                let mut services: Vec<StackService> = containers.iter()
+                    .map(|(service, c)| StackService {
+                        service: service.clone(),
+                        container_name: c.names.as_ref()
+                            .and_then(|n| n.first())
+                            .map(|n| n.trim_start_matches('/').to_string())
+                            .unwrap_or_default(),
+                        image: c.image.clone().unwrap_or_default(),
+                        container_id: c.id.clone(),
+                        state: c.state.clone(),
+                        outdated: false,
+                    })
+                    .collect();
+                services.sort_by(|a, b| a.service.cmp(&b.service));
+                (services, Some(e.to_string()))
+            }
+        };
+
+        Ok(StackInfo {
+            name: definition.name.clone(),
+            path: definition.path.clone(),
+            working_dir: definition.working_dir().display().to_string(),
+            services,
+            error,
+            created_at: definition.created_at,
+            updated_at: definition.updated_at,
+        })
+    }
+
+    /// 创建项目需要的网络和卷，已存在的跳过；外部网络和卷必须已经存在
+    pub async fn prepare(&self, project: &ComposeProject, networks: &NetworkManager, volumes: &VolumeManager) -> Result<()> {
+        for network in &project.networks {
+            match self.docker.inspect_network::<String>(&network.spec.name, None).await {
+                Ok(_) => continue,
+                Err(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. }) => {}
+                Err(e) => return Err(e.into()),
+            }
+            if network.external {
+                return Err(ApiError::invalid(format!("外部网络 {} 不存在", network.spec.name)).into());
+            }
+            networks.create_network(&network.spec).await?;
+        }
+
+        for volume in &project.volumes {
+            match self.docker.inspect_volume(&volume.spec.name).await {
+                Ok(_) => continue,
+                Err(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. }) => {}
+                Err(e) => return Err(e.into()),
+            }
+            if volume.external {
+                return Err(ApiError::invalid(format!("外部卷 {} 不存在", volume.spec.name)).into());
+            }
+            volumes.create_volume(&volume.spec).await?;
+        }
+        Ok(())
+    }
+
+    /// 删除项目创建的网络，`remove_volumes` 时同时删除项目创建的卷
+    pub async fn cleanup(&self, project: &ComposeProject, remove_volumes: bool) {
+        for network in project.networks.iter().filter(|n| !n.external) {
+            match self.docker.remove_network(&network.spec.name).await {
+                Ok(_) => println!("已删除网络: {}", network.spec.name),
+                Err(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. }) => {}
+                Err(e) => eprintln!("删除网络 {} 失败: {}", network.spec.name, e),
+            }
+        }
+        if !remove_volumes {
+            return;
+        }
+        for volume in project.volumes.iter().filter(|v| !v.external) {
+            match self.docker.remove_volume(&volume.spec.name, None).await {
+                Ok(_) => println!("已删除卷: {}", volume.spec.name),
+                Err(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. }) => {}
+                Err(e) => eprintln!("删除卷 {} 失败: {}", volume.spec.name, e),
+            }
+        }
+    }
+
+    /// 根据提交的内容生成项目定义；`existing` 为已保存的定义，未提交的字段沿用旧值。
+    /// 没有指定名称时依次使用 compose 文件中的 name 和文件所在目录名
+    pub fn build_definition(&self, name: &str, source: StackSource) -> Result<StackDefinition> {
+        let (content, path) = match (source.content, source.path) {
+            (Some(_), Some(_)) => return Err(ApiError::invalid("content 和 path 只能指定一个").into()),
+            (Some(content), None) => (Some(content), None),
+            (None, Some(path)) => {
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|e| ApiError::invalid(format!("读取 compose 文件 {} 失败: {}", path, e)))?;
+                (Some(content), Some(path))
+            }
+            (None, None) => (None, None),
+        };
+
+        let mut name = compose::normalize_project_name(name);
+        if name.is_empty() {
+            let content = content.as_deref()
+                .ok_or_else(|| ApiError::invalid("需要提供 compose 文件内容或路径"))?;
+            let draft = StackDefinition {
+                name: String::new(),
+                content: content.to_string(),
+                path: path.clone(),
+                working_dir: source.working_dir.clone().filter(|d| !d.is_empty()),
+                env: source.env.clone().unwrap_or_default(),
+                created_at: Local::now(),
+                updated_at: Local::now(),
+            };
+            name = compose::parse("", &draft.content, &draft.working_dir(), &draft.env)
+                .map(|p| p.name)
+                .ok()
+                .or_else(|| path.as_ref()
+                    .and_then(|p| Path::new(p).parent()?.file_name().map(|n| n.to_string_lossy().to_string()))
+                    .map(|n| compose::normalize_project_name(&n)))
+                .filter(|n| !n.is_empty())
+                .ok_or_else(|| ApiError::invalid("无法确定项目名称，请指定 name"))?;
+        }
+
+        // 按最终的项目名合并已保存的定义，保留创建时间和这次没有指定的设置
+        let now = Local::now();
+        let mut definition = self.get(&name).unwrap_or_else(|_| StackDefinition {
+            name: name.clone(),
+            content: String::new(),
+            path: None,
+            working_dir: None,
+            env: HashMap::new(),
+            created_at: now,
+            updated_at: now,
+        });
+        definition.updated_at = now;
+        match content {
+            Some(content) => {
+                definition.content = content;
+                definition.path = path;
+            }
+            None if definition.content.is_empty() => {
+                return Err(ApiError::invalid("需要提供 compose 文件内容或路径").into());
+            }
+            None => {}
+        }
+        if source.working_dir.is_some() {
+            definition.working_dir = source.working_dir.filter(|d| !d.is_empty());
+        }
+        if let Some(env) = source.env {
+            definition.env = env;
+        }
+
+        // 保存前先确认文件可以解析
+        definition.project()?;
+        Ok(definition)
+    }
+}
@@ -0,0 +1,183 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+use crate::maintenance::MaintenanceWindow;
+use crate::storage;
+
+/// 事件循环超过这么久没有心跳视为卡住，`/healthz` 返回失败
+pub const STALL_SECS: i64 = 120;
+
+// 影响管理器行为的设置文件，运行状态和历史记录不算在内
+const CONFIG_FILES: &[&str] = &[
+    crate::registry::STORE_FILE,
+    crate::health::SETTINGS_FILE,
+    crate::probes::PROBES_FILE,
+    crate::oom::SETTINGS_FILE,
+    crate::updater::SETTINGS_FILE,
+    crate::backups::SETTINGS_FILE,
+    crate::notify::SETTINGS_FILE,
+    crate::events::SETTINGS_FILE,
+    crate::exec::SETTINGS_FILE,
+    crate::files::SETTINGS_FILE,
+    crate::stacks::STACKS_FILE,
+    crate::scheduler::SCHEDULES_FILE,
+];
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamState {
+    Starting,
+    Connected,
+    Reconnecting,
+}
+
+/// Docker 事件流的状态
+#[derive(Clone, Debug, Serialize)]
+pub struct EventStreamStatus {
+    pub state: StreamState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connected_since: Option<DateTime<Local>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_event: Option<DateTime<Local>>,
+    /// 事件循环最近一次响应的时间，没有事件时也会定时更新
+    pub last_heartbeat: DateTime<Local>,
+    pub reconnects: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DockerStatus {
+    pub reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub profile: &'static str,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            profile: if cfg!(debug_assertions) { "debug" } else { "release" },
+        }
+    }
+}
+
+/// 管理器自身的状态
+#[derive(Clone, Debug, Serialize)]
+pub struct ManagerStatus {
+    /// 没有任何问题
+    pub healthy: bool,
+    /// 事件循环仍在运行，与 `/healthz` 一致
+    pub live: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+    pub build: BuildInfo,
+    pub started: DateTime<Local>,
+    pub uptime_secs: i64,
+    pub docker: DockerStatus,
+    pub event_stream: EventStreamStatus,
+    pub managed_containers: usize,
+    /// 生效中的维护窗口
+    pub maintenance: Vec<MaintenanceWindow>,
+    /// 设置文件内容的摘要，任何设置改变后都会变化
+    pub config_version: String,
+}
+
+/// 记录事件循环的运行情况
+pub struct StatusTracker {
+    started: DateTime<Local>,
+    stream: Mutex<EventStreamStatus>,
+}
+
+impl StatusTracker {
+    pub fn new() -> Self {
+        let now = Local::now();
+        Self {
+            started: now,
+            stream: Mutex::new(EventStreamStatus {
+                state: StreamState::Starting,
+                connected_since: None,
+                last_event: None,
+                last_heartbeat: now,
+                reconnects: 0,
+                last_error: None,
+            }),
+        }
+    }
+
+    pub fn started(&self) -> DateTime<Local> {
+        self.started
+    }
+
+    pub fn stream(&self) -> EventStreamStatus {
+        self.stream.lock().unwrap().clone()
+    }
+
+    pub fn connected(&self) {
+        let mut stream = self.stream.lock().unwrap();
+        let now = Local::now();
+        stream.state = StreamState::Connected;
+        stream.connected_since = Some(now);
+        stream.last_heartbeat = now;
+    }
+
+    pub fn event_received(&self) {
+        let mut stream = self.stream.lock().unwrap();
+        let now = Local::now();
+        stream.last_event = Some(now);
+        stream.last_heartbeat = now;
+    }
+
+    pub fn heartbeat(&self) {
+        self.stream.lock().unwrap().last_heartbeat = Local::now();
+    }
+
+    pub fn disconnected(&self, error: String) {
+        let mut stream = self.stream.lock().unwrap();
+        stream.state = StreamState::Reconnecting;
+        stream.connected_since = None;
+        stream.last_heartbeat = Local::now();
+        stream.reconnects += 1;
+        stream.last_error = Some(error);
+    }
+
+    /// 事件循环卡住时返回原因
+    pub fn stalled(&self) -> Option<String> {
+        let silent = (Local::now() - self.stream.lock().unwrap().last_heartbeat).num_seconds();
+        (silent > STALL_SECS).then(|| format!("事件循环已 {} 秒没有响应", silent))
+    }
+}
+
+impl Default for StatusTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 设置文件内容的 SHA-256 摘要（前 12 位）
+pub fn config_version() -> String {
+    let mut hasher = Sha256::new();
+    for name in CONFIG_FILES {
+        if let Ok(content) = std::fs::read(storage::data_path(name)) {
+            hasher.update(name.as_bytes());
+            hasher.update((content.len() as u64).to_le_bytes());
+            hasher.update(&content);
+        }
+    }
+    hasher.finalize().iter().take(6).map(|b| format!("{:02x}", b)).collect()
+}
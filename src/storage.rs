@@ -0,0 +1,76 @@
+use serde::{Serialize, de::DeserializeOwned};
+use std::fs;
+use std::path::PathBuf;
+use anyhow::Result;
+
+// 管理器自身的状态（凭据、计划任务等）都保存在数据目录下，
+// 可以通过 DOCKER_MANAGER_DATA_DIR 指定，默认是当前目录下的 data
+pub fn data_dir() -> PathBuf {
+    std::env::var("DOCKER_MANAGER_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("data"))
+}
+
+pub fn data_path(name: &str) -> PathBuf {
+    data_dir().join(name)
+}
+
+// 读取数据目录下的 JSON 文件，文件不存在时返回默认值
+pub fn load_json<T: DeserializeOwned + Default>(name: &str) -> Result<T> {
+    let path = data_path(name);
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let content = fs::read_to_string(&path)?;
+    serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("解析 {} 失败: {}", path.display(), e))
+}
+
+// 序列化为格式化的 JSON 写入数据目录
+pub fn save_json<T: Serialize>(name: &str, value: &T) -> Result<()> {
+    let content = serde_json::to_vec_pretty(value)?;
+    write_private(name, &content)
+}
+
+// 在文件末尾追加一行，用于只追加的日志
+pub fn append_line(name: &str, line: &str) -> Result<()> {
+    let path = data_path(name);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.append(true).create(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path)?;
+    std::io::Write::write_all(&mut file, format!("{}\n", line).as_bytes())?;
+    Ok(())
+}
+
+// 写入只有当前用户可读的文件，用于保存密钥和凭据。
+// 先写临时文件再重命名，避免进程中断时留下写了一半的文件
+pub fn write_private(name: &str, content: &[u8]) -> Result<()> {
+    let dir = data_dir();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(name);
+    let tmp = dir.join(format!(".{}.tmp", name));
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp)?;
+    std::io::Write::write_all(&mut file, content)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
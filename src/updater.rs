@@ -0,0 +1,179 @@
+use chrono::{DateTime, Local, NaiveTime};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use anyhow::Result;
+use crate::storage;
+
+pub(crate) const SETTINGS_FILE: &str = "updater.json";
+const HISTORY_LIMIT: usize = 200;
+
+/// 设置为 `true` 的容器才会被自动更新
+pub const LABEL_ENABLE: &str = "docker-manager.auto-update";
+/// 设置为 `true` 时只检查并通知，不重建容器
+pub const LABEL_MONITOR_ONLY: &str = "docker-manager.auto-update.monitor-only";
+
+/// 自动更新设置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdaterSettings {
+    pub enabled: bool,
+    /// 两次检查之间的间隔（秒）
+    pub interval_secs: u64,
+    /// 允许更新的时间窗口（本地时间），如 `02:00-05:00`，为空表示任何时间
+    pub windows: Vec<String>,
+    /// 只检查并通知，不重建容器
+    pub dry_run: bool,
+}
+
+impl Default for UpdaterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 3600,
+            windows: Vec::new(),
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateStatus {
+    /// 仓库中有新镜像，但处于只检查模式
+    Available,
+    Updated,
+    Failed,
+}
+
+/// 一次更新检查的结果
+#[derive(Clone, Debug, Serialize)]
+pub struct UpdateRecord {
+    pub container_id: String,
+    pub name: String,
+    pub image: String,
+    pub old_image_id: String,
+    pub new_image_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_container_id: Option<String>,
+    pub status: UpdateStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub timestamp: DateTime<Local>,
+}
+
+/// 自动更新的状态：设置、最近一次检查时间和更新历史
+pub struct ImageUpdater {
+    settings: Mutex<UpdaterSettings>,
+    last_run: Mutex<Option<DateTime<Local>>>,
+    history: Mutex<VecDeque<UpdateRecord>>,
+    // 防止定时检查和手动触发同时进行
+    pub(crate) run_lock: tokio::sync::Mutex<()>,
+}
+
+impl ImageUpdater {
+    pub fn load() -> Result<Self> {
+        let settings: UpdaterSettings = storage::load_json(SETTINGS_FILE)?;
+        validate_windows(&settings.windows)?;
+        Ok(Self {
+            settings: Mutex::new(settings),
+            last_run: Mutex::new(None),
+            history: Mutex::new(VecDeque::new()),
+            run_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn settings(&self) -> UpdaterSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update_settings(&self, settings: UpdaterSettings) -> Result<()> {
+        if settings.interval_secs < 60 {
+            return Err(anyhow::anyhow!("检查间隔不能小于 60 秒"));
+        }
+        validate_windows(&settings.windows)?;
+        storage::save_json(SETTINGS_FILE, &settings)?;
+        *self.settings.lock().unwrap() = settings;
+        println!("已更新自动更新设置");
+        Ok(())
+    }
+
+    pub fn history(&self) -> Vec<UpdateRecord> {
+        self.history.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    pub fn last_run(&self) -> Option<DateTime<Local>> {
+        *self.last_run.lock().unwrap()
+    }
+
+    pub(crate) fn record(&self, record: UpdateRecord) {
+        let mut history = self.history.lock().unwrap();
+        history.push_back(record);
+        while history.len() > HISTORY_LIMIT {
+            history.pop_front();
+        }
+    }
+
+    pub(crate) fn mark_run(&self, now: DateTime<Local>) {
+        *self.last_run.lock().unwrap() = Some(now);
+    }
+
+    /// 是否到了下一次定时检查的时间，并且处于允许的时间窗口内
+    pub fn is_due(&self, now: DateTime<Local>) -> bool {
+        let settings = self.settings();
+        if !settings.enabled {
+            return false;
+        }
+        let elapsed = self.last_run()
+            .map(|last| (now - last).num_seconds().max(0) as u64 >= settings.interval_secs)
+            .unwrap_or(true);
+        elapsed && in_windows(&settings.windows, now.time())
+    }
+}
+
+/// 容器是否通过标签开启了自动更新
+pub fn is_enabled_for(labels: Option<&HashMap<String, String>>) -> bool {
+    label_is_true(labels, LABEL_ENABLE)
+}
+
+pub fn is_monitor_only(labels: Option<&HashMap<String, String>>) -> bool {
+    label_is_true(labels, LABEL_MONITOR_ONLY)
+}
+
+fn label_is_true(labels: Option<&HashMap<String, String>>, key: &str) -> bool {
+    labels
+        .and_then(|l| l.get(key))
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(false)
+}
+
+fn parse_window(window: &str) -> Result<(NaiveTime, NaiveTime)> {
+    let (start, end) = window.split_once('-')
+        .ok_or_else(|| anyhow::anyhow!("时间窗口格式应为 HH:MM-HH:MM: {}", window))?;
+    let parse = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M")
+        .map_err(|_| anyhow::anyhow!("无效的时间: {}", t));
+    Ok((parse(start)?, parse(end)?))
+}
+
+fn validate_windows(windows: &[String]) -> Result<()> {
+    for window in windows {
+        parse_window(window)?;
+    }
+    Ok(())
+}
+
+// 结束时间早于开始时间的窗口视为跨越午夜，如 22:00-04:00
+fn in_windows(windows: &[String], time: NaiveTime) -> bool {
+    if windows.is_empty() {
+        return true;
+    }
+    windows.iter()
+        .filter_map(|w| parse_window(w).ok())
+        .any(|(start, end)| {
+            if start <= end {
+                time >= start && time < end
+            } else {
+                time >= start || time < end
+            }
+        })
+}
@@ -0,0 +1,203 @@
+use bollard::Docker;
+use bollard::container::ListContainersOptions;
+use bollard::models::{MountPointTypeEnum, Volume};
+use bollard::volume::{CreateVolumeOptions, ListVolumesOptions, RemoveVolumeOptions};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use anyhow::Result;
+
+/// 卷列表中的单个卷
+#[derive(Clone, Debug, Serialize)]
+pub struct VolumeInfo {
+    pub name: String,
+    pub driver: String,
+    pub mountpoint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    pub labels: HashMap<String, String>,
+    pub options: HashMap<String, String>,
+    /// 来自 `system df` 的占用空间（字节），驱动不支持时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<i64>,
+    pub containers: Vec<VolumeContainer>,
+    /// 保存的容器配置中引用了该卷，即使容器当前不存在
+    pub referenced: bool,
+    /// 没有任何容器挂载，也没有被保存的配置引用
+    pub dangling: bool,
+}
+
+/// 挂载某个卷的容器
+#[derive(Clone, Debug, Serialize)]
+pub struct VolumeContainer {
+    pub id: String,
+    pub name: String,
+    pub destination: String,
+    pub read_only: bool,
+    pub managed: bool,
+}
+
+/// 创建卷的参数
+#[derive(Clone, Debug, Deserialize)]
+pub struct VolumeSpec {
+    pub name: String,
+    #[serde(default = "default_driver")]
+    pub driver: String,
+    #[serde(default)]
+    pub driver_opts: HashMap<String, String>,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+fn default_driver() -> String {
+    "local".to_string()
+}
+
+/// 删除卷的结果
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum VolumeRemoveOutcome {
+    Removed,
+    InUse { containers: Vec<String> },
+}
+
+/// 管理器所依赖的卷：受管容器 ID，以及保存的配置中挂载的卷名
+#[derive(Clone, Debug, Default)]
+pub struct VolumeReferences {
+    pub managed_containers: HashSet<String>,
+    pub volume_names: HashSet<String>,
+}
+
+pub struct VolumeManager {
+    docker: Docker,
+}
+
+impl VolumeManager {
+    pub fn new(docker: Docker) -> Self {
+        Self { docker }
+    }
+
+    pub async fn list_volumes(&self, refs: &VolumeReferences) -> Result<Vec<VolumeInfo>> {
+        let response = self.docker.list_volumes(None::<ListVolumesOptions<String>>).await?;
+        let users = self.volume_users(refs).await?;
+        let sizes = self.volume_sizes().await;
+
+        let mut result: Vec<VolumeInfo> = response.volumes.unwrap_or_default()
+            .into_iter()
+            .map(|volume| Self::to_info(volume, &users, &sizes, refs))
+            .collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+
+    pub async fn inspect_volume(&self, name: &str, refs: &VolumeReferences) -> Result<VolumeInfo> {
+        let volume = self.docker.inspect_volume(name).await?;
+        let users = self.volume_users(refs).await?;
+        let sizes = self.volume_sizes().await;
+        Ok(Self::to_info(volume, &users, &sizes, refs))
+    }
+
+    pub async fn create_volume(&self, spec: &VolumeSpec) -> Result<String> {
+        let volume = self.docker.create_volume(CreateVolumeOptions {
+            name: spec.name.clone(),
+            driver: spec.driver.clone(),
+            driver_opts: spec.driver_opts.clone(),
+            labels: spec.labels.clone(),
+        }).await?;
+        println!("已创建卷: {}", volume.name);
+        Ok(volume.name)
+    }
+
+    /// 删除卷；只要还有容器挂载或保存的配置引用，就拒绝删除
+    pub async fn remove_volume(&self, name: &str, refs: &VolumeReferences) -> Result<VolumeRemoveOutcome> {
+        // 先确认卷存在，不存在时返回 Docker 的 404
+        self.docker.inspect_volume(name).await?;
+        let users = self.volume_users(refs).await?;
+
+        let mut names: Vec<String> = users.get(name)
+            .map(|containers| containers.iter().map(|c| c.name.clone()).collect())
+            .unwrap_or_default();
+        if names.is_empty() && refs.volume_names.contains(name) {
+            names.push("已保存的容器配置".to_string());
+        }
+        if !names.is_empty() {
+            println!("卷 {} 正在使用中，拒绝删除: {:?}", name, names);
+            return Ok(VolumeRemoveOutcome::InUse { containers: names });
+        }
+
+        self.docker.remove_volume(name, Some(RemoveVolumeOptions { force: false })).await?;
+        println!("已删除卷: {}", name);
+        Ok(VolumeRemoveOutcome::Removed)
+    }
+
+    fn to_info(
+        volume: Volume,
+        users: &HashMap<String, Vec<VolumeContainer>>,
+        sizes: &HashMap<String, i64>,
+        refs: &VolumeReferences,
+    ) -> VolumeInfo {
+        let containers = users.get(&volume.name).cloned().unwrap_or_default();
+        let referenced = refs.volume_names.contains(&volume.name);
+        VolumeInfo {
+            dangling: containers.is_empty() && !referenced,
+            // 驱动无法统计时 Docker 返回 -1
+            size: sizes.get(&volume.name).copied().filter(|size| *size >= 0),
+            created_at: volume.created_at.map(|t| t.to_string()),
+            name: volume.name,
+            driver: volume.driver,
+            mountpoint: volume.mountpoint,
+            labels: volume.labels,
+            options: volume.options,
+            containers,
+            referenced,
+        }
+    }
+
+    // `system df` 统计较慢且可能失败，失败时只是不显示大小
+    async fn volume_sizes(&self) -> HashMap<String, i64> {
+        match self.docker.df().await {
+            Ok(usage) => usage.volumes.unwrap_or_default()
+                .into_iter()
+                .filter_map(|v| v.usage_data.map(|u| (v.name, u.size)))
+                .collect(),
+            Err(e) => {
+                eprintln!("获取卷占用空间失败: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+
+    /// 按卷名汇总挂载它的容器
+    async fn volume_users(&self, refs: &VolumeReferences) -> Result<HashMap<String, Vec<VolumeContainer>>> {
+        let containers = self.docker.list_containers(Some(ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        })).await?;
+
+        let mut users: HashMap<String, Vec<VolumeContainer>> = HashMap::new();
+        for container in containers {
+            let Some(id) = container.id else {
+                continue;
+            };
+            let name = container.names.unwrap_or_default()
+                .first()
+                .map(|n| n.trim_start_matches('/').to_string())
+                .unwrap_or_default();
+            for mount in container.mounts.unwrap_or_default() {
+                if mount.typ != Some(MountPointTypeEnum::VOLUME) {
+                    continue;
+                }
+                let Some(volume) = mount.name else {
+                    continue;
+                };
+                users.entry(volume).or_default().push(VolumeContainer {
+                    id: id.clone(),
+                    name: name.clone(),
+                    destination: mount.destination.unwrap_or_default(),
+                    read_only: !mount.rw.unwrap_or(true),
+                    managed: refs.managed_containers.contains(&id),
+                });
+            }
+        }
+        Ok(users)
+    }
+}
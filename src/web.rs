@@ -1,15 +1,43 @@
 use axum::{
     Router,
     routing::{get, post, put, delete},
-    extract::State,
-    response::{Json, Html},
-    http::StatusCode,
+    body::Body,
+    extract::{ConnectInfo, MatchedPath, RawPathParams, Request, State, Query, ws::{Message, WebSocket, WebSocketUpgrade}},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Html, Response, sse::{Event, KeepAlive, Sse}},
+    http::{header, HeaderMap, Method, StatusCode},
 };
+use futures::{SinkExt, Stream, StreamExt};
 use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use bollard::container::Config;
 use bollard::models::HostConfig;
 use crate::monitor::ContainerMonitor;
+use crate::status::ManagerStatus;
+use crate::error::{ApiError, ApiErrorKind};
+use crate::models::{ContainerNetwork, MountSpec};
+use crate::images::{ImageInfo, PullError, PullErrorKind, PullPolicy, PullProgress, PruneReport, RemoveOutcome};
+use crate::registry::RegistryEntry;
+use crate::updater::{UpdateRecord, UpdaterSettings};
+use crate::networks::{NetworkAttachment, NetworkInfo, NetworkSpec};
+use crate::backups::{BackupInfo, BackupSettings, RestoreReport};
+use crate::export::ExportFormat;
+use crate::health::{ContainerHealth, HealthSettings};
+use crate::probes::{ProbeInfo, ProbeSpec};
+use crate::oom::{OomRecord, OomSettings};
+use crate::notify::{NotificationRecord, NotifySettings};
+use crate::audit::{AuditEntry, AuditQuery};
+use crate::files::{FileChange, FileListing, FileSettings, TransferFormat};
+use crate::snapshots::{CommitRequest, Snapshot};
+use crate::maintenance::{MaintenanceRequest, MaintenanceWindow};
+use crate::scheduler::{Schedule, ScheduleInfo, ScheduleRun};
+use crate::exec::{CommandOutput, CommandRequest, ExecAction, ExecSettings, Terminal, TerminalOptions};
+use crate::events::{EventPage, EventQuery, EventSettings};
+use crate::groups::{GroupInfo};
+use crate::stacks::{StackInfo, StackSource};
+use crate::volumes::{VolumeInfo, VolumeRemoveOutcome, VolumeSpec};
 
 #[derive(Serialize)]
 pub struct ContainerInfo {
@@ -17,16 +45,28 @@ pub struct ContainerInfo {
     pub name: String,
     pub image: String,
     pub status: String,
+    /// 健康检查状态，没有配置健康检查时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<ContainerHealth>,
     pub ports: Vec<String>,
-    pub mounts: Vec<String>,
+    pub mounts: Vec<MountSpec>,
     pub env: Vec<String>,
-    pub ip_address: Option<String>,
+    pub networks: Vec<ContainerNetwork>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub host_config: Option<HostConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub config: Option<Config<String>>,
 }
 
+// 审计时读取的请求内容上限，与 Json 提取器的默认上限一致
+const AUDIT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+// 请求内容按流处理的路由，审计时不读取内容，只记录大小
+const STREAMED_ROUTES: &[&str] = &["/api/container/:id/files/upload"];
+// 失败响应中保留的错误信息长度
+const AUDIT_ERROR_LIMIT: usize = 64 * 1024;
+// 反向代理认证后传入的用户名
+const USER_HEADERS: &[&str] = &["x-forwarded-user", "x-remote-user", "remote-user"];
+
 // 添加首页处理函数
 async fn index() -> Html<&'static str> {
     Html(include_str!("../static/index.html"))
@@ -41,6 +81,96 @@ pub async fn start_web_server(monitor: Arc<ContainerMonitor>) {
         .route("/api/container/:id", delete(delete_container))
         .route("/api/containers", post(create_container))
         .route("/api/container/:id", put(update_container))
+        .route("/api/images", get(list_images))
+        .route("/api/images/pull", post(pull_image))
+        .route("/api/images/prune", post(prune_images))
+        .route("/api/image/*id", delete(remove_image))
+        .route("/api/registries", get(list_registries))
+        .route("/api/registries", post(save_registry))
+        .route("/api/registry/:registry", delete(remove_registry))
+        .route("/api/networks", get(list_networks))
+        .route("/api/networks", post(create_network))
+        .route("/api/network/:id", get(get_network))
+        .route("/api/network/:id", delete(remove_network))
+        .route("/api/container/:id/networks", post(connect_network))
+        .route("/api/container/:id/networks/:network", delete(disconnect_network))
+        .route("/api/volumes", get(list_volumes))
+        .route("/api/volumes", post(create_volume))
+        .route("/api/volume/:name", get(get_volume))
+        .route("/api/volume/:name", delete(remove_volume))
+        .route("/api/volume/:name/backup", post(backup_volume))
+        .route("/api/container/:id/backup", post(backup_container))
+        .route("/api/backups", get(list_backups))
+        .route("/api/backups/settings", get(get_backup_settings))
+        .route("/api/backups/settings", put(update_backup_settings))
+        .route("/api/backup/:file/restore", post(restore_backup))
+        .route("/api/backup/:file", delete(remove_backup))
+        .route("/api/stacks", get(list_stacks))
+        .route("/api/stacks", post(deploy_stack))
+        .route("/api/stack/:name", get(get_stack))
+        .route("/api/stack/:name", put(update_stack))
+        .route("/api/stack/:name", delete(remove_stack))
+        .route("/api/stack/:name/up", post(stack_up))
+        .route("/api/stack/:name/down", post(stack_down))
+        .route("/api/stack/:name/restart", post(stack_restart))
+        .route("/api/stack/:name/services/:service/update", post(stack_update_service))
+        .route("/api/groups", get(list_groups))
+        .route("/api/group/:name", get(get_group))
+        .route("/api/group/:name/start", post(group_start))
+        .route("/api/group/:name/stop", post(group_stop))
+        .route("/api/group/:name/restart", post(group_restart))
+        .route("/api/container/:id/export", get(export_container))
+        .route("/api/export", get(export_all))
+        .route("/api/probes", get(list_probes))
+        .route("/api/container/:id/probe", get(get_probe))
+        .route("/api/container/:id/probe", put(set_probe))
+        .route("/api/container/:id/probe", delete(remove_probe))
+        .route("/api/oom/settings", get(get_oom_settings))
+        .route("/api/oom/settings", put(update_oom_settings))
+        .route("/api/oom/history", get(oom_history))
+        .route("/api/notifications/settings", get(get_notification_settings))
+        .route("/api/notifications/settings", put(update_notification_settings))
+        .route("/api/notifications/test", post(test_notification))
+        .route("/api/notifications/history", get(notification_history))
+        .route("/api/health/settings", get(get_health_settings))
+        .route("/api/health/settings", put(update_health_settings))
+        .route("/api/updater", get(get_updater))
+        .route("/api/updater", put(update_updater))
+        .route("/api/updater/history", get(updater_history))
+        .route("/api/updater/run", post(run_updater))
+        .route("/api/events/history", get(event_history))
+        .route("/api/events/settings", get(get_event_settings))
+        .route("/api/events/settings", put(update_event_settings))
+        .route("/api/container/:id/terminal", get(container_terminal))
+        .route("/api/container/:id/exec", post(run_container_command))
+        .route("/api/container/:id/exec/actions", get(list_exec_actions))
+        .route("/api/container/:id/files", get(list_files))
+        .route("/api/container/:id/files/download", get(download_files))
+        .route("/api/container/:id/files/upload", post(upload_files))
+        .route("/api/files/settings", get(get_file_settings))
+        .route("/api/files/settings", put(update_file_settings))
+        // 文件变更和快照
+        .route("/api/container/:id/changes", get(container_changes))
+        .route("/api/container/:id/commit", post(commit_container))
+        .route("/api/snapshots", get(list_snapshots))
+        .route("/api/snapshot/*image", get(get_snapshot))
+        .route("/api/status", get(get_status))
+        .route("/healthz", get(healthz))
+        .route("/api/maintenance", get(list_maintenance))
+        .route("/api/maintenance", post(start_maintenance))
+        .route("/api/maintenance/:id", delete(end_maintenance))
+        .route("/api/schedules", get(list_schedules))
+        .route("/api/schedules", post(create_schedule))
+        .route("/api/schedules/history", get(schedule_history))
+        .route("/api/schedule/:name", get(get_schedule))
+        .route("/api/schedule/:name", put(update_schedule))
+        .route("/api/schedule/:name", delete(remove_schedule))
+        .route("/api/schedule/:name/run", post(run_schedule))
+        .route("/api/exec/settings", get(get_exec_settings))
+        .route("/api/exec/settings", put(update_exec_settings))
+        .route("/api/audit", get(query_audit))
+        .route("/api/audit/export", get(export_audit))
+        .route_layer(middleware::from_fn_with_state(monitor.clone(), audit_request))
         .with_state(monitor);
 
     // 尝试不同的端口
@@ -49,7 +179,7 @@ pub async fn start_web_server(monitor: Arc<ContainerMonitor>) {
         match tokio::net::TcpListener::bind(&addr).await {
             Ok(listener) => {
                 println!("Web 服务器启动在 http://{}", addr);
-                axum::serve(listener, app).await.unwrap();
+                axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
                 break;
             }
             Err(e) => {
@@ -75,8 +205,8 @@ async fn list_containers(
     let mut containers = Vec::new();
     for config in container_data {
         // 获取容器状态
-        let status = monitor.get_container_status(&config.container_id).await
-            .unwrap_or_else(|_| "unknown".to_string());
+        let (status, health) = monitor.get_container_state(&config.container_id).await
+            .unwrap_or_else(|_| ("unknown".to_string(), None));
 
         // 从配置中提取端口映射
         let ports = config.host_config
@@ -98,13 +228,6 @@ async fn list_containers(
             })
             .unwrap_or_default();
 
-        // 从配置中提取挂载点
-        let mounts = config.host_config
-            .as_ref()
-            .and_then(|host_config| host_config.binds.as_ref())
-            .map(|binds| binds.clone())
-            .unwrap_or_default();
-
         // 从配置中提取环境变量
         let env = config.config
             .as_ref()
@@ -116,10 +239,11 @@ async fn list_containers(
             name: config.name,
             image: config.image,
             status,
+            health,
             ports,
-            mounts,
+            mounts: config.mounts,
             env,
-            ip_address: config.ip_address,
+            networks: config.networks,
             host_config: config.host_config,
             config: config.config,
         });
@@ -140,8 +264,8 @@ async fn get_container(
             .clone()
     };
 
-    let status = monitor.get_container_status(&config.container_id).await
-        .unwrap_or_else(|_| "unknown".to_string());
+    let (status, health) = monitor.get_container_state(&config.container_id).await
+        .unwrap_or_else(|_| ("unknown".to_string(), None));
 
     // 从配置中提取端口映射
     let ports = config.host_config
@@ -163,13 +287,6 @@ async fn get_container(
         })
         .unwrap_or_default();
 
-    // 从配置中提取挂载点
-    let mounts = config.host_config
-        .as_ref()
-        .and_then(|host_config| host_config.binds.as_ref())
-        .map(|binds| binds.clone())
-        .unwrap_or_default();
-
     // 从配置中提取环境变量
     let env = config.config
         .as_ref()
@@ -181,10 +298,11 @@ async fn get_container(
         name: config.name,
         image: config.image,
         status,
+        health,
         ports,
-        mounts,
+        mounts: config.mounts,
         env,
-        ip_address: config.ip_address,
+        networks: config.networks,
         host_config: config.host_config,
         config: config.config,
     }))
@@ -205,23 +323,54 @@ struct CreateContainerRequest {
     name: String,
     image: String,
     ports: Vec<String>,
-    mounts: Vec<String>,
+    mounts: Vec<MountInput>,
     env: Vec<String>,
+    #[serde(default)]
+    pull_policy: PullPolicy,
+}
+
+/// 挂载点既可以是结构化对象，也可以是 `-v` 或 `--mount` 风格的字符串
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MountInput {
+    Spec(MountSpec),
+    Text(String),
+}
+
+fn parse_mounts(mounts: &[MountInput]) -> Result<Vec<MountSpec>, StatusCode> {
+    mounts.iter()
+        .map(|mount| {
+            let spec = match mount {
+                MountInput::Spec(spec) => spec.clone(),
+                MountInput::Text(text) => text.parse::<MountSpec>().map_err(|e| {
+                    eprintln!("解析挂载点失败: {}", e);
+                    StatusCode::BAD_REQUEST
+                })?,
+            };
+            spec.check_bind_source().map_err(|e| {
+                eprintln!("解析挂载点失败: {}", e);
+                StatusCode::BAD_REQUEST
+            })?;
+            Ok(spec)
+        })
+        .collect()
 }
 
 #[axum::debug_handler]
 async fn create_container(
     State(monitor): State<Arc<ContainerMonitor>>,
     Json(payload): Json<CreateContainerRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mounts = parse_mounts(&payload.mounts).map_err(|code| (code, "无效的挂载点配置".to_string()))?;
     monitor.as_ref().create_container(
         &payload.name,
         &payload.image,
         &payload.ports,
-        &payload.mounts,
-        &payload.env
+        &mounts,
+        &payload.env,
+        payload.pull_policy,
     ).await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|e| (error_status(&e), e.to_string()))?;
     Ok(StatusCode::CREATED)
 }
 
@@ -230,15 +379,1507 @@ async fn update_container(
     State(monitor): State<Arc<ContainerMonitor>>,
     axum::extract::Path(id): axum::extract::Path<String>,
     Json(payload): Json<CreateContainerRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, (StatusCode, String)> {
+    let mounts = parse_mounts(&payload.mounts).map_err(|code| (code, "无效的挂载点配置".to_string()))?;
     monitor.as_ref().update_container(
         &id,
         &payload.name,
         &payload.image,
         &payload.ports,
-        &payload.mounts,
-        &payload.env
+        &mounts,
+        &payload.env,
+        payload.pull_policy,
     ).await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|e| (error_status(&e), e.to_string()))?;
+    Ok(StatusCode::OK)
+} 
+
+// 按错误类型映射 HTTP 状态码：业务错误按类别，镜像拉取问题按原因，Docker 错误沿用其状态码
+fn error_status(e: &anyhow::Error) -> StatusCode {
+    if let Some(error) = e.downcast_ref::<ApiError>() {
+        return match error.kind {
+            ApiErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ApiErrorKind::Invalid => StatusCode::BAD_REQUEST,
+            ApiErrorKind::Conflict => StatusCode::CONFLICT,
+            ApiErrorKind::Forbidden => StatusCode::FORBIDDEN,
+            ApiErrorKind::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiErrorKind::Busy => StatusCode::TOO_MANY_REQUESTS,
+        };
+    }
+    if let Some(error) = e.downcast_ref::<PullError>() {
+        return match error.kind {
+            PullErrorKind::NotFound | PullErrorKind::NotPresent => StatusCode::NOT_FOUND,
+            PullErrorKind::Unauthorized => StatusCode::UNAUTHORIZED,
+            PullErrorKind::Network => StatusCode::BAD_GATEWAY,
+            PullErrorKind::Other => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+    }
+    match e.downcast_ref::<bollard::errors::Error>() {
+        Some(bollard::errors::Error::DockerResponseServerError { status_code, .. }) => {
+            StatusCode::from_u16(*status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[axum::debug_handler]
+async fn list_images(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Result<Json<Vec<ImageInfo>>, StatusCode> {
+    monitor.list_images().await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("获取镜像列表失败: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[derive(Deserialize)]
+struct PullImageRequest {
+    image: String,
+}
+
+#[derive(Serialize)]
+struct PullFailure {
+    kind: PullErrorKind,
+    message: String,
+}
+
+// 拉取镜像，并通过 SSE 推送每层的进度，最后以 done 或 error 事件结束
+#[axum::debug_handler]
+async fn pull_image(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(payload): Json<PullImageRequest>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<PullProgress>();
+    let image = payload.image.clone();
+    let task = tokio::spawn(async move {
+        monitor.pull_image(&image, Some(&tx)).await
+    });
+
+    let progress = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|p| (Event::default().event("progress").json_data(p), rx))
+    });
+    let finish = futures::stream::once(async move {
+        match task.await {
+            Ok(Ok(())) => Ok(Event::default().event("done").data(payload.image)),
+            Ok(Err(e)) => Event::default().event("error").json_data(PullFailure {
+                kind: e.downcast_ref::<PullError>().map(|p| p.kind).unwrap_or(PullErrorKind::Other),
+                message: e.to_string(),
+            }),
+            Err(e) => Ok(Event::default().event("error").data(e.to_string())),
+        }
+    });
+
+    Sse::new(progress.chain(finish)).keep_alive(KeepAlive::default())
+}
+
+#[derive(Deserialize)]
+struct RemoveImageQuery {
+    #[serde(default)]
+    force: bool,
+}
+
+#[axum::debug_handler]
+async fn remove_image(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Query(query): Query<RemoveImageQuery>,
+) -> Result<(StatusCode, Json<RemoveOutcome>), StatusCode> {
+    let outcome = monitor.remove_image(&id, query.force).await
+        .map_err(|e| {
+            eprintln!("删除镜像 {} 失败: {}", id, e);
+            error_status(&e)
+        })?;
+    let status = match outcome {
+        RemoveOutcome::Removed { .. } => StatusCode::OK,
+        RemoveOutcome::InUse { .. } => StatusCode::CONFLICT,
+    };
+    Ok((status, Json(outcome)))
+}
+
+#[derive(Deserialize)]
+struct PruneImagesQuery {
+    #[serde(default)]
+    all: bool,
+}
+
+#[axum::debug_handler]
+async fn prune_images(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Query(query): Query<PruneImagesQuery>,
+) -> Result<Json<PruneReport>, StatusCode> {
+    monitor.prune_images(query.all).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("清理镜像失败: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[axum::debug_handler]
+async fn list_registries(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<Vec<RegistryEntry>> {
+    Json(monitor.registry_store().list())
+}
+
+#[derive(Deserialize)]
+struct SaveRegistryRequest {
+    registry: String,
+    username: String,
+    password: String,
+    #[serde(default)]
+    email: Option<String>,
+}
+
+#[axum::debug_handler]
+async fn save_registry(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(payload): Json<SaveRegistryRequest>,
+) -> Result<StatusCode, StatusCode> {
+    monitor.registry_store()
+        .save(&payload.registry, &payload.username, &payload.password, payload.email)
+        .map_err(|e| {
+            eprintln!("保存仓库凭据失败: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+    Ok(StatusCode::CREATED)
+}
+
+#[axum::debug_handler]
+async fn remove_registry(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(registry): axum::extract::Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    match monitor.registry_store().remove(&registry) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("删除仓库凭据失败: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct UpdaterStatus {
+    #[serde(flatten)]
+    settings: UpdaterSettings,
+    last_run: Option<chrono::DateTime<chrono::Local>>,
+}
+
+#[axum::debug_handler]
+async fn list_probes(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<Vec<ProbeInfo>> {
+    Json(monitor.list_probes())
+}
+
+#[axum::debug_handler]
+async fn get_probe(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<ProbeInfo>, (StatusCode, String)> {
+    monitor.get_probe(&id).await
+        .map(Json)
+        .map_err(|e| (error_status(&e), e.to_string()))
+}
+
+#[axum::debug_handler]
+async fn set_probe(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(spec): Json<ProbeSpec>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.set_probe(&id, spec).await
+        .map_err(|e| {
+            eprintln!("设置容器 {} 的存活探测失败: {}", id, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::OK)
+}
+
+#[axum::debug_handler]
+async fn remove_probe(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.remove_probe(&id).await
+        .map_err(|e| (error_status(&e), e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn get_health_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<HealthSettings> {
+    Json(monitor.health_settings())
+}
+
+#[axum::debug_handler]
+async fn update_health_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(settings): Json<HealthSettings>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.update_health_settings(settings)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(StatusCode::OK)
+}
+
+#[axum::debug_handler]
+async fn get_oom_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<OomSettings> {
+    Json(monitor.oom_settings())
+}
+
+#[axum::debug_handler]
+async fn update_oom_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(settings): Json<OomSettings>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.update_oom_settings(settings)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(StatusCode::OK)
+}
+
+#[axum::debug_handler]
+async fn oom_history(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<Vec<OomRecord>> {
+    Json(monitor.oom_history())
+}
+
+#[axum::debug_handler]
+async fn get_notification_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<NotifySettings> {
+    Json(monitor.notification_settings())
+}
+
+#[axum::debug_handler]
+async fn update_notification_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(settings): Json<NotifySettings>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.update_notification_settings(settings)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+struct TestNotificationQuery {
+    channel: String,
+}
+
+// 向指定通道发送测试通知，返回发送时的错误
+#[axum::debug_handler]
+async fn test_notification(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Query(query): Query<TestNotificationQuery>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let channel = monitor.notification_channel(&query.channel)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("通知通道 {} 不存在", query.channel)))?;
+    monitor.send_test_notification(&channel).await
+        .map_err(|e| {
+            eprintln!("发送测试通知失败: {}", e);
+            (StatusCode::BAD_GATEWAY, e.to_string())
+        })?;
+    Ok(StatusCode::OK)
+}
+
+#[axum::debug_handler]
+async fn notification_history(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<Vec<NotificationRecord>> {
+    Json(monitor.notification_history())
+}
+
+#[axum::debug_handler]
+async fn get_updater(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<UpdaterStatus> {
+    Json(UpdaterStatus {
+        settings: monitor.updater().settings(),
+        last_run: monitor.updater().last_run(),
+    })
+}
+
+#[axum::debug_handler]
+async fn update_updater(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(settings): Json<UpdaterSettings>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.updater().update_settings(settings)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(StatusCode::OK)
+}
+
+#[axum::debug_handler]
+async fn updater_history(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<Vec<UpdateRecord>> {
+    Json(monitor.updater().history())
+}
+
+#[derive(Deserialize)]
+struct RunUpdaterQuery {
+    dry_run: Option<bool>,
+}
+
+// 立即检查一次镜像更新，不受时间窗口限制
+#[axum::debug_handler]
+async fn run_updater(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Query(query): Query<RunUpdaterQuery>,
+) -> Result<Json<Vec<UpdateRecord>>, StatusCode> {
+    monitor.check_for_updates(query.dry_run).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("检查镜像更新失败: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[axum::debug_handler]
+async fn list_networks(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Result<Json<Vec<NetworkInfo>>, StatusCode> {
+    monitor.networks().list_networks(&monitor.managed_container_ids()).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("获取网络列表失败: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[axum::debug_handler]
+async fn get_network(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<NetworkInfo>, StatusCode> {
+    monitor.networks().inspect_network(&id, &monitor.managed_container_ids()).await
+        .map(Json)
+        .map_err(|e| error_status(&e))
+}
+
+#[derive(Serialize)]
+struct CreatedResponse {
+    id: String,
+}
+
+#[axum::debug_handler]
+async fn create_network(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(spec): Json<NetworkSpec>,
+) -> Result<(StatusCode, Json<CreatedResponse>), (StatusCode, String)> {
+    let id = monitor.networks().create_network(&spec).await
+        .map_err(|e| {
+            eprintln!("创建网络 {} 失败: {}", spec.name, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok((StatusCode::CREATED, Json(CreatedResponse { id })))
+}
+
+#[axum::debug_handler]
+async fn remove_network(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.networks().remove_network(&id).await
+        .map_err(|e| {
+            eprintln!("删除网络 {} 失败: {}", id, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn connect_network(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(attachment): Json<NetworkAttachment>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.connect_container_network(&id, &attachment).await
+        .map_err(|e| {
+            eprintln!("连接容器 {} 到网络 {} 失败: {}", id, attachment.network, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+struct DisconnectNetworkQuery {
+    #[serde(default)]
+    force: bool,
+}
+
+#[axum::debug_handler]
+async fn disconnect_network(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path((id, network)): axum::extract::Path<(String, String)>,
+    Query(query): Query<DisconnectNetworkQuery>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.disconnect_container_network(&id, &network, query.force).await
+        .map_err(|e| {
+            eprintln!("断开容器 {} 与网络 {} 失败: {}", id, network, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn list_volumes(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Result<Json<Vec<VolumeInfo>>, StatusCode> {
+    monitor.list_volumes().await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("获取卷列表失败: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[axum::debug_handler]
+async fn get_volume(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<Json<VolumeInfo>, StatusCode> {
+    monitor.inspect_volume(&name).await
+        .map(Json)
+        .map_err(|e| error_status(&e))
+}
+
+#[axum::debug_handler]
+async fn create_volume(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(spec): Json<VolumeSpec>,
+) -> Result<(StatusCode, Json<CreatedResponse>), (StatusCode, String)> {
+    let id = monitor.create_volume(&spec).await
+        .map_err(|e| {
+            eprintln!("创建卷 {} 失败: {}", spec.name, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok((StatusCode::CREATED, Json(CreatedResponse { id })))
+}
+
+#[axum::debug_handler]
+async fn remove_volume(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<(StatusCode, Json<VolumeRemoveOutcome>), StatusCode> {
+    let outcome = monitor.remove_volume(&name).await
+        .map_err(|e| {
+            eprintln!("删除卷 {} 失败: {}", name, e);
+            error_status(&e)
+        })?;
+    let status = match outcome {
+        VolumeRemoveOutcome::Removed => StatusCode::OK,
+        VolumeRemoveOutcome::InUse { .. } => StatusCode::CONFLICT,
+    };
+    Ok((status, Json(outcome)))
+}
+
+#[derive(Deserialize, Default)]
+struct BackupRequest {
+    #[serde(default)]
+    pause: bool,
+}
+
+#[axum::debug_handler]
+async fn backup_volume(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    payload: Option<Json<BackupRequest>>,
+) -> Result<Json<BackupInfo>, (StatusCode, String)> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    monitor.backup_volume(&name, payload.pause).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("备份卷 {} 失败: {}", name, e);
+            (error_status(&e), e.to_string())
+        })
+}
+
+#[axum::debug_handler]
+async fn backup_container(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    payload: Option<Json<BackupRequest>>,
+) -> Result<Json<Vec<BackupInfo>>, (StatusCode, String)> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    monitor.backup_container(&id, payload.pause).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("备份容器 {} 的卷失败: {}", id, e);
+            (error_status(&e), e.to_string())
+        })
+}
+
+#[derive(Deserialize)]
+struct ListBackupsQuery {
+    volume: Option<String>,
+}
+
+#[axum::debug_handler]
+async fn list_backups(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Query(query): Query<ListBackupsQuery>,
+) -> Result<Json<Vec<BackupInfo>>, StatusCode> {
+    monitor.list_backups(query.volume.as_deref())
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("获取备份列表失败: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[axum::debug_handler]
+async fn get_backup_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<BackupSettings> {
+    Json(monitor.backup_settings())
+}
+
+#[axum::debug_handler]
+async fn update_backup_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(settings): Json<BackupSettings>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.update_backup_settings(settings)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
     Ok(StatusCode::OK)
-} 
\ No newline at end of file
+}
+
+#[derive(Deserialize, Default)]
+struct RestoreRequest {
+    /// 恢复到的卷，为空时恢复到原来的卷
+    volume: Option<String>,
+    #[serde(default)]
+    replace: bool,
+}
+
+#[axum::debug_handler]
+async fn restore_backup(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(file): axum::extract::Path<String>,
+    payload: Option<Json<RestoreRequest>>,
+) -> Result<Json<RestoreReport>, (StatusCode, String)> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    monitor.restore_backup(&file, payload.volume.as_deref(), payload.replace).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("从备份 {} 恢复失败: {}", file, e);
+            (error_status(&e), e.to_string())
+        })
+}
+
+#[axum::debug_handler]
+async fn remove_backup(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(file): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.remove_backup(&file)
+        .map_err(|e| (error_status(&e), e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn list_stacks(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Result<Json<Vec<StackInfo>>, StatusCode> {
+    monitor.list_stacks().await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("获取项目列表失败: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[derive(Serialize)]
+struct StackDetail {
+    #[serde(flatten)]
+    info: StackInfo,
+    content: String,
+    env: HashMap<String, String>,
+}
+
+#[axum::debug_handler]
+async fn get_stack(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<Json<StackDetail>, (StatusCode, String)> {
+    let (definition, info) = monitor.stack_info(&name).await
+        .map_err(|e| (error_status(&e), e.to_string()))?;
+    Ok(Json(StackDetail {
+        info,
+        content: definition.content,
+        env: definition.env,
+    }))
+}
+
+#[derive(Deserialize)]
+struct DeployStackRequest {
+    #[serde(default)]
+    name: String,
+    #[serde(flatten)]
+    source: StackSource,
+    #[serde(default)]
+    pull_policy: PullPolicy,
+}
+
+#[axum::debug_handler]
+async fn deploy_stack(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(payload): Json<DeployStackRequest>,
+) -> Result<(StatusCode, Json<StackInfo>), (StatusCode, String)> {
+    let info = monitor.deploy_stack(&payload.name, payload.source, payload.pull_policy).await
+        .map_err(|e| {
+            eprintln!("部署项目失败: {}", e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok((StatusCode::CREATED, Json(info)))
+}
+
+#[derive(Deserialize)]
+struct UpdateStackRequest {
+    #[serde(flatten)]
+    source: StackSource,
+    #[serde(default)]
+    pull_policy: PullPolicy,
+}
+
+#[axum::debug_handler]
+async fn update_stack(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    Json(payload): Json<UpdateStackRequest>,
+) -> Result<Json<StackInfo>, (StatusCode, String)> {
+    // 只更新已有的项目，避免拼错名称时意外创建新项目
+    monitor.stack_info(&name).await
+        .map_err(|e| (error_status(&e), e.to_string()))?;
+    monitor.deploy_stack(&name, payload.source, payload.pull_policy).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("更新项目 {} 失败: {}", name, e);
+            (error_status(&e), e.to_string())
+        })
+}
+
+#[derive(Deserialize)]
+struct StackDownQuery {
+    #[serde(default)]
+    volumes: bool,
+}
+
+#[axum::debug_handler]
+async fn remove_stack(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    Query(query): Query<StackDownQuery>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.remove_stack(&name, query.volumes).await
+        .map_err(|e| {
+            eprintln!("删除项目 {} 失败: {}", name, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, Default)]
+struct StackActionRequest {
+    #[serde(default)]
+    pull_policy: Option<PullPolicy>,
+}
+
+#[axum::debug_handler]
+async fn stack_up(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    payload: Option<Json<StackActionRequest>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    monitor.stack_up(&name, payload.pull_policy.unwrap_or_default()).await
+        .map_err(|e| {
+            eprintln!("启动项目 {} 失败: {}", name, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::OK)
+}
+
+#[axum::debug_handler]
+async fn stack_down(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    Query(query): Query<StackDownQuery>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.stack_down(&name, query.volumes).await
+        .map_err(|e| {
+            eprintln!("停止项目 {} 失败: {}", name, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::OK)
+}
+
+#[axum::debug_handler]
+async fn stack_restart(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.stack_restart(&name).await
+        .map_err(|e| {
+            eprintln!("重启项目 {} 失败: {}", name, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::OK)
+}
+
+// 更新单个服务时默认总是拉取镜像
+#[axum::debug_handler]
+async fn stack_update_service(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path((name, service)): axum::extract::Path<(String, String)>,
+    payload: Option<Json<StackActionRequest>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    let policy = payload.pull_policy.unwrap_or(PullPolicy::Always);
+    monitor.stack_update_service(&name, &service, policy).await
+        .map_err(|e| {
+            eprintln!("更新项目 {} 的服务 {} 失败: {}", name, service, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::OK)
+}
+
+#[axum::debug_handler]
+async fn list_groups(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Result<Json<Vec<GroupInfo>>, StatusCode> {
+    monitor.list_groups().await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("获取分组列表失败: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[axum::debug_handler]
+async fn get_group(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<Json<GroupInfo>, (StatusCode, String)> {
+    monitor.group_info(&name).await
+        .map(Json)
+        .map_err(|e| (error_status(&e), e.to_string()))
+}
+
+#[axum::debug_handler]
+async fn group_start(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.group_start(&name).await
+        .map_err(|e| {
+            eprintln!("启动分组 {} 失败: {}", name, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::OK)
+}
+
+#[axum::debug_handler]
+async fn group_stop(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.group_stop(&name).await
+        .map_err(|e| {
+            eprintln!("停止分组 {} 失败: {}", name, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::OK)
+}
+
+#[axum::debug_handler]
+async fn group_restart(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.group_restart(&name).await
+        .map_err(|e| {
+            eprintln!("重启分组 {} 失败: {}", name, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+    /// 为 true 时不隐藏环境变量中的密码、令牌等敏感值
+    #[serde(default)]
+    show_secrets: bool,
+}
+
+#[axum::debug_handler]
+async fn export_container(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<String, (StatusCode, String)> {
+    monitor.export_container(&id, query.format, query.show_secrets).await
+        .map_err(|e| {
+            eprintln!("导出容器 {} 失败: {}", id, e);
+            (error_status(&e), e.to_string())
+        })
+}
+
+#[axum::debug_handler]
+async fn export_all(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Query(query): Query<ExportQuery>,
+) -> Result<String, (StatusCode, String)> {
+    monitor.export_all(query.format, query.show_secrets).await
+        .map_err(|e| {
+            eprintln!("导出容器失败: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })
+}
+
+#[axum::debug_handler]
+async fn event_history(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Query(query): Query<EventQuery>,
+) -> Result<Json<EventPage>, (StatusCode, String)> {
+    monitor.event_history(&query).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("查询事件历史失败: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })
+}
+
+#[axum::debug_handler]
+async fn get_event_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<EventSettings> {
+    Json(monitor.event_settings())
+}
+
+#[axum::debug_handler]
+async fn update_event_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(settings): Json<EventSettings>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.update_event_settings(settings)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(StatusCode::OK)
+}
+
+/// 文件操作的路径和格式
+#[derive(Deserialize)]
+struct FileQuery {
+    path: String,
+    #[serde(default)]
+    format: Option<TransferFormat>,
+}
+
+#[axum::debug_handler]
+async fn list_files(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Query(query): Query<FileQuery>,
+) -> Result<Json<FileListing>, (StatusCode, String)> {
+    monitor.files().list(&id, &query.path).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("列出容器 {} 的 {} 失败: {}", id, query.path, e);
+            (error_status(&e), e.to_string())
+        })
+}
+
+#[axum::debug_handler]
+async fn download_files(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Query(query): Query<FileQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let format = query.format.unwrap_or_default();
+    let download = monitor.files().download(&id, &query.path, format).await
+        .map_err(|e| {
+            eprintln!("下载容器 {} 的 {} 失败: {}", id, query.path, e);
+            (error_status(&e), e.to_string())
+        })?;
+
+    let content_type = match format {
+        TransferFormat::Tar => "application/x-tar",
+        TransferFormat::File => "application/octet-stream",
+    };
+    // 文件名可能包含非 ASCII 字符，同时提供 RFC 5987 编码的版本
+    let ascii: String = download.file_name.chars()
+        .map(|c| if c.is_ascii_graphic() && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+    let encoded: String = download.file_name.bytes()
+        .map(|b| if b.is_ascii_alphanumeric() || b"-._~".contains(&b) { (b as char).to_string() } else { format!("%{:02X}", b) })
+        .collect();
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", ascii, encoded));
+    if let Some(size) = download.size {
+        response = response.header(header::CONTENT_LENGTH, size);
+    }
+    response.body(Body::from_stream(download.stream))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// 上传的内容直接作为请求体，大小按设置限制
+#[axum::debug_handler]
+async fn upload_files(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Query(query): Query<FileQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let limit = monitor.files().settings().max_upload_size;
+    let format = query.format.unwrap_or_else(|| {
+        let tar = headers.get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("application/x-tar"));
+        if tar { TransferFormat::Tar } else { TransferFormat::File }
+    });
+    let data = axum::body::to_bytes(body, limit.try_into().unwrap_or(usize::MAX)).await
+        .map_err(|_| (StatusCode::PAYLOAD_TOO_LARGE, format!("上传内容超过 {} 字节的上限", limit)))?;
+    let size = data.len();
+    match monitor.files().upload(&id, &query.path, format, data.to_vec()).await {
+        Ok(path) => Ok(Json(serde_json::json!({ "path": path, "size": size }))),
+        Err(e) => {
+            eprintln!("上传文件到容器 {} 的 {} 失败: {}", id, query.path, e);
+            Err((error_status(&e), e.to_string()))
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn container_changes(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<Vec<FileChange>>, (StatusCode, String)> {
+    monitor.files().changes(&id).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("获取容器 {} 的文件变更失败: {}", id, e);
+            (error_status(&e), e.to_string())
+        })
+}
+
+#[axum::debug_handler]
+async fn commit_container(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(request): Json<CommitRequest>,
+) -> Result<Json<Snapshot>, (StatusCode, String)> {
+    monitor.commit_container(&id, &request).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("提交容器 {} 失败: {}", id, e);
+            (error_status(&e), e.to_string())
+        })
+}
+
+#[axum::debug_handler]
+async fn list_snapshots(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<Vec<Snapshot>> {
+    Json(monitor.snapshots().list())
+}
+
+#[axum::debug_handler]
+async fn get_snapshot(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(image): axum::extract::Path<String>,
+) -> Result<Json<Snapshot>, (StatusCode, String)> {
+    monitor.snapshots().find(&image)
+        .map(Json)
+        .map_err(|e| (error_status(&e), e.to_string()))
+}
+
+#[axum::debug_handler]
+async fn get_status(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<ManagerStatus> {
+    Json(monitor.status().await)
+}
+
+// 存活检查：只在事件循环卡住时失败，Docker 暂时不可用时管理器会自动重连
+#[axum::debug_handler]
+async fn healthz(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> (StatusCode, String) {
+    match monitor.liveness() {
+        Ok(()) => (StatusCode::OK, "ok".to_string()),
+        Err(reason) => (StatusCode::SERVICE_UNAVAILABLE, reason),
+    }
+}
+
+#[axum::debug_handler]
+async fn list_maintenance(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<Vec<MaintenanceWindow>> {
+    Json(monitor.maintenance_windows())
+}
+
+#[axum::debug_handler]
+async fn start_maintenance(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    headers: HeaderMap,
+    Json(request): Json<MaintenanceRequest>,
+) -> Result<(StatusCode, Json<MaintenanceWindow>), (StatusCode, String)> {
+    let window = monitor.start_maintenance(request, request_user(&headers)).await
+        .map_err(|e| {
+            eprintln!("开启维护模式失败: {}", e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok((StatusCode::CREATED, Json(window)))
+}
+
+#[axum::debug_handler]
+async fn end_maintenance(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<u64>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.end_maintenance(id)
+        .map_err(|e| (error_status(&e), e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn list_schedules(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<Vec<ScheduleInfo>> {
+    Json(monitor.schedules())
+}
+
+#[axum::debug_handler]
+async fn get_schedule(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<Json<ScheduleInfo>, (StatusCode, String)> {
+    monitor.schedule(&name)
+        .map(Json)
+        .map_err(|e| (error_status(&e), e.to_string()))
+}
+
+#[axum::debug_handler]
+async fn create_schedule(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    headers: HeaderMap,
+    Json(schedule): Json<Schedule>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.save_schedule(schedule, None, request_user(&headers))
+        .map_err(|e| {
+            eprintln!("创建计划任务失败: {}", e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::CREATED)
+}
+
+#[axum::debug_handler]
+async fn update_schedule(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    headers: HeaderMap,
+    Json(schedule): Json<Schedule>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.save_schedule(schedule, Some(&name), request_user(&headers))
+        .map_err(|e| {
+            eprintln!("更新计划任务 {} 失败: {}", name, e);
+            (error_status(&e), e.to_string())
+        })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn remove_schedule(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.remove_schedule(&name)
+        .map_err(|e| (error_status(&e), e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// 立即在后台执行一次，结果记录在执行历史中
+#[axum::debug_handler]
+async fn run_schedule(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.trigger_schedule(&name, request_user(&headers).as_deref())
+        .map_err(|e| (error_status(&e), e.to_string()))?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[derive(Deserialize)]
+struct ScheduleHistoryQuery {
+    schedule: Option<String>,
+    limit: Option<usize>,
+}
+
+#[axum::debug_handler]
+async fn schedule_history(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Query(query): Query<ScheduleHistoryQuery>,
+) -> Json<Vec<ScheduleRun>> {
+    Json(monitor.schedule_history(query.schedule.as_deref(), query.limit))
+}
+
+#[axum::debug_handler]
+async fn get_file_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+) -> Json<FileSettings> {
+    Json(monitor.files().settings())
+}
+
+#[axum::debug_handler]
+async fn update_file_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Json(settings): Json<FileSettings>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    monitor.files().update_settings(settings)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(StatusCode::OK)
+}
+
+// 为所有修改操作记录审计日志：操作者、路由、对象、隐藏敏感信息后的参数和结果
+async fn audit_request(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    connect: Option<ConnectInfo<SocketAddr>>,
+    matched: Option<MatchedPath>,
+    path_params: Option<RawPathParams>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(request).await;
+    }
+
+    let address = connect.map(|ConnectInfo(addr)| addr.ip().to_string());
+    let actor = request_user(request.headers())
+        .or_else(|| address.clone())
+        .unwrap_or_else(|| "unknown".to_string());
+    let route = matched.as_ref().map(|m| m.as_str()).unwrap_or(request.uri().path()).to_string();
+    let action = format!("{} {}", request.method(), route);
+
+    let (parts, body) = request.into_parts();
+    let streamed = matched.as_ref().is_some_and(|m| STREAMED_ROUTES.contains(&m.as_str()));
+    let (body, bytes) = if streamed {
+        (body, Default::default())
+    } else {
+        match axum::body::to_bytes(body, AUDIT_BODY_LIMIT).await {
+            Ok(bytes) => (Body::from(bytes.clone()), bytes),
+            Err(_) => return (StatusCode::PAYLOAD_TOO_LARGE, "请求内容过大").into_response(),
+        }
+    };
+
+    let mut params = serde_json::Map::new();
+    if let Ok(Query(query)) = Query::<HashMap<String, String>>::try_from_uri(&parts.uri) {
+        if !query.is_empty() {
+            params.insert("query".to_string(), serde_json::json!(query));
+        }
+    }
+    let body_json = serde_json::from_slice::<serde_json::Value>(&bytes).ok();
+    if let Some(body) = &body_json {
+        params.insert("body".to_string(), body.clone());
+    } else if !bytes.is_empty() {
+        params.insert("body_size".to_string(), serde_json::json!(bytes.len()));
+    } else if let Some(size) = parts.headers.get(header::CONTENT_LENGTH).and_then(|v| v.to_str().ok()) {
+        params.insert("body_size".to_string(), serde_json::json!(size.parse::<u64>().ok()));
+    }
+    let params = serde_json::Value::Object(params);
+
+    // 路径参数即操作对象；创建类请求用请求中的名称
+    let target = path_params
+        .map(|p| p.iter().map(|(_, value)| value.to_string()).collect::<Vec<_>>().join("/"))
+        .filter(|t| !t.is_empty())
+        .or_else(|| body_json.as_ref()
+            .and_then(|b| b.get("name").or_else(|| b.get("image")))
+            .and_then(|n| n.as_str())
+            .map(str::to_string));
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+    let status = response.status();
+    let (response, error) = if status.is_success() || status.is_redirection() {
+        (response, None)
+    } else {
+        let (parts, body) = response.into_parts();
+        let bytes = axum::body::to_bytes(body, AUDIT_ERROR_LIMIT).await.unwrap_or_default();
+        let error = String::from_utf8_lossy(&bytes).trim().to_string();
+        let error = if error.is_empty() {
+            status.canonical_reason().unwrap_or_default().to_string()
+        } else {
+            error
+        };
+        (Response::from_parts(parts, Body::from(bytes)), Some(error))
+    };
+
+    monitor.audit().record(AuditEntry::api(actor, address, action, target, params, status.as_u16(), error));
+    response
+}
+
+#[axum::debug_handler]
+async fn get_exec_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    headers: HeaderMap,
+) -> Result<Json<ExecSettings>, (StatusCode, String)> {
+    // 操作的命令和环境变量只给允许执行命令的用户看
+    monitor.exec().check_command_user(request_user(&headers).as_deref())
+        .map_err(|e| (error_status(&e), e.to_string()))?;
+    Ok(Json(monitor.exec().settings()))
+}
+
+#[axum::debug_handler]
+async fn update_exec_settings(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    headers: HeaderMap,
+    Json(settings): Json<ExecSettings>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    // 按修改前的允许列表检查，不在列表中的用户不能放开限制
+    monitor.exec().check_command_user(request_user(&headers).as_deref())
+        .map_err(|e| (error_status(&e), e.to_string()))?;
+    monitor.exec().update_settings(settings)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(StatusCode::OK)
+}
+
+#[axum::debug_handler]
+async fn run_container_command(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<CommandRequest>,
+) -> Result<Json<CommandOutput>, (StatusCode, String)> {
+    let user = request_user(&headers);
+    match monitor.exec().run_command(&id, &request, user.as_deref()).await {
+        Ok(output) => {
+            if output.timed_out {
+                eprintln!("在容器 {} 中执行命令超时: {:?}", id, output.command);
+            }
+            Ok(Json(output))
+        }
+        Err(e) => {
+            eprintln!("在容器 {} 中执行命令失败: {}", id, e);
+            Err((error_status(&e), e.to_string()))
+        }
+    }
+}
+
+#[axum::debug_handler]
+async fn list_exec_actions(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<Vec<ExecAction>>, (StatusCode, String)> {
+    monitor.exec().actions(&id).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("获取容器 {} 的操作失败: {}", id, e);
+            (error_status(&e), e.to_string())
+        })
+}
+
+/// 终端的控制消息；二进制消息直接作为输入
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TerminalMessage {
+    Input { data: String },
+    Resize { cols: u16, rows: u16 },
+}
+
+// 用于会话结束时写入审计日志
+struct TerminalSession {
+    actor: String,
+    address: Option<String>,
+    container: String,
+    params: serde_json::Value,
+}
+
+// 在容器中打开交互式终端：先创建 exec，成功后再升级为 WebSocket，失败时直接返回状态码
+#[axum::debug_handler]
+async fn container_terminal(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Query(options): Query<TerminalOptions>,
+    connect: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let address = connect.map(|ConnectInfo(addr)| addr.ip().to_string());
+    let user = request_user(&headers);
+    let actor = user.clone().or_else(|| address.clone()).unwrap_or_else(|| "unknown".to_string());
+    let action = "GET /api/container/:id/terminal".to_string();
+    let mut params = serde_json::json!({ "shell": options.shell, "user": options.user });
+
+    let opened = if !same_origin(&headers) {
+        Err((StatusCode::FORBIDDEN, "不允许跨站打开终端".to_string()))
+    } else {
+        match monitor.exec().authorize(user.as_deref()) {
+            Ok(()) => monitor.exec().open_terminal(&id, &options).await
+                .map_err(|e| (error_status(&e), e.to_string())),
+            Err(e) => Err((error_status(&e), e.to_string())),
+        }
+    };
+    let terminal = match opened {
+        Ok(terminal) => terminal,
+        Err((status, message)) => {
+            eprintln!("打开容器 {} 的终端失败: {}", id, message);
+            monitor.audit().record(AuditEntry::api(
+                actor, address, action, Some(id), params, status.as_u16(), Some(message.clone()),
+            ));
+            return (status, message).into_response();
+        }
+    };
+
+    params = serde_json::json!({ "shell": terminal.shell, "user": terminal.user, "exec_id": terminal.exec_id });
+    println!("{} 打开了容器 {} 的终端", actor, id);
+    monitor.audit().record(AuditEntry::api(
+        actor.clone(), address.clone(), action, Some(id.clone()), params.clone(),
+        StatusCode::SWITCHING_PROTOCOLS.as_u16(), None,
+    ));
+    let session = TerminalSession { actor, address, container: id, params };
+    ws.on_upgrade(move |socket| run_terminal(monitor, socket, terminal, session))
+}
+
+// 在 WebSocket 和 exec 的输入输出之间转发，直到任一端关闭或空闲超时
+async fn run_terminal(monitor: Arc<ContainerMonitor>, socket: WebSocket, terminal: Terminal, session: TerminalSession) {
+    use tokio::io::AsyncWriteExt;
+
+    let started = std::time::Instant::now();
+    let idle = std::time::Duration::from_secs(monitor.exec().settings().idle_timeout);
+    let (mut sender, mut receiver) = socket.split();
+    let Terminal { exec_id, mut output, mut input, .. } = terminal;
+    let mut deadline = tokio::time::Instant::now() + idle;
+    let mut reason = "closed";
+
+    loop {
+        tokio::select! {
+            chunk = output.next() => match chunk {
+                Some(Ok(chunk)) => {
+                    if sender.send(Message::Binary(chunk.into_bytes().to_vec())).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Err(e)) => {
+                    eprintln!("读取终端输出失败: {}", e);
+                    reason = "error";
+                    break;
+                }
+                None => {
+                    reason = "exited";
+                    break;
+                }
+            },
+            message = receiver.next() => {
+                let data = match message {
+                    Some(Ok(Message::Binary(data))) => data,
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str::<TerminalMessage>(&text) {
+                        Ok(TerminalMessage::Input { data }) => data.into_bytes(),
+                        Ok(TerminalMessage::Resize { cols, rows }) => {
+                            if let Err(e) = monitor.exec().resize(&exec_id, cols, rows).await {
+                                eprintln!("调整终端大小失败: {}", e);
+                            }
+                            continue;
+                        }
+                        Err(_) => continue,
+                    },
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                deadline = tokio::time::Instant::now() + idle;
+                if input.write_all(&data).await.is_err() {
+                    break;
+                }
+            },
+            _ = tokio::time::sleep_until(deadline) => {
+                reason = "idle_timeout";
+                let _ = sender.send(Message::Binary("\r\n终端空闲超时，已断开\r\n".as_bytes().to_vec())).await;
+                break;
+            }
+        }
+    }
+
+    // 关闭输入后 TTY 挂断，shell 随之退出
+    drop(input);
+    drop(output);
+    let exit_code = monitor.exec().exit_code(&exec_id).await;
+    let _ = sender.send(Message::Text(serde_json::json!({ "type": "exit", "code": exit_code }).to_string())).await;
+    let _ = sender.close().await;
+
+    println!("{} 关闭了容器 {} 的终端", session.actor, session.container);
+    let mut params = session.params;
+    if let serde_json::Value::Object(map) = &mut params {
+        map.insert("reason".to_string(), serde_json::json!(reason));
+        map.insert("duration_secs".to_string(), serde_json::json!(started.elapsed().as_secs()));
+        map.insert("exit_code".to_string(), serde_json::json!(exit_code));
+    }
+    monitor.audit().record(AuditEntry::api(
+        session.actor, session.address, "terminal_closed".to_string(), Some(session.container), params,
+        StatusCode::OK.as_u16(), None,
+    ));
+}
+
+// 浏览器允许跨站建立 WebSocket 连接，需要确认请求来自管理器自己的页面
+fn same_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|o| o.to_str().ok()) else {
+        // 非浏览器客户端不带 Origin
+        return true;
+    };
+    let origin = origin.split_once("://").map(|(_, host)| host).unwrap_or(origin);
+    [header::HOST.as_str(), "x-forwarded-host"].iter()
+        .filter_map(|name| headers.get(*name))
+        .filter_map(|value| value.to_str().ok())
+        .any(|host| host.split(',').any(|h| h.trim().eq_ignore_ascii_case(origin)))
+}
+
+// 管理器只监听本机地址，经反向代理访问时由代理传入已认证的用户名
+fn request_user(headers: &HeaderMap) -> Option<String> {
+    USER_HEADERS.iter()
+        .filter_map(|name| headers.get(*name))
+        .filter_map(|value| value.to_str().ok())
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+}
+
+#[axum::debug_handler]
+async fn query_audit(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, (StatusCode, String)> {
+    monitor.audit().query(&query).await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("查询审计日志失败: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })
+}
+
+// 按同样的条件导出为 JSONL 文件
+#[axum::debug_handler]
+async fn export_audit(
+    State(monitor): State<Arc<ContainerMonitor>>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let content = monitor.audit().export(&query).await
+        .map_err(|e| {
+            eprintln!("导出审计日志失败: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"audit.jsonl\""),
+        ],
+        content,
+    ).into_response())
+}